@@ -1,9 +1,11 @@
+import json
 from collections.abc import Iterator
-from typing import Any, Generic, List, Optional, TypeVar
+from typing import Any, Dict, Generic, List, Optional, TypeVar, Union
 from .algoforge import ProcPipeline as RustProcPipeline
-from dataclasses import dataclass
+from .algoforge import setup_logging as rust_setup_logging
+from dataclasses import dataclass, field
 
-__constructs__ = ["ProcessingRequest", "ResultItem", "ProcPipeline"]
+__constructs__ = ["ProcessingRequest", "ResultItem", "ProcPipeline", "setup_logging"]
 
 T = TypeVar("T")
 
@@ -11,13 +13,24 @@ T = TypeVar("T")
 @dataclass
 class ProcessingRequest:
     id: str
-    input: str
+    # Structured documents (for `JsonFields`) can be passed as dicts
+    input: Union[str, Dict[str, Any]]
+    # Arbitrary fields (company, year, country...) echoed
+    # on the `ResultItem` and readable by processors
+    metadata: Dict[str, Any] = field(default_factory=dict)
 
 
 @dataclass
 class ResultItem(Generic[T]):
     id: str
     content: Optional[T] = None
+    # Set (and `content` left empty) if processing failed
+    error: Optional[str] = None
+    # Document-level attributes recorded by processors
+    # e.g. {"language": "en", "language_confidence": 0.99}
+    attributes: Dict[str, Any] = field(default_factory=dict)
+    # Metadata of the corresponding `ProcessingRequest`
+    metadata: Dict[str, Any] = field(default_factory=dict)
 
 
 class ProcPipeline(Generic[T]):
@@ -62,15 +75,109 @@ class ProcPipeline(Generic[T]):
 
         self._pipeline.build_pipeline(inner_processors)
 
-    def process(self, requests: List[ProcessingRequest]) -> Iterator[ResultItem[T]]:
+    @classmethod
+    def from_config(
+        cls, config: Union[str, List[Dict[str, Any]], Dict[str, Any]]
+    ) -> "ProcPipeline[T]":
+        """
+        Build the pipeline from a config naming every processor
+        along with its parameters, either the path of a JSON file
+        or the config itself:
+        ```
+            >>> ProcPipeline.from_config([
+            ...     {"processor": "Tokenizer"},
+            ...     {"processor": "Stemmer", "language": "french"},
+            ... ])
+        ```
+        An object with a `"processors"` list of stages is accepted too.
+        See `describe_processors()` for the available processors.
+
+        Raises:
+            ValueError: If the config is invalid, or the
+                processors aren't chainable in the given order
+        """
+
+        pipeline = cls.__new__(cls)
+        pipeline._pipeline = RustProcPipeline.from_config(config)
+        pipeline._last_processor = None
+        return pipeline
+
+    @property
+    def _processor(self) -> Any:
+        """
+        Allows a pipeline to be nested as a single stage
+        in another pipeline, `FanOut` or `Router`
+        """
+        return self._pipeline
+
+    def stats(self) -> Dict[str, Any]:
+        """
+        Metrics collected since the pipeline was built or `reset_stats`:
+        - `stages`: per processor, `calls`, `errors`, `seconds` (summed
+          over every thread), `tokens_in` and `tokens_out` (list items,
+          or words for strings)
+        - `batches`, `documents`, `errors`, `bytes` (of the inputs),
+          `seconds` (wall time), `documents_per_second` and
+          `bytes_per_second` of the batches processed
+        """
+        return self._pipeline.stats()
+
+    def reset_stats(self) -> None:
+        self._pipeline.reset_stats()
+
+    def process(
+        self,
+        requests: List[ProcessingRequest],
+        chunk_size: int = 1,
+        split_size: Optional[int] = None,
+        timeout: Optional[float] = None,
+    ) -> Iterator[ResultItem[T]]:
         """
         Process documents through the pipeline.
 
         Args:
             requests: List of ProcessingRequest objects
+            chunk_size: Minimum number of documents processed by a single
+                task, worth raising for many short documents (e.g. tweets)
+            split_size: Documents longer than this many bytes are split at
+                blank lines into parts of about that size, processed in
+                parallel and reassembled in order. Lists are concatenated,
+                strings joined with the blank lines they were split at.
+                Attributes recorded differently for several parts are
+                averaged if numbers (weighted by the length of the parts),
+                otherwise take the value recorded for most of the document.
+                JSON documents are never split
+            timeout: Documents taking longer than this many seconds are
+                returned with `error` set instead of holding up the batch.
+                Processors can't be interrupted, so a document is given up
+                on once the processor running at the deadline returns
 
         Returns:
-            Iterator of ResultItems
+            Iterator of ResultItems. Processing stops once it is dropped
+            or its `cancel()` method is called
         """
-        req_tuples = [(req.id, req.input) for req in requests]
-        return self._pipeline.process(req_tuples)
+        req_tuples = [
+            (
+                req.id,
+                req.input if isinstance(req.input, str) else json.dumps(req.input),
+                req.metadata,
+            )
+            for req in requests
+        ]
+        return self._pipeline.process(
+            req_tuples, chunk_size=chunk_size, split_size=split_size, timeout=timeout
+        )
+
+
+def setup_logging(level: str = "INFO") -> None:
+    """
+    Forward algoforge diagnostics (failed documents, dictionary
+    reloads...) to python's `logging`, from `level` up.
+    Loggers are named after the Rust module, e.g. `algoforge.scheduling`,
+    and messages carry the request id and processor name:
+    `request{id=42}:processor{name=Lemmatizer}: Processor failed error=...`.
+    The same fields are set as a dict on the `algoforge` record attribute.
+
+    Calling it again changes the level.
+    """
+    rust_setup_logging(level)
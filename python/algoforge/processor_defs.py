@@ -1,41 +1,133 @@
-from typing import Any, Dict, List
+from typing import Any, Callable, Dict, List, Optional, Union
 from .algoforge import (
-    Tokenizer as RustTokenizer,
+    LanguageDetector as RustLanguageDetector,
     SpellingMapper as RustSpellingMapper,
+    PosTagger as RustPosTagger,
     Lemmatizer as RustLemmatizer,
-    ToLowerCase as RustToLowerCase,
-    PreProcessor as RustPreProcessor,
-    PostProcessor as RustPostProcessor,
-    PorterStemmer as RustPorterStemmer,
+    FanOut as RustFanOut,
+    Router as RustRouter,
+    JsonFields as RustJsonFields,
+    PyFunctionProcessor as RustPyFunctionProcessor,
+    Processor as RustProcessor,
+    describe_processors as rust_describe_processors,
 )
 
 __constructs__ = [
+    "LanguageDetector",
     "Tokenizer",
     "SpellingMapper",
+    "SpellingCorrector",
+    "PosTagger",
     "Lemmatizer",
     "ToLowerCase",
     "PreProcessor",
     "PostProcessor",
     "PorterStemmer",
+    "Stemmer",
+    "FanOut",
+    "Router",
+    "JsonFields",
+    "PyFunctionProcessor",
+    "Processor",
+    "describe_processors",
 ]
 
 __typings__ = [
     "TokenizerContent",
+    "LanguageDetectorContent",
     "SpellingMapperContent",
+    "SpellingCorrectorContent",
+    "PosTaggerContent",
     "LemmatizerContent",
     "ToLowerCaseContent",
     "PreProcessorContent",
     "PostProcessorContent",
     "PorterStemmerContent",
+    "StemmerContent",
+    "FanOutContent",
+    "RouterContent",
+    "JsonFieldsContent",
+    "PyFunctionProcessorContent",
+    "Branch",
+    "AnnotatedToken",
+    "AnnotatedContent",
+    "DictionaryIssue",
 ]
 
 TokenizerContent = List[str]
+# Same as the input of `LanguageDetector`
+LanguageDetectorContent = Any
 SpellingMapperContent = List[str]
+SpellingCorrectorContent = List[str]
 LemmatizerContent = List[str]
 ToLowerCaseContent = List[str]
 PreProcessorContent = str
 PostProcessorContent = Dict[str, Any]
 PorterStemmerContent = List[str]
+StemmerContent = List[str]
+# {"lemmas": [...], "stems": [...]}
+FanOutContent = Dict[str, Any]
+# Output of whichever route was taken
+RouterContent = Any
+# Input document, with processed fields replaced
+JsonFieldsContent = Dict[str, Any]
+# Whatever the wrapped function returns
+PyFunctionProcessorContent = Any
+# Either a `ProcPipeline` or a list of processors
+Branch = Union[Any, List[Any]]
+# {"text": "colour", "spelling": "rule", ...}
+AnnotatedToken = Dict[str, str]
+AnnotatedContent = List[AnnotatedToken]
+# {"line": 12, "kind": "duplicate", "message": "..."}
+# kind: "duplicate" | "empty" | "self_mapping" | "chain" | "cycle"
+DictionaryIssue = Dict[str, Any]
+# [{"text": "saw", "pos": "VERB"}, ...]
+PosTaggerContent = AnnotatedContent
+
+
+class LanguageDetector:
+    """
+    Detects the language of each document using character n-gram
+    profiles. The input is passed through unchanged, and the
+    ISO 639-1 code and confidence are recorded on the `ResultItem`:
+    `attributes["language"]`, `attributes["language_confidence"]`
+    """
+
+    def __init__(
+        self,
+        profiles_path: Optional[str] = None,
+        languages: Optional[List[str]] = None,
+        min_confidence: float = 0.0,
+    ):
+        """
+        Initialize `LanguageDetector` with the embedded profiles
+        (da, de, en, es, fr, id, it, nl, pl, pt, ru, sv, tr, zh)
+        or a profiles file created by `LanguageDetector.build_profiles`
+
+        `languages` restricts detection to a subset of the profiles.
+        Documents without letters, or detected with a confidence
+        below `min_confidence`, are recorded as `"und"` (undetermined)
+        """
+        self._processor = RustProcessor(
+            "LanguageDetector",
+            profiles_path=profiles_path,
+            languages=languages,
+            min_confidence=min_confidence,
+        )
+
+    @staticmethod
+    def build_profiles(samples: Dict[str, str], output_path: str):
+        """
+        Builds a profiles file from sample texts, given as
+        a mapping of ISO 639-1 code -> path to a text file
+        """
+        RustLanguageDetector.build_profiles(samples, output_path)
+
+    def languages(self) -> List[str]:
+        """
+        Returns the language codes the detector can choose from
+        """
+        return self._processor.languages()
 
 
 class Tokenizer:
@@ -44,7 +136,7 @@ class Tokenizer:
     """
 
     def __init__(self):
-        self._processor = RustTokenizer()
+        self._processor = RustProcessor("Tokenizer")
 
 
 class SpellingMapper:
@@ -54,7 +146,17 @@ class SpellingMapper:
     keys in the dictionary
     """
 
-    def __init__(self, spelling_map_path: str):
+    def __init__(
+        self,
+        spelling_map_path: str,
+        case_insensitive: bool = False,
+        direction: str = "us_to_uk",
+        rules: bool = False,
+        exceptions: Optional[List[str]] = None,
+        vocabulary_path: Optional[str] = None,
+        annotate: bool = False,
+        watch: bool = False,
+    ):
         """
         Initialize `SpellingMapper` with a list of processors.
         Spelling map should be a CSV file with the following format:
@@ -65,8 +167,231 @@ class SpellingMapper:
             >>> | flavour | flavor               |
             >>> | ...     | ...                  |
         ```
+
+        If `case_insensitive` is set, words are looked up regardless
+        of their casing and the original casing pattern is re-applied
+        to the replacement (Color -> Colour, COLOR -> COLOUR)
+
+        `direction` selects which column is replaced with which:
+        `"us_to_uk"` maps `alternative_spelling` -> `target` (default),
+        `"uk_to_us"` maps `target` -> `alternative_spelling`
+
+        If `rules` is set, words missing from the map are converted
+        using morphological rules (-ize -> -ise, -yze -> -yse, -or -> -our,
+        -l -> -ll, -ense -> -ence), unless their base form is listed in
+        `exceptions` (on top of the built-in list). If `vocabulary_path`
+        (a file with one word per line) is given, a conversion is only
+        accepted if the result is a known word, which is recommended as
+        the rules alone can't tell every word apart (e.g. `theorise` from
+        `sunrise`). Passing `exceptions` or `vocabulary_path` without
+        `rules` raises a `ValueError`.
+
+        If `annotate` is set, the output is a list of `AnnotatedToken`s
+        and replaced tokens are tagged with `"spelling": "map" | "rule"`
+
+        `spelling_map_path` may also be a dictionary created by
+        `SpellingMapper.compile`, which loads near-instantly
+
+        If `watch` is set, the spelling map is reloaded (see `reload`)
+        whenever its file changes
         """
-        self._processor = RustSpellingMapper(spelling_map_path)
+        self._processor = RustProcessor(
+            "SpellingMapper",
+            spelling_map_path=spelling_map_path,
+            case_insensitive=case_insensitive,
+            direction=direction,
+            rules=rules,
+            exceptions=exceptions or [],
+            vocabulary_path=vocabulary_path,
+            annotate=annotate,
+            watch=watch,
+        )
+
+    def reload(self) -> None:
+        """
+        Reloads the spelling map from its file, for every pipeline
+        using this mapper. Documents already being processed finish
+        with the previous version. If the file can't be loaded,
+        an error is raised and the previous version stays in use
+        """
+        self._processor.reload()
+
+    def conflicts(self) -> Dict[str, List[str]]:
+        """
+        Returns every word that has more than one counterpart
+        in the spelling map (i.e. the map can't be reversed one-to-one)
+        mapped to all of its counterparts, in file order
+        """
+        return self._processor.conflicts()
+
+    @staticmethod
+    def from_dict(
+        spelling_map: Dict[str, str],
+        case_insensitive: bool = False,
+        direction: str = "us_to_uk",
+        rules: bool = False,
+        exceptions: Optional[List[str]] = None,
+        vocabulary_path: Optional[str] = None,
+        annotate: bool = False,
+    ) -> "SpellingMapper":
+        """
+        Initialize `SpellingMapper` with an in-memory spelling map,
+        mapping `target` -> `alternative_spelling` like the rows of
+        the CSV (`{"colour": "color", ...}`), see `__init__` for the options
+        """
+        mapper = SpellingMapper.__new__(SpellingMapper)
+        mapper._processor = RustSpellingMapper.from_dict(
+            spelling_map,
+            case_insensitive=case_insensitive,
+            direction=direction,
+            rules=rules,
+            exceptions=exceptions or [],
+            vocabulary_path=vocabulary_path,
+            annotate=annotate,
+        )
+        return mapper
+
+    @staticmethod
+    def default(
+        case_insensitive: bool = False,
+        direction: str = "us_to_uk",
+        rules: bool = False,
+        exceptions: Optional[List[str]] = None,
+        vocabulary_path: Optional[str] = None,
+        annotate: bool = False,
+    ) -> "SpellingMapper":
+        """
+        Initialize `SpellingMapper` with the bundled US/UK spelling map
+        (`data/spelling_map.csv`), see `__init__` for the options.
+        Only available if built with the `bundled-data` feature,
+        which the published wheels are
+        """
+        mapper = SpellingMapper.__new__(SpellingMapper)
+        mapper._processor = RustProcessor(
+            "SpellingMapper",
+            spelling_map_path=None,
+            case_insensitive=case_insensitive,
+            direction=direction,
+            rules=rules,
+            exceptions=exceptions or [],
+            vocabulary_path=vocabulary_path,
+            annotate=annotate,
+        )
+        return mapper
+
+    @staticmethod
+    def validate(spelling_map_path: str) -> List[DictionaryIssue]:
+        """
+        Checks a spelling map CSV for duplicates, empty entries,
+        self-mappings and cycles (words converted back to
+        themselves), in line order.
+        Malformed rows raise an error with their line number
+        """
+        return RustSpellingMapper.validate(spelling_map_path)
+
+    @staticmethod
+    def compile(spelling_map_path: str, output_path: str):
+        """
+        Compiles a spelling map CSV into a memory-mapped dictionary,
+        shared between every process that loads it. Both directions
+        are compiled into the same file
+        """
+        RustSpellingMapper.compile(spelling_map_path, output_path)
+
+
+class SpellingCorrector:
+    """
+    Fuzzy spelling correction for noisy (e.g. OCR) input,
+    using a SymSpell-style deletion index.
+    Tokens are corrected to the closest word within
+    `max_edit_distance`, preferring more frequent words
+    """
+
+    def __init__(
+        self,
+        frequency_path: str,
+        max_edit_distance: int = 2,
+        prefix_length: int = 7,
+        min_length: int = 3,
+        lemmatizer: Optional["Lemmatizer"] = None,
+        annotate: bool = False,
+    ):
+        """
+        Initialize `SpellingCorrector` with a word frequency list.
+        Frequency list should be a CSV file with the following format:
+        ```
+            >>> | word   | frequency |
+            >>> | ----   | --------- |
+            >>> | child  | 5000      |
+            >>> | labour | 3000      |
+            >>> | ...    | ...       |
+        ```
+
+        Only the first `prefix_length` characters of each word are indexed.
+        Tokens shorter than `min_length` or containing non-alphabetic
+        characters are left alone, as are tokens that are valid lemmas
+        or derivatives in `lemmatizer` (if provided).
+
+        If `annotate` is set, the output is a list of `AnnotatedToken`s
+        and corrected tokens are tagged with `"corrected_from": <original>`
+        """
+        self._processor = RustProcessor(
+            "SpellingCorrector",
+            frequency_path=frequency_path,
+            max_edit_distance=max_edit_distance,
+            prefix_length=prefix_length,
+            min_length=min_length,
+            annotate=annotate,
+        )
+        if lemmatizer is not None:
+            self._processor.set_lemmatizer(lemmatizer._processor)
+
+
+class PosTagger:
+    """
+    Part-of-speech tagger using an averaged perceptron.
+    Tags every token with `"pos": <tag>`, where the tagset
+    is whatever the model was trained on (e.g. UPOS or Penn Treebank)
+    """
+
+    def __init__(self, model_path: str):
+        """
+        Initialize `PosTagger` with a model file
+        created by `PosTagger.train`
+        """
+        self._processor = RustProcessor("PosTagger", model_path=model_path)
+
+    @staticmethod
+    def train(
+        corpus_path: str,
+        model_path: Optional[str] = None,
+        iterations: int = 5,
+        word_column: int = 1,
+        tag_column: int = 3,
+    ) -> "PosTagger":
+        """
+        Trains a new tagger on a CoNLL-format corpus
+        (one token per line, blank lines between sentences).
+        Columns are counted from 0, the defaults read the
+        word form and UPOS tag from CoNLL-U files.
+
+        The model is saved to `model_path` if provided
+        """
+        tagger = PosTagger.__new__(PosTagger)
+        tagger._processor = RustPosTagger.train(
+            corpus_path,
+            model_path=model_path,
+            iterations=iterations,
+            word_column=word_column,
+            tag_column=tag_column,
+        )
+        return tagger
+
+    def save(self, model_path: str):
+        """
+        Saves the model to `model_path`
+        """
+        self._processor.save(model_path)
 
 
 class Lemmatizer:
@@ -77,7 +402,15 @@ class Lemmatizer:
     ASSUMES USAGE OF BRITISH ENGLISH
     """
 
-    def __init__(self, lemma_map_path: str):
+    def __init__(
+        self,
+        lemma_map_path: str,
+        case_insensitive: bool = False,
+        rules_fallback: bool = False,
+        porter_fallback: bool = False,
+        annotate: bool = False,
+        watch: bool = False,
+    ):
         """
         Initialize `Lemmatizer` with a list of processors.
         Lemma map should be a CSV file with the following format:
@@ -88,8 +421,119 @@ class Lemmatizer:
             >>> | run   | "runs, ran, running"              |
             >>> | ...   | ...                               |
         ```
+
+        If `case_insensitive` is set, words are looked up regardless
+        of their casing and the original casing pattern is re-applied
+        to the lemma (RUNNING -> RUN, Running -> Run)
+
+        Words found in neither column are left unchanged, unless:
+        - `rules_fallback` is set, in which case English inflectional
+          suffixes are stripped (subcontractors -> subcontractor) and the
+          result is accepted if it is a known lemma
+        - `porter_fallback` is set, in which case the Porter stemmer
+          is used as a last resort
+
+        If `annotate` is set, the output is a list of `AnnotatedToken`s
+        with every token tagged with the method that produced it:
+        `"lemma_source": "lemma" | "derivative" | "rule" | "porter" | "unknown"`
+
+        Tokens tagged as verbs by a preceding `PosTagger` prefer
+        the derivative reading of words that are also lemmas
+        (`saw` -> `see` as a verb, `saw` as a noun). That is the only
+        use of the tag: the lemma map has a single lemma per derivative,
+        so a derivative whose lemma depends on its POS
+        (`leaves` -> `leaf` or `leave`) always gets the same one
+
+        `lemma_map_path` may also be a dictionary created by
+        `Lemmatizer.compile`, which loads near-instantly
+
+        If `watch` is set, the lemma map is reloaded (see `reload`)
+        whenever its file changes
         """
-        self._processor = RustLemmatizer(lemma_map_path)
+        self._processor = RustProcessor(
+            "Lemmatizer",
+            lemma_map_path=lemma_map_path,
+            case_insensitive=case_insensitive,
+            rules_fallback=rules_fallback,
+            porter_fallback=porter_fallback,
+            annotate=annotate,
+            watch=watch,
+        )
+
+    def reload(self) -> None:
+        """
+        Reloads the lemma map from its file, for every pipeline
+        using this lemmatizer. Documents already being processed
+        finish with the previous version. If the file can't be
+        loaded, an error is raised and the previous version
+        stays in use
+        """
+        self._processor.reload()
+
+    @staticmethod
+    def from_dict(
+        lemma_map: Dict[str, List[str]],
+        case_insensitive: bool = False,
+        rules_fallback: bool = False,
+        porter_fallback: bool = False,
+        annotate: bool = False,
+    ) -> "Lemmatizer":
+        """
+        Initialize `Lemmatizer` with an in-memory lemma map,
+        mapping each lemma to its derivatives
+        (`{"be": ["is", "was"], ...}`), see `__init__` for the options
+        """
+        lemmatizer = Lemmatizer.__new__(Lemmatizer)
+        lemmatizer._processor = RustLemmatizer.from_dict(
+            lemma_map,
+            case_insensitive=case_insensitive,
+            rules_fallback=rules_fallback,
+            porter_fallback=porter_fallback,
+            annotate=annotate,
+        )
+        return lemmatizer
+
+    @staticmethod
+    def default(
+        case_insensitive: bool = False,
+        rules_fallback: bool = False,
+        porter_fallback: bool = False,
+        annotate: bool = False,
+    ) -> "Lemmatizer":
+        """
+        Initialize `Lemmatizer` with the bundled English lemma database
+        (`data/lemma_map.csv`), see `__init__` for the options.
+        Only available if built with the `bundled-data` feature,
+        which the published wheels are
+        """
+        lemmatizer = Lemmatizer.__new__(Lemmatizer)
+        lemmatizer._processor = RustProcessor(
+            "Lemmatizer",
+            lemma_map_path=None,
+            case_insensitive=case_insensitive,
+            rules_fallback=rules_fallback,
+            porter_fallback=porter_fallback,
+            annotate=annotate,
+        )
+        return lemmatizer
+
+    @staticmethod
+    def validate(lemma_map_path: str) -> List[DictionaryIssue]:
+        """
+        Checks a lemma map CSV for duplicates, empty entries,
+        self-mappings and chains (a lemma that is itself a
+        derivative of another lemma), in line order.
+        Malformed rows raise an error with their line number
+        """
+        return RustLemmatizer.validate(lemma_map_path)
+
+    @staticmethod
+    def compile(lemma_map_path: str, output_path: str):
+        """
+        Compiles a lemma map CSV into a memory-mapped dictionary,
+        shared between every process that loads it
+        """
+        RustLemmatizer.compile(lemma_map_path, output_path)
 
 
 class ToLowerCase:
@@ -101,7 +545,7 @@ class ToLowerCase:
         """
         Initialize `ToLowerCase` with a list of processors.
         """
-        self._processor = RustToLowerCase()
+        self._processor = RustProcessor("ToLowerCase")
 
 
 class PreProcessor:
@@ -115,7 +559,7 @@ class PreProcessor:
         """
         Initialize `PreProcessor` with a list of processors.
         """
-        self._processor = RustPreProcessor()
+        self._processor = RustProcessor("PreProcessor")
 
 
 class PostProcessor:
@@ -129,7 +573,7 @@ class PostProcessor:
         """
         Initialize `PostProcessor` with a list of processors.
         """
-        self._processor = RustPostProcessor()
+        self._processor = RustProcessor("PostProcessor")
 
 
 class PorterStemmer:
@@ -143,4 +587,183 @@ class PorterStemmer:
         """
         Initialize `PorterStemmer` with a list of processors.
         """
-        self._processor = RustPorterStemmer()
+        self._processor = RustProcessor("PorterStemmer")
+
+
+class Stemmer:
+    """
+    Reduces words to their stem using one of several algorithms:
+    - `"porter"`: the original Porter algorithm (English only)
+    - `"porter2"`: Snowball stemmers (Porter2 for English),
+      available for most major European languages
+    - `"lancaster"`: the more aggressive Paice/Husk stemmer (English only)
+    """
+
+    def __init__(self, algorithm: str = "porter2", language: str = "english"):
+        """
+        Initialize `Stemmer` with an algorithm and a language.
+        `language` is either a language name (e.g. `"french"`)
+        or an ISO 639-1 code (e.g. `"fr"`)
+        """
+        self._processor = RustProcessor("Stemmer", algorithm=algorithm, language=language)
+
+
+def _unwrap_branch(branch: Branch) -> Any:
+    if isinstance(branch, list):
+        if not branch:
+            raise ValueError("No processors provided for branch")
+        return [getattr(processor, "_processor", processor) for processor in branch]
+    return getattr(branch, "_processor", branch)
+
+
+class FanOut:
+    """
+    Runs several sub-pipelines on the same input.
+    Outputs a dictionary with the output of every branch
+    keyed by the branch name, e.g. `{"lemmas": [...], "stems": [...]}`
+
+    Branches don't see the attributes recorded by the other branches,
+    those are merged onto the `ResultItem` once every branch has run
+    """
+
+    def __init__(self, branches: Dict[str, Branch]):
+        """
+        Initialize `FanOut` with a mapping of branch name
+        to either a `ProcPipeline` or a list of processors
+        """
+        self._processor = RustFanOut(
+            {name: _unwrap_branch(branch) for name, branch in branches.items()}
+        )
+
+
+class Router:
+    """
+    Picks a sub-pipeline based on a document attribute
+    recorded by an earlier processor, e.g. `"language"`
+    from `LanguageDetector`, or on a `ProcessingRequest`
+    metadata field if no processor recorded one:
+    ```
+        >>> Router("language", {
+        ...     "en": [SpellingMapper(...), Lemmatizer(...)],
+        ...     "fr": [Stemmer(language="french")],
+        ... })
+    ```
+    """
+
+    def __init__(
+        self,
+        attribute: str,
+        routes: Dict[str, Branch],
+        default: Optional[Branch] = None,
+    ):
+        """
+        Initialize `Router` with the attribute to route on and
+        a mapping of attribute value to either a `ProcPipeline`
+        or a list of processors.
+
+        Documents without a matching route go through `default`,
+        or are passed through unchanged if there is none.
+        The output of the route is passed on to the next processor,
+        so routes should produce the same type if more stages follow
+        """
+        self._processor = RustRouter(
+            attribute,
+            {key: _unwrap_branch(route) for key, route in routes.items()},
+            default=_unwrap_branch(default) if default is not None else None,
+        )
+
+
+class JsonFields:
+    """
+    Processes selected fields of structured JSON documents.
+    Runs a sub-pipeline on every string field matching one of
+    the field paths, and returns the document with each of those
+    fields replaced by the output of the sub-pipeline:
+    ```
+        >>> JsonFields(["title", "sections.*.body"], [Tokenizer(), ToLowerCase()])
+        >>> # {"title": "Our Policy", "sections": [{"body": "Forced Labour"}]}
+        >>> # -> {"title": ["our", "policy"], "sections": [{"body": ["forced", "labour"]}]}
+    ```
+    """
+
+    def __init__(self, fields: List[str], processors: Branch):
+        """
+        Initialize `JsonFields` with dotted field paths and either
+        a `ProcPipeline` or a list of processors.
+        Paths may contain array indices (`sections.0.body`) and
+        wildcards (`sections.*.body`). Missing fields and
+        non-string values are left untouched.
+
+        The request input should be a JSON object
+        (a `dict` or a JSON string)
+        """
+        self._processor = RustJsonFields(fields, _unwrap_branch(processors))
+
+
+class PyFunctionProcessor:
+    """
+    Wraps a python function as a pipeline stage, to prototype
+    a step alongside the Rust processors before porting it:
+    ```
+        >>> PyFunctionProcessor(lambda words: [w for w in words if len(w) > 2])
+    ```
+    The GIL is only held while the function runs, so the other
+    stages keep processing documents in parallel
+    """
+
+    def __init__(
+        self,
+        function: Callable[[Any], Any],
+        input: str = "list[str]",
+        output: str = "list[str]",
+    ):
+        """
+        Initialize `PyFunctionProcessor` with a function taking
+        the output of the previous stage and returning the input
+        of the next one, declared as one of:
+        - `"str"`: e.g. after `PreProcessor`
+        - `"list[str]"`: e.g. after `Tokenizer`
+        - `"list[dict]"`: annotated tokens (`AnnotatedContent`), input only
+        - `"dict"`: e.g. the output of `FanOut`
+        - `"any"`: anything as input, any JSON-like value as output
+
+        If the function raises, the document's `ResultItem`
+        has no content and the exception in `error`
+        """
+        self._processor = RustPyFunctionProcessor(function, input=input, output=output)
+
+
+class Processor:
+    """
+    Any registered processor, constructed by name with the
+    same parameters as in a pipeline config:
+    ```
+        >>> Processor("Stemmer", language="french")
+        >>> Processor("FanOut", branches={"tokens": [{"processor": "Tokenizer"}]})
+    ```
+    Sub-pipelines are given as configs (lists of stages)
+    """
+
+    def __init__(self, name: str, **params: Any):
+        """
+        Initialize `Processor` with the name of a processor
+        and its parameters, see `describe_processors()`
+
+        Raises:
+            ValueError: If the processor is unknown or
+                a parameter is missing or invalid
+        """
+        self._processor = RustProcessor(name, **params)
+
+    @property
+    def name(self) -> str:
+        return self._processor.name
+
+
+def describe_processors() -> List[Dict[str, Any]]:
+    """
+    Describes every processor that can be constructed by name,
+    with its parameters (name, type, default, description)
+    and the types of input it accepts
+    """
+    return rust_describe_processors()
@@ -0,0 +1,583 @@
+//! Serves pipelines built from config files over HTTP, for tools
+//! that can't use the python bindings
+//!
+//! ```text
+//! algoforge-server [--bind 127.0.0.1:8080] [--max-body BYTES] [NAME=]CONFIG...
+//! ```
+//!
+//! A pipeline is named after `NAME=` if given, otherwise the `"name"`
+//! of its config, otherwise the config file name without extension
+//!
+//! - `GET /health`
+//! - `GET /processors`: every processor that can be used in a config
+//! - `GET /pipelines`, `GET /pipelines/{name}`: the loaded configs,
+//!   along with the per-stage stats of the latter
+//! - `POST /pipelines/{name}/process`: a JSON request, a JSON list of
+//!   requests, or NDJSON (`Content-Type: application/x-ndjson`), where
+//!   a request is `{"id": "1", "input": "...", "metadata": {...}}`.
+//!   Results are streamed back as NDJSON as they finish processing
+//!
+//! NDJSON requests are processed as their lines arrive, so a batch
+//! isn't bounded by `--max-body`, only each of its lines is. An invalid
+//! line ends the batch with a last `{"error": "Line 2: ..."}` line
+
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use algoforge::{process_stream, registry::Registry, LibError, Pipeline, ProcessingRequest};
+use axum::{
+    body::{Body, Bytes},
+    extract::{self, DefaultBodyLimit, FromRequest, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{future, stream, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+const DEFAULT_BIND: &str = "127.0.0.1:8080";
+/// Largest JSON body, or NDJSON line, accepted by default
+const DEFAULT_MAX_BODY: usize = 16 * 1024 * 1024;
+const NDJSON: &str = "application/x-ndjson";
+
+struct LoadedPipeline {
+    pipeline: Arc<Pipeline>,
+    config: Value,
+}
+
+type Pipelines = Arc<BTreeMap<String, LoadedPipeline>>;
+
+/// Error responses are `{"error": "..."}`
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({"error": self.1}))).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct RequestBody {
+    id: Value,
+    /// Structured documents (for `JsonFields`) can be passed as objects
+    input: Value,
+    #[serde(default)]
+    metadata: Map<String, Value>,
+}
+
+impl From<RequestBody> for ProcessingRequest {
+    fn from(body: RequestBody) -> Self {
+        let to_string = |value: Value| match value {
+            Value::String(s) => s,
+            value => value.to_string(),
+        };
+
+        ProcessingRequest {
+            id: to_string(body.id),
+            input: to_string(body.input),
+            metadata: body.metadata,
+        }
+    }
+}
+
+/// Loads a `[NAME=]CONFIG` argument
+fn load_pipeline(arg: &str) -> Result<(String, LoadedPipeline), LibError> {
+    let (name, path) = match arg.split_once('=') {
+        Some((name, path)) => (Some(name.to_string()), Path::new(path)),
+        None => (None, Path::new(arg)),
+    };
+
+    let config = std::fs::read_to_string(path)
+        .map_err(|e| LibError::IO(format!("Failed to read {}: {}", path.display(), e)))?;
+    let config: Value = serde_json::from_str(&config)
+        .map_err(|e| LibError::Json(format!("Invalid config {}: {}", path.display(), e)))?;
+    let pipeline = Registry::global().build_pipeline(&config)?;
+
+    let name = name
+        .or_else(|| config["name"].as_str().map(str::to_string))
+        .or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| arg.to_string());
+
+    Ok((
+        name,
+        LoadedPipeline {
+            pipeline: Arc::new(pipeline),
+            config,
+        },
+    ))
+}
+
+fn is_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("ndjson"))
+}
+
+fn parse_json_requests(body: &[u8]) -> Result<Vec<ProcessingRequest>, ApiError> {
+    let bad_request = |message: String| ApiError(StatusCode::BAD_REQUEST, message);
+
+    let requests: Vec<RequestBody> =
+        match serde_json::from_slice(body).map_err(|e| bad_request(e.to_string()))? {
+            Value::Array(requests) => requests
+                .into_iter()
+                .enumerate()
+                .map(|(index, request)| {
+                    serde_json::from_value(request)
+                        .map_err(|e| bad_request(format!("Request {}: {}", index + 1, e)))
+                })
+                .collect::<Result<_, _>>()?,
+            request => {
+                vec![serde_json::from_value(request).map_err(|e| bad_request(e.to_string()))?]
+            }
+        };
+
+    Ok(requests.into_iter().map(ProcessingRequest::from).collect())
+}
+
+/// Lines of an NDJSON body not read yet
+struct NdjsonLines {
+    chunks: axum::body::BodyDataStream,
+    buffer: Vec<u8>,
+    /// Number of the last line taken from `buffer`
+    line: usize,
+    ended: bool,
+}
+
+impl NdjsonLines {
+    /// Next complete line, or what's left of the body once it ended
+    fn take_line(&mut self) -> Option<Vec<u8>> {
+        match self.buffer.iter().position(|&byte| byte == b'\n') {
+            Some(end) => Some(self.buffer.drain(..=end).collect()),
+            None if self.ended && !self.buffer.is_empty() => Some(std::mem::take(&mut self.buffer)),
+            None => None,
+        }
+    }
+}
+
+/// Requests of an NDJSON body, parsed as its lines arrive
+/// The first invalid line ends the requests, and is reported in `error`
+fn ndjson_requests(
+    body: Body,
+    max_line: usize,
+    error: Arc<Mutex<Option<String>>>,
+) -> impl Stream<Item = ProcessingRequest> {
+    let lines = NdjsonLines {
+        chunks: body.into_data_stream(),
+        buffer: Vec::new(),
+        line: 0,
+        ended: false,
+    };
+
+    stream::unfold(lines, move |mut lines| {
+        let error = error.clone();
+        async move {
+            let fail = |message: String| {
+                *error.lock().unwrap() = Some(message);
+                None
+            };
+
+            loop {
+                if let Some(line) = lines.take_line() {
+                    lines.line += 1;
+                    if line.len() > max_line {
+                        return fail(format!(
+                            "Line {}: longer than {} bytes",
+                            lines.line, max_line
+                        ));
+                    }
+                    if line.trim_ascii().is_empty() {
+                        continue;
+                    }
+                    return match serde_json::from_slice::<RequestBody>(&line) {
+                        Ok(request) => Some((request.into(), lines)),
+                        Err(e) => fail(format!("Line {}: {}", lines.line, e)),
+                    };
+                }
+                if lines.ended {
+                    return None;
+                }
+                if lines.buffer.len() > max_line {
+                    return fail(format!(
+                        "Line {}: longer than {} bytes",
+                        lines.line + 1,
+                        max_line
+                    ));
+                }
+                match lines.chunks.next().await {
+                    Some(Ok(chunk)) => lines.buffer.extend_from_slice(&chunk),
+                    Some(Err(e)) => return fail(format!("Failed to read body: {}", e)),
+                    None => lines.ended = true,
+                }
+            }
+        }
+    })
+}
+
+fn find<'a>(pipelines: &'a Pipelines, name: &str) -> Result<&'a LoadedPipeline, ApiError> {
+    pipelines
+        .get(name)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Unknown pipeline: {}", name)))
+}
+
+async fn health(State(pipelines): State<Pipelines>) -> Json<Value> {
+    Json(json!({"status": "ok", "pipelines": pipelines.len()}))
+}
+
+async fn processors() -> Json<Value> {
+    Json(Registry::global().describe())
+}
+
+async fn list_pipelines(State(pipelines): State<Pipelines>) -> Json<Value> {
+    Json(Value::Array(
+        pipelines
+            .iter()
+            .map(|(name, loaded)| json!({"name": name, "config": loaded.config}))
+            .collect(),
+    ))
+}
+
+async fn describe_pipeline(
+    State(pipelines): State<Pipelines>,
+    extract::Path(name): extract::Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let loaded = find(&pipelines, &name)?;
+    Ok(Json(json!({
+        "name": name,
+        "config": loaded.config,
+        "stats": loaded.pipeline.stats(),
+    })))
+}
+
+async fn process(
+    State(pipelines): State<Pipelines>,
+    extract::Path(name): extract::Path<String>,
+    max_body: usize,
+    request: Request,
+) -> Result<Response, ApiError> {
+    let pipeline = find(&pipelines, &name)?.pipeline.clone();
+    let error = Arc::new(Mutex::new(None));
+
+    let requests = if is_ndjson(request.headers()) {
+        ndjson_requests(request.into_body(), max_body, error.clone()).boxed()
+    } else {
+        // Bounded by the `DefaultBodyLimit` of the router
+        let body = Bytes::from_request(request, &())
+            .await
+            .map_err(|rejection| ApiError(rejection.status(), rejection.body_text()))?;
+        stream::iter(parse_json_requests(&body)?).boxed()
+    };
+
+    // Only known once every request before the invalid line was read
+    let error_line = stream::once(async move { error.lock().unwrap().take() })
+        .filter_map(|error| future::ready(error.map(|error| json!({ "error": error }))));
+    let results = process_stream(pipeline, requests)
+        .map(|result| serde_json::to_vec(&result))
+        .chain(error_line.map(|error| serde_json::to_vec(&error)))
+        .map(|line| {
+            line.map(|mut line| {
+                line.push(b'\n');
+                line
+            })
+        });
+
+    Ok(([(header::CONTENT_TYPE, NDJSON)], Body::from_stream(results)).into_response())
+}
+
+fn app(pipelines: Pipelines, max_body: usize) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/processors", get(processors))
+        .route("/pipelines", get(list_pipelines))
+        .route("/pipelines/{name}", get(describe_pipeline))
+        .route(
+            "/pipelines/{name}/process",
+            post(move |pipelines, name, request| process(pipelines, name, max_body, request)),
+        )
+        .layer(DefaultBodyLimit::max(max_body))
+        .with_state(pipelines)
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: algoforge-server [--bind {}] [--max-body BYTES] [NAME=]CONFIG...",
+        DEFAULT_BIND
+    );
+    std::process::exit(2);
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_max_level(tracing::Level::INFO)
+        .init();
+
+    let mut bind = DEFAULT_BIND.to_string();
+    let mut max_body = DEFAULT_MAX_BODY;
+    let mut pipelines = BTreeMap::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => bind = args.next().unwrap_or_else(|| usage()),
+            "--max-body" => {
+                max_body = args
+                    .next()
+                    .and_then(|bytes| bytes.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "-h" | "--help" => usage(),
+            _ => match load_pipeline(&arg) {
+                Ok((name, loaded)) => {
+                    if pipelines.insert(name.clone(), loaded).is_some() {
+                        eprintln!("Several pipelines are named {}", name);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}: {}", arg, e);
+                    std::process::exit(1);
+                }
+            },
+        }
+    }
+    if pipelines.is_empty() {
+        usage();
+    }
+
+    let addr: SocketAddr = bind.parse().unwrap_or_else(|_| usage());
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    tracing::info!(
+        pipelines = pipelines.keys().cloned().collect::<Vec<_>>().join(", "),
+        "Serving on http://{}",
+        addr
+    );
+
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    if let Err(e) = axum::serve(listener, app(Arc::new(pipelines), max_body))
+        .with_graceful_shutdown(shutdown)
+        .await
+    {
+        eprintln!("Server error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn test_app(max_body: usize) -> Router {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+        std::fs::write(
+            &path,
+            r#"{"name": "words", "processors": [{"processor": "Tokenizer"}, {"processor": "ToLowerCase"}]}"#,
+        )
+        .unwrap();
+
+        let (name, loaded) = load_pipeline(path.to_str().unwrap()).unwrap();
+        assert_eq!(name, "words");
+        app(Arc::new(BTreeMap::from([(name, loaded)])), max_body)
+    }
+
+    async fn send(request: Request<Body>) -> (StatusCode, String) {
+        send_to(test_app(DEFAULT_MAX_BODY), request).await
+    }
+
+    async fn send_to(app: Router, request: Request<Body>) -> (StatusCode, String) {
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn post(uri: &str, content_type: &str, body: &str) -> Request<Body> {
+        Request::post(uri)
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn results(body: &str) -> BTreeMap<String, Value> {
+        body.lines()
+            .map(|line| {
+                let result: Value = serde_json::from_str(line).unwrap();
+                (result["id"].as_str().unwrap().to_string(), result)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_process_json() {
+        let (status, body) = send(post(
+            "/pipelines/words/process",
+            "application/json",
+            r#"[{"id": "1", "input": "Hello World"}, {"id": 2, "input": "Bye", "metadata": {"year": 2024}}]"#,
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = results(&body);
+        assert_eq!(results["1"]["content"], json!(["hello", "world"]));
+        assert_eq!(results["2"]["content"], json!(["bye"]));
+        assert_eq!(results["2"]["metadata"], json!({"year": 2024}));
+    }
+
+    #[tokio::test]
+    async fn test_process_ndjson() {
+        let (status, body) = send(post(
+            "/pipelines/words/process",
+            NDJSON,
+            "{\"id\": \"a\", \"input\": \"One\"}\n\n{\"id\": \"b\", \"input\": \"Two\"}\n",
+        ))
+        .await;
+
+        assert_eq!(status, StatusCode::OK);
+        let results = results(&body);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results["b"]["content"], json!(["two"]));
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (status, _) = send(post("/pipelines/other/process", NDJSON, "")).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, body) = send(post("/pipelines/words/process", "application/json", "[{")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("error"));
+
+        // The lines before the invalid one are already being processed
+        let (status, body) = send(post(
+            "/pipelines/words/process",
+            NDJSON,
+            "{\"id\": \"a\", \"input\": \"One\"}\n{\"id\": \"b\"}\n{\"id\": \"c\", \"input\": \"Three\"}",
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let lines: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["content"], json!(["one"]));
+        assert!(lines[1]["error"].as_str().unwrap().starts_with("Line 2: "));
+
+        assert!(load_pipeline("missing.json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_max_body() {
+        let request = r#"{"id": "1", "input": "Hello World"}"#;
+
+        let json = format!("[{}, {}]", request, request);
+        let (status, _) = send_to(
+            test_app(json.len() - 1),
+            post("/pipelines/words/process", "application/json", &json),
+        )
+        .await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Only each line of NDJSON is bounded
+        let ndjson = format!("{}\n", request).repeat(100);
+        let (status, body) = send_to(
+            test_app(request.len() + 1),
+            post("/pipelines/words/process", NDJSON, &ndjson),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.lines().count(), 100);
+
+        let (_, body) = send_to(
+            test_app(request.len() - 1),
+            post("/pipelines/words/process", NDJSON, &ndjson),
+        )
+        .await;
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap()["error"],
+            format!("Line 1: longer than {} bytes", request.len() - 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_streamed() {
+        let (body_tx, body_rx) = futures::channel::mpsc::unbounded::<Result<Bytes, LibError>>();
+        let request = Request::post("/pipelines/words/process")
+            .header(header::CONTENT_TYPE, NDJSON)
+            .body(Body::from_stream(body_rx))
+            .unwrap();
+        let response = test_app(DEFAULT_MAX_BODY).oneshot(request).await.unwrap();
+        let mut results = response.into_body().into_data_stream();
+
+        // A line split over several chunks
+        body_tx
+            .unbounded_send(Ok(Bytes::from(r#"{"id": "a", "inp"#)))
+            .unwrap();
+        body_tx
+            .unbounded_send(Ok(Bytes::from("ut\": \"One\"}\n{\"id\": \"b\", ")))
+            .unwrap();
+        // Processed before the rest of the body is sent
+        let first = results.next().await.unwrap().unwrap();
+        let first: Value = serde_json::from_slice(&first).unwrap();
+        assert_eq!(first["id"], "a");
+        assert_eq!(first["content"], json!(["one"]));
+
+        body_tx
+            .unbounded_send(Ok(Bytes::from("\"input\": \"Two\"}")))
+            .unwrap();
+        drop(body_tx);
+        let second = results.next().await.unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&second).unwrap()["content"],
+            json!(["two"])
+        );
+        assert!(results.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_describe() {
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let (status, body) = send(get("/health")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({"status": "ok", "pipelines": 1})
+        );
+
+        let (_, body) = send(get("/pipelines/words")).await;
+        let description: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            description["config"]["processors"][0]["processor"],
+            "Tokenizer"
+        );
+        assert_eq!(description["stats"]["stages"][0]["name"], "Tokenizer");
+
+        let (_, body) = send(get("/processors")).await;
+        assert!(body.contains("\"Lemmatizer\""));
+    }
+}
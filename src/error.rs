@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
 #[allow(dead_code)]
@@ -9,9 +11,39 @@ pub enum LibError {
     #[error("Invalid input for processor: {0}")]
     InvalidInput(String),
 
+    #[error("Invalid dictionary {file}, line {line}: {message}")]
+    Dictionary {
+        file: String,
+        line: u64,
+        message: String,
+    },
+
+    #[error("Python error: {0}")]
+    Python(String),
+
+    #[error("Invalid pipeline config: {0}")]
+    Config(String),
+
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
+
+    #[error("Cancelled")]
+    Cancelled,
+
     #[error("JSON error: {0}")]
     Json(String),
 
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
+
+impl LibError {
+    /// Message without the variant prefix, used
+    /// when wrapping errors in more context
+    pub(crate) fn message(&self) -> String {
+        match self {
+            LibError::InvalidInput(message) | LibError::Config(message) => message.clone(),
+            _ => self.to_string(),
+        }
+    }
+}
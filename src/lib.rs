@@ -1,143 +1,105 @@
 mod error;
+#[cfg(feature = "python")]
+mod logging;
 mod model;
 mod pipeline_builder;
-#[macro_use]
+// Dictionary compilation, validation and tagger training
+// are only exposed through the python bindings
+#[cfg_attr(not(feature = "python"), allow(dead_code))]
 mod pipeline_components;
-
-use std::sync::Arc;
-
-use crossbeam::channel::Receiver;
-use model::{Data, ProcessingRequest, ProcessingResult, ResultIterator};
-use pipeline_builder::Pipeline;
-use pipeline_components::{
-    Lemmatizer, PorterStemmer, PostProcessor, PreProcessor, SpellingMapper, ToLowerCase, Tokenizer,
-};
-use pyo3::{
-    pyclass, pymethods, pymodule,
-    types::{PyModule, PyModuleMethods},
-    Bound, PyAny, PyErr, PyObject, PyRef, PyResult, Python,
-};
-
-use pythonize::pythonize;
-use rayon::{
-    iter::{IntoParallelIterator, ParallelIterator},
-    ThreadPoolBuilder,
+#[cfg(feature = "python")]
+mod python;
+pub mod registry;
+mod scheduling;
+mod stats;
+#[cfg(feature = "async")]
+mod stream;
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Instant,
 };
-use serde_json::Value;
 
-#[pyclass]
-pub struct ProcPipeline {
-    pipeline: Arc<Pipeline>,
-}
+use crossbeam::{channel::Receiver, select};
+use scheduling::process_request_catching_panics;
 
-#[pymethods]
-impl ProcPipeline {
-    #[new]
-    pub fn new() -> Self {
-        ThreadPoolBuilder::new()
-            .num_threads(num_cpus::get())
-            .build_global()
-            .unwrap();
-        Self {
-            pipeline: Arc::new(Pipeline::new()),
-        }
-    }
+pub use error::LibError;
+pub use model::{Context, Data, DataType, ProcessingRequest, ProcessingResult, Token};
+pub use pipeline_builder::{Pipeline, Processor};
+#[cfg(feature = "python")]
+pub use python::{serde_to_py, ProcPipeline};
+pub use scheduling::{BatchOptions, CancelToken};
+pub use stats::{PipelineStats, StageStats};
+#[cfg(feature = "async")]
+pub use stream::{process_stream, ResultStream};
 
-    pub fn build_pipeline(&mut self, py: Python, processors: Vec<PyObject>) -> PyResult<()> {
-        let mut pipeline = Pipeline::new();
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 
-        for processor_obj in processors {
-            build_dyn_proc_mappings!(
-                py,
-                &mut pipeline,
-                processor_obj,
-                [
-                    PreProcessor,
-                    PostProcessor,
-                    ToLowerCase,
-                    Tokenizer,
-                    SpellingMapper,
-                    Lemmatizer,
-                    PorterStemmer
-                ]
-            );
-        }
-
-        self.pipeline = Arc::new(pipeline);
-        Ok(())
-    }
-
-    pub fn process(
-        &self,
-        _py: Python,
-        requests: Vec<(String, String)>,
-    ) -> PyResult<ResultIterator> {
-        let requests = requests
-            .into_iter()
-            .map(|(id, input)| ProcessingRequest { id, input })
-            .collect();
-
-        let result_rx = process_batch(self.pipeline.clone(), requests);
-
-        Ok(ResultIterator {
-            receiver: result_rx,
-        })
-    }
+pub fn process_batch(
+    pipeline: Arc<Pipeline>,
+    requests: Vec<ProcessingRequest>,
+) -> Receiver<ProcessingResult> {
+    process_batch_with(pipeline, requests, BatchOptions::default())
 }
 
-pub fn process_batch(
+/// Same as `process_batch`, with control over how
+/// requests are spread over the rayon pool
+///
+/// Processing stops once the receiver is dropped or the
+/// cancel token of `options` is cancelled, the batch
+/// statistics then only count the documents processed
+pub fn process_batch_with(
     pipeline: Arc<Pipeline>,
     requests: Vec<ProcessingRequest>,
+    options: BatchOptions,
 ) -> Receiver<ProcessingResult> {
     let (result_tx, result_rx) = crossbeam::channel::bounded(100);
-    let requests = requests.clone();
-    let pipeline = pipeline.clone();
 
     std::thread::spawn(move || {
-        requests
+        let start = Instant::now();
+        let documents = AtomicU64::new(0);
+        let bytes = AtomicU64::new(0);
+        let errors = AtomicU64::new(0);
+        let cancel_token = options.cancel_token();
+
+        // Short-circuits on the first `Err`, skipping the remaining requests
+        let completed = requests
             .into_par_iter()
-            .for_each_with(result_tx, move |result_tx, req| {
-                let result = pipeline.process(Data::OwnedStr(req.input.clone())).unwrap();
-                let result = ProcessingResult {
-                    id: req.id,
-                    content: result,
-                };
-
-                let _ = result_tx.send(result);
+            .with_min_len(options.chunk_size())
+            .try_for_each_with(result_tx, |result_tx, req| {
+                if cancel_token.is_cancelled() {
+                    return Err(());
+                }
+                documents.fetch_add(1, Ordering::Relaxed);
+                bytes.fetch_add(req.input.len() as u64, Ordering::Relaxed);
+
+                let result = process_request_catching_panics(&pipeline, req, &options);
+                if result.error.is_some() {
+                    errors.fetch_add(1, Ordering::Relaxed);
+                }
+                // Waiting for room in the channel ends on cancellation too
+                select! {
+                    send(result_tx, result) -> sent => sent.map_err(|_| ()),
+                    recv(cancel_token.receiver()) -> _ => Err(()),
+                }
             });
+        if completed.is_err() {
+            tracing::info!(
+                documents = documents.load(Ordering::Relaxed),
+                "Batch cancelled"
+            );
+        }
+
+        pipeline.counters().record_batch(
+            documents.into_inner(),
+            errors.into_inner(),
+            bytes.into_inner(),
+            start.elapsed(),
+        );
     });
 
     result_rx
 }
-
-impl Default for ProcPipeline {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-/// Convert a serde_json::Value to a Python object
-/// This is needed for correct python interop
-pub fn serde_to_py<'a>(py: Python<'a>, value: &'a Value) -> PyResult<Bound<'a, PyAny>> {
-    pythonize(py, value).map_err(|e| {
-        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
-            "Failed to convert serde_json::Value to Python object: {}",
-            e
-        ))
-    })
-}
-
-#[pymodule]
-fn algoforge(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_class::<ResultIterator>()?;
-    m.add_class::<ProcPipeline>()?;
-    m.add_class::<PreProcessor>()?;
-    m.add_class::<PostProcessor>()?;
-    m.add_class::<Tokenizer>()?;
-    m.add_class::<SpellingMapper>()?;
-    m.add_class::<Lemmatizer>()?;
-    m.add_class::<ToLowerCase>()?;
-    m.add_class::<PorterStemmer>()?;
-    m.add_class::<ProcessingRequest>()?;
-    Ok(())
-}
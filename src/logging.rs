@@ -0,0 +1,386 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Once, OnceLock, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    pyfunction,
+    types::{PyAnyMethods, PyDict, PyDictMethods},
+    PyErr, PyResult, Python,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer,
+};
+
+/// Most verbose level forwarded, as a python level
+/// (`u8::MAX` until `setup_logging` is called)
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// How long whether a python logger is enabled for a level is
+/// cached, so level changes in python still apply shortly after
+const ENABLED_TTL: Duration = Duration::from_secs(1);
+
+/// `isEnabledFor` of each logger and level, with when it was checked
+type EnabledCache = RwLock<HashMap<(String, u8), (bool, Instant)>>;
+
+fn enabled_cache() -> &'static EnabledCache {
+    static CACHE: OnceLock<EnabledCache> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+/// Whether python's `logging` would handle a record, only
+/// taking the GIL when the cached answer is missing or stale
+fn is_enabled(logger: &str, level: u8) -> bool {
+    let key = (logger.to_string(), level);
+    let cached = enabled_cache()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&key)
+        .copied();
+    if let Some((enabled, checked)) = cached {
+        if checked.elapsed() < ENABLED_TTL {
+            return enabled;
+        }
+    }
+
+    // Events emitted while python shuts down are dropped
+    if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+        return false;
+    }
+    let enabled = Python::with_gil(|py| {
+        py.import("logging")
+            .and_then(|logging| logging.call_method1("getLogger", (logger,)))
+            .and_then(|logger| logger.call_method1("isEnabledFor", (level,)))
+            .and_then(|enabled| enabled.extract())
+            // Left to `emit` to report
+            .unwrap_or(true)
+    });
+    enabled_cache()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key, (enabled, Instant::now()));
+    enabled
+}
+
+fn python_level(level: &Level) -> u8 {
+    match *level {
+        Level::TRACE => 5,
+        Level::DEBUG => 10,
+        Level::INFO => 20,
+        Level::WARN => 30,
+        Level::ERROR => 40,
+    }
+}
+
+/// Fields of a span or event, with the message kept apart
+#[derive(Default)]
+struct Fields {
+    message: String,
+    pairs: Vec<(String, String)>,
+}
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.pairs
+                .push((field.name().to_string(), value.to_string()));
+        }
+    }
+}
+
+impl Fields {
+    /// `key=value key=value`
+    fn format(&self) -> String {
+        let mut formatted = String::new();
+        for (key, value) in &self.pairs {
+            if !formatted.is_empty() {
+                formatted.push(' ');
+            }
+            let _ = write!(formatted, "{}={}", key, value);
+        }
+        formatted
+    }
+}
+
+/// Forwards events to python's `logging`, to a logger named
+/// after the module emitting them (e.g. `algoforge.pipeline_builder`)
+///
+/// Messages are prefixed with the spans they were emitted in, as
+/// `request{id=42}:processor{name=Lemmatizer}: message`, and the
+/// fields of the event and its spans are set as a dict on the
+/// `algoforge` attribute of the log record
+pub(crate) struct PythonLogLayer;
+
+impl<S> Layer<S> for PythonLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        python_level(metadata.level()) >= MAX_LEVEL.load(Ordering::Relaxed)
+    }
+
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let logger = metadata.target().replace("::", ".");
+        let level = python_level(metadata.level());
+        if !is_enabled(&logger, level) {
+            return;
+        }
+
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+
+        let mut prefix = String::new();
+        let mut extra: Vec<(String, String)> = Vec::new();
+        if let Some(scope) = ctx.event_scope(event) {
+            for span in scope.from_root() {
+                let extensions = span.extensions();
+                let span_fields = extensions.get::<Fields>();
+                let _ = write!(
+                    prefix,
+                    "{}{{{}}}:",
+                    span.name(),
+                    span_fields.map(Fields::format).unwrap_or_default()
+                );
+                extra.extend(span_fields.iter().flat_map(|f| f.pairs.iter().cloned()));
+            }
+        }
+
+        let mut message = if prefix.is_empty() {
+            fields.message.clone()
+        } else {
+            format!("{} {}", prefix, fields.message)
+        };
+        if !fields.pairs.is_empty() {
+            let _ = write!(message, " {}", fields.format());
+        }
+        extra.extend(fields.pairs);
+
+        emit(&logger, level, &message, extra);
+    }
+}
+
+fn emit(logger: &str, level: u8, message: &str, extra: Vec<(String, String)>) {
+    // Events emitted while python shuts down are dropped
+    if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+        return;
+    }
+
+    Python::with_gil(|py| {
+        let result = (|| -> PyResult<()> {
+            let fields = PyDict::new(py);
+            for (key, value) in extra {
+                fields.set_item(key, value)?;
+            }
+            let extra = PyDict::new(py);
+            extra.set_item("algoforge", fields)?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("extra", extra)?;
+
+            py.import("logging")?
+                .call_method1("getLogger", (logger,))?
+                .call_method("log", (level, message), Some(&kwargs))?;
+            Ok(())
+        })();
+        // Logging must never fail processing
+        if let Err(e) = result {
+            e.print(py);
+        }
+    });
+}
+
+/// Forwards algoforge diagnostics to python's `logging` from `level`
+/// up (`"TRACE"`, `"DEBUG"`, `"INFO"`, `"WARNING"` or `"ERROR"`)
+///
+/// Only needed once, calling it again changes the level. Level
+/// changes of python loggers are picked up within a second. Rust
+/// applications use their own `tracing` subscriber instead
+#[pyfunction]
+#[pyo3(signature = (level = "INFO"))]
+pub fn setup_logging(level: &str) -> PyResult<()> {
+    let level = match level.to_uppercase().as_str() {
+        "TRACE" => Level::TRACE,
+        "DEBUG" => Level::DEBUG,
+        "INFO" => Level::INFO,
+        "WARN" | "WARNING" => Level::WARN,
+        "ERROR" => Level::ERROR,
+        _ => {
+            return Err(PyErr::new::<PyValueError, _>(format!(
+                "Unknown log level: {}",
+                level
+            )))
+        }
+    };
+    MAX_LEVEL.store(python_level(&level), Ordering::Relaxed);
+    enabled_cache()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+
+    static INSTALL: Once = Once::new();
+    let mut install_error = None;
+    INSTALL.call_once(|| {
+        let subscriber = tracing_subscriber::registry().with(PythonLogLayer);
+        if let Err(e) = tracing::subscriber::set_global_default(subscriber) {
+            install_error = Some(e.to_string());
+        }
+    });
+    if let Some(e) = install_error {
+        return Err(PyErr::new::<PyRuntimeError, _>(format!(
+            "Failed to set up logging: {}",
+            e
+        )));
+    }
+
+    // Callsites cache whether they are enabled
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+
+    use pyo3::{
+        types::{PyList, PyListMethods},
+        Bound,
+    };
+    use serde_json::{Map, Value};
+    use tracing_subscriber::registry;
+
+    use super::*;
+    use crate::{
+        error::LibError,
+        model::{Data, ProcessingRequest},
+        pipeline_builder::{Pipeline, Processor},
+        scheduling::{process_request, BatchOptions},
+    };
+
+    #[derive(Debug)]
+    struct Failing;
+
+    impl Processor for Failing {
+        fn process<'a>(&self, _input: Data<'a>) -> Result<Data<'a>, LibError> {
+            Err(LibError::InvalidInput("broken".to_string()))
+        }
+
+        fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+            data.to_value()
+        }
+    }
+
+    #[test]
+    fn test_python_logging() {
+        pyo3::prepare_freethreaded_python();
+        let records = Python::with_gil(|py| {
+            let code = CString::new(
+                "import logging\n\
+                 records = []\n\
+                 class Handler(logging.Handler):\n    \
+                     def emit(self, record):\n        \
+                         records.append((record.name, record.levelno, record.getMessage(), record.algoforge))\n\
+                 logger = logging.getLogger('algoforge')\n\
+                 logger.addHandler(Handler())\n\
+                 logger.setLevel(logging.DEBUG)\n",
+            )
+            .unwrap();
+            let globals = PyDict::new(py);
+            py.run(&code, Some(&globals), None).unwrap();
+            globals
+                .get_item("records")
+                .unwrap()
+                .unwrap()
+                .downcast_into::<PyList>()
+                .unwrap()
+                .unbind()
+        });
+
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Failing);
+        let request = ProcessingRequest {
+            id: "doc-1".to_string(),
+            input: "text".to_string(),
+            metadata: Map::new(),
+        };
+
+        MAX_LEVEL.store(python_level(&Level::DEBUG), Ordering::Relaxed);
+        tracing::subscriber::with_default(registry().with(PythonLogLayer), || {
+            process_request(&pipeline, request, &BatchOptions::default());
+        });
+
+        Python::with_gil(|py| {
+            let records = records.bind(py);
+            assert_eq!(records.len(), 2);
+
+            let (logger, level, message, _): (String, u8, String, Bound<PyDict>) =
+                records.get_item(0).unwrap().extract().unwrap();
+            assert_eq!(logger, "algoforge.pipeline_builder");
+            assert_eq!(level, 10);
+            assert_eq!(
+                message,
+                "request{id=doc-1}:processor{name=Failing}: Processor failed \
+                 error=Invalid input for processor: broken"
+            );
+
+            let (logger, level, _, fields): (String, u8, String, Bound<PyDict>) =
+                records.get_item(1).unwrap().extract().unwrap();
+            assert_eq!(logger, "algoforge.scheduling");
+            assert_eq!(level, 30);
+            let id: String = fields.get_item("id").unwrap().unwrap().extract().unwrap();
+            assert_eq!(id, "doc-1");
+        });
+    }
+
+    #[test]
+    fn test_enabled_cache() {
+        pyo3::prepare_freethreaded_python();
+        let set_level = |level: &str| {
+            Python::with_gil(|py| {
+                py.import("logging")
+                    .unwrap()
+                    .call_method1("getLogger", ("algoforge.cached",))
+                    .unwrap()
+                    .call_method1("setLevel", (level,))
+                    .unwrap();
+            })
+        };
+
+        set_level("WARNING");
+        assert!(!is_enabled("algoforge.cached", 10));
+        assert!(is_enabled("algoforge.cached", 30));
+
+        // Answered from the cache until it expires
+        set_level("DEBUG");
+        assert!(!is_enabled("algoforge.cached", 10));
+        let key = ("algoforge.cached".to_string(), 10);
+        enabled_cache().write().unwrap().get_mut(&key).unwrap().1 -= ENABLED_TTL;
+        assert!(is_enabled("algoforge.cached", 10));
+    }
+}
@@ -1,129 +1,232 @@
-use std::borrow::Cow;
-
-use crossbeam::channel::Receiver;
-use pyo3::{
-    exceptions::PyStopIteration,
-    pyclass, pymethods,
-    types::{PyAnyMethods, PyDict, PyList},
-    PyRef, PyResult, Python, ToPyObject,
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant},
 };
-use serde_json::Value;
 
-#[derive(Debug)]
+#[cfg(feature = "python")]
+use pyo3::pyclass;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{error::LibError, scheduling::CancelToken};
+
+#[derive(Debug, Clone)]
 pub enum Data<'a> {
     OwnedStr(String),
     CowStr(Cow<'a, str>),
     VecCowStr(Vec<Cow<'a, str>>),
+    VecToken(Vec<Token<'a>>),
     Json(serde_json::Value),
 }
 
-impl<'a> Data<'a> {
-    pub fn pytype(&self) -> String {
-        match self {
-            Data::OwnedStr(_) => "str".to_string(),
-            Data::CowStr(_) => "str".to_string(),
-            Data::VecCowStr(_) => "list[str]".to_string(),
-            Data::Json(_) => "dict".to_string(),
+/// A token along with annotations
+/// added by processors along the way
+/// Serializes to `{"text": "colour", "spelling": "rule"}`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Token<'a> {
+    pub text: Cow<'a, str>,
+    #[serde(flatten)]
+    pub tags: BTreeMap<&'static str, String>,
+}
+
+impl<'a> Token<'a> {
+    pub fn new(text: Cow<'a, str>) -> Self {
+        Self {
+            text,
+            tags: BTreeMap::new(),
         }
     }
+
+    pub fn with_tag(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.tags.insert(key, value.into());
+        self
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(|s| s.as_str())
+    }
 }
 
-#[pyclass]
-#[derive(Debug, Clone)]
-pub struct ProcessingRequest {
-    #[pyo3(get, set)]
-    pub id: String,
-    #[pyo3(get, set)]
-    pub input: String,
+/// Kind of `Data` a processor accepts or outputs,
+/// named after the python type it converts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    /// `Data::OwnedStr` or `Data::CowStr`
+    Str,
+    /// `Data::VecCowStr`
+    ListStr,
+    /// `Data::VecToken`
+    ListDict,
+    /// `Data::Json`
+    Dict,
+    /// Any of the above, e.g. when it isn't known
+    /// until the document is processed
+    Any,
 }
 
-#[derive(Debug, Clone)]
-pub struct PyJsonValue(Value);
-
-impl ToPyObject for PyJsonValue {
-    fn to_object(&self, py: Python<'_>) -> pyo3::PyObject {
-        match &self.0 {
-            Value::Null => py.None(),
-            Value::Bool(b) => b.to_object(py),
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    i.to_object(py)
-                } else if let Some(f) = n.as_f64() {
-                    f.to_object(py)
-                } else {
-                    py.None()
-                }
-            }
-            Value::String(s) => s.to_object(py),
-            Value::Array(arr) => {
-                PyList::new_bound(py, arr.iter().map(|v| PyJsonValue(v.clone()))).into()
-            }
-            Value::Object(map) => {
-                let dict = PyDict::new_bound(py);
-                for (key, value) in map {
-                    let _ = dict.set_item(key, PyJsonValue(value.clone()).to_object(py));
-                }
-                dict.into()
-            }
-        }
+impl DataType {
+    /// Whether data of type `other` can be passed where `self` is expected
+    pub fn accepts(&self, other: DataType) -> bool {
+        *self == DataType::Any || other == DataType::Any || *self == other
     }
 }
 
-#[pymethods]
-impl ProcessingRequest {
-    #[new]
-    pub fn new(id: String, input: String) -> Self {
-        Self { id, input }
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DataType::Str => "str",
+            DataType::ListStr => "list[str]",
+            DataType::ListDict => "list[dict]",
+            DataType::Dict => "dict",
+            DataType::Any => "any",
+        })
     }
 }
 
-#[derive(Debug)]
-pub struct ProcessingResult {
-    pub id: String,
-    pub content: Value,
-}
+impl FromStr for DataType {
+    type Err = LibError;
 
-#[pyclass]
-pub struct ResultItem {
-    #[pyo3(get)]
-    id: String,
-    #[pyo3(get)]
-    content: Option<PyJsonValue>,
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace(' ', "").to_lowercase().as_str() {
+            "str" => Ok(DataType::Str),
+            "list[str]" => Ok(DataType::ListStr),
+            "list[dict]" => Ok(DataType::ListDict),
+            "dict" => Ok(DataType::Dict),
+            "any" => Ok(DataType::Any),
+            _ => Err(LibError::InvalidInput(format!(
+                "Unknown data type: {} (expected \"str\", \"list[str]\", \"list[dict]\", \"dict\" or \"any\")",
+                s
+            ))),
+        }
+    }
 }
 
-impl ToPyObject for ResultItem {
-    fn to_object(&self, py: pyo3::Python<'_>) -> pyo3::PyObject {
-        let py_dict = pyo3::types::PyDict::new_bound(py);
-        py_dict.set_item("id", &self.id).unwrap();
+impl<'a> Data<'a> {
+    pub fn pytype(&self) -> String {
+        self.data_type().to_string()
+    }
 
-        if let Some(content) = &self.content {
-            py_dict.set_item("content", content.to_object(py)).unwrap();
-        } else {
-            py_dict.set_item("content", py.None()).unwrap();
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Data::OwnedStr(_) | Data::CowStr(_) => DataType::Str,
+            Data::VecCowStr(_) => DataType::ListStr,
+            Data::VecToken(_) => DataType::ListDict,
+            Data::Json(_) => DataType::Dict,
         }
+    }
 
-        py_dict.into()
+    /// Generic JSON representation, for processors
+    /// whose output type depends on their input
+    pub fn to_value(&self) -> Result<Value, LibError> {
+        match self {
+            Data::OwnedStr(s) => Ok(Value::String(s.clone())),
+            Data::CowStr(s) => Ok(Value::String(s.to_string())),
+            Data::VecCowStr(v) => {
+                serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
+            }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
+            Data::Json(v) => Ok(v.clone()),
+        }
     }
 }
 
-#[pyclass]
-pub struct ResultIterator {
-    pub receiver: Receiver<ProcessingResult>,
+/// Per-document state shared by every processor in a pipeline
+/// Processors record document-level attributes here
+/// (e.g. the detected language), which are returned
+/// alongside the content on every `ResultItem`
+/// The request metadata is available read-only
+#[derive(Debug, Default, Clone)]
+pub struct Context {
+    pub attributes: Map<String, Value>,
+    metadata: Map<String, Value>,
+    /// When to give up on the document, with the timeout it was set from
+    deadline: Option<(Instant, Duration)>,
+    cancel_token: Option<CancelToken>,
 }
 
-#[pymethods]
-impl ResultIterator {
-    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
-        slf
+impl Context {
+    pub fn new(metadata: Map<String, Value>) -> Self {
+        Self {
+            attributes: Map::new(),
+            metadata,
+            deadline: None,
+            cancel_token: None,
+        }
+    }
+
+    /// Gives up on the document once `timeout` has elapsed or
+    /// `cancel_token` is cancelled, see `check_interrupted`
+    pub(crate) fn with_limits(
+        mut self,
+        timeout: Option<Duration>,
+        cancel_token: Option<CancelToken>,
+    ) -> Self {
+        self.deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+        self.cancel_token = cancel_token;
+        self
     }
 
-    fn __next__(slf: pyo3::PyRefMut<Self>) -> PyResult<Option<ResultItem>> {
-        match slf.receiver.recv() {
-            Ok(result) => Ok(Some(ResultItem {
-                id: result.id,
-                content: Some(PyJsonValue(result.content.clone())),
-            })),
-            Err(_) => Err(PyStopIteration::new_err("Iterator exhausted")),
+    /// Checked by pipelines between stages, as
+    /// a stage itself can't be interrupted
+    pub(crate) fn check_interrupted(&self) -> Result<(), LibError> {
+        if let Some((deadline, timeout)) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(LibError::Timeout(timeout));
+            }
         }
+        if self
+            .cancel_token
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+        {
+            return Err(LibError::Cancelled);
+        }
+        Ok(())
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&Value> {
+        self.attributes.get(key)
+    }
+
+    /// Metadata field of the request being processed
+    pub fn metadata(&self, key: &str) -> Option<&Value> {
+        self.metadata.get(key)
+    }
+
+    /// Attribute recorded by a processor, falling back
+    /// to the request metadata if there is none
+    pub fn value(&self, key: &str) -> Option<&Value> {
+        self.attribute(key).or_else(|| self.metadata(key))
     }
+
+    pub fn into_parts(self) -> (Map<String, Value>, Map<String, Value>) {
+        (self.attributes, self.metadata)
+    }
+
+    pub fn set_attribute(&mut self, key: impl Into<String>, value: impl Into<Value>) {
+        self.attributes.insert(key.into(), value.into());
+    }
+}
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct ProcessingRequest {
+    pub id: String,
+    pub input: String,
+    /// Arbitrary fields (company, year, country...)
+    /// echoed on the `ResultItem`
+    pub metadata: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessingResult {
+    pub id: String,
+    /// `None` if processing failed
+    pub content: Option<Value>,
+    pub error: Option<String>,
+    pub attributes: Map<String, Value>,
+    pub metadata: Map<String, Value>,
 }
@@ -1,14 +1,37 @@
 use core::fmt;
+use std::{sync::Arc, time::Instant};
 
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data};
+use crate::{
+    error::LibError,
+    model::{Context, Data, DataType},
+    stats::{token_count, PipelineCounters, PipelineStats},
+};
 
 pub trait Processor: Send + Sync + fmt::Debug {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError>;
 
+    /// Same as `process`, with access to the per-document context
+    /// Only processors that read or record document attributes
+    /// need to override this
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        _context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        self.process(input)
+    }
+
     fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError>;
 
+    /// Input and output types of processors whose types are
+    /// only known once constructed (e.g. a wrapped python function),
+    /// which are type checked with these rather than their spec
+    fn declared_types(&self) -> Option<(DataType, DataType)> {
+        None
+    }
+
     /// Only used for debugging purposes
     /// don't override the default implementation
     /// unless there's a good reason to
@@ -56,13 +79,55 @@ where
         self.second.process(intermediate)
     }
 
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        let intermediate = self.first.process_with_context(input, context)?;
+        self.second.process_with_context(intermediate, context)
+    }
+
     fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
         self.second.to_json(data)
     }
 }
 
+/// Shared processors, e.g. a pipeline nested
+/// in several other pipelines
+impl<P> Processor for Arc<P>
+where
+    P: Processor + ?Sized,
+{
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        (**self).process(input)
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        (**self).process_with_context(input, context)
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        (**self).to_json(data)
+    }
+
+    fn declared_types(&self) -> Option<(DataType, DataType)> {
+        (**self).declared_types()
+    }
+
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+}
+
 pub struct Pipeline {
     processors: Vec<Box<dyn Processor>>,
+    /// One stage per processor
+    stats: PipelineCounters,
 }
 
 /// Custom implementation of Debug for Pipeline
@@ -88,6 +153,7 @@ impl Pipeline {
     pub fn new() -> Self {
         Self {
             processors: Vec::new(),
+            stats: PipelineCounters::default(),
         }
     }
 
@@ -95,20 +161,102 @@ impl Pipeline {
     where
         P: Processor + 'static,
     {
-        self.processors.push(Box::new(processor));
+        self.add_boxed(Box::new(processor));
     }
 
-    pub fn process(&self, input: Data<'_>) -> Result<Value, LibError> {
-        let last_processor = self
-            .processors
-            .last()
-            .ok_or_else(|| LibError::InvalidInput("No processors in pipeline".to_string()))?;
+    /// Same as `add_processor`, for processors
+    /// constructed by the `Registry`
+    pub fn add_boxed(&mut self, processor: Box<dyn Processor>) {
+        self.stats.add_stage(processor.name());
+        self.processors.push(processor);
+    }
 
-        let res = self
-            .processors
+    /// Runs every processor in order, letting them
+    /// record document attributes in `context`,
+    /// and serializes the output of the last one
+    pub fn process_to_json(
+        &self,
+        input: Data<'_>,
+        context: &mut Context,
+    ) -> Result<Value, LibError> {
+        let res = self.run(input, context)?;
+        self.last_processor()?.to_json(&res)
+    }
+
+    /// Runs every processor in order without serializing the output,
+    /// stopping before the next one once the document timed out or
+    /// its batch was cancelled
+    pub fn run<'a>(&self, input: Data<'a>, context: &mut Context) -> Result<Data<'a>, LibError> {
+        self.last_processor()?;
+
+        self.processors
             .iter()
-            .try_fold(input, |data, proc| proc.process(data));
+            .enumerate()
+            .try_fold(input, |data, (index, proc)| {
+                context.check_interrupted()?;
+                let _span = tracing::debug_span!("processor", name = proc.name()).entered();
+                let tokens_in = token_count(&data);
+                let start = Instant::now();
+                let output = proc.process_with_context(data, context);
+                self.stats.stage(index).record(
+                    start.elapsed(),
+                    tokens_in,
+                    output.as_ref().ok().map(token_count),
+                );
+                if let Err(e) = &output {
+                    tracing::debug!(error = %e, "Processor failed");
+                }
+                output
+            })
+    }
 
-        last_processor.to_json(&res?)
+    /// Time spent, calls, errors and tokens in and out of every
+    /// stage, plus the throughput of the batches processed
+    pub fn stats(&self) -> PipelineStats {
+        self.stats.snapshot()
+    }
+
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    pub(crate) fn counters(&self) -> &PipelineCounters {
+        &self.stats
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    fn last_processor(&self) -> Result<&dyn Processor, LibError> {
+        self.processors
+            .last()
+            .map(|p| p.as_ref())
+            .ok_or_else(|| LibError::InvalidInput("No processors in pipeline".to_string()))
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pipelines can be nested as a single stage in another pipeline
+impl Processor for Pipeline {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        self.run(input, &mut Context::default())
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        self.run(input, context)
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        self.last_processor()?.to_json(data)
     }
 }
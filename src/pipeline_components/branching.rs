@@ -0,0 +1,398 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+#[cfg(feature = "python")]
+use pyo3::{pyclass, pymethods, PyObject, PyResult, Python};
+use serde_json::{Map, Value};
+
+use crate::{
+    error::LibError,
+    model::{Context, Data, DataType},
+    pipeline_builder::{Pipeline, Processor},
+    registry::{ParamKind, ParamSpec, ProcessorSpec},
+};
+
+/// Runs several sub-pipelines on the same input
+///
+/// Outputs `Data::Json` with the (serialized) output
+/// of every branch keyed by the branch name:
+/// `{"lemmas": [...], "stems": [...]}`
+///
+/// Every branch starts from the attributes recorded before the
+/// `FanOut` and doesn't see those recorded by the other branches.
+/// The attributes recorded by the branches are merged back afterwards,
+/// the last branch (in name order) winning if several record the same one
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct FanOut {
+    branches: BTreeMap<String, Arc<Pipeline>>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl FanOut {
+    /// Every branch is either a `ProcPipeline`
+    /// or a list of processors
+    #[new]
+    pub fn py_new(py: Python, branches: BTreeMap<String, PyObject>) -> PyResult<Self> {
+        let branches = branches
+            .into_iter()
+            .map(|(name, branch)| Ok((name, crate::python::pipeline_from_py(py, branch)?)))
+            .collect::<PyResult<_>>()?;
+
+        Ok(Self::new(branches))
+    }
+}
+
+impl FanOut {
+    pub fn new(branches: BTreeMap<String, Arc<Pipeline>>) -> Self {
+        Self { branches }
+    }
+}
+
+impl FanOut {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("FanOut", |params| {
+            Ok(Self::new(params.pipelines("branches")?))
+        })
+        .with_description("Runs several sub-pipelines on the same input")
+        .with_param(ParamSpec::required(
+            "branches",
+            ParamKind::PipelineMap,
+            "Sub-pipelines keyed by the name of their output",
+        ))
+        .with_output(DataType::Dict)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for FanOut {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        self.process_with_context(input, &mut Context::default())
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        let mut output = Map::new();
+        let mut recorded = Map::new();
+        for (name, branch) in &self.branches {
+            let mut branch_context = context.clone();
+            output.insert(
+                name.clone(),
+                branch.process_to_json(input.clone(), &mut branch_context)?,
+            );
+
+            let (attributes, _) = branch_context.into_parts();
+            for (key, value) in attributes {
+                if context.attribute(&key) != Some(&value) {
+                    recorded.insert(key, value);
+                }
+            }
+        }
+
+        context.attributes.extend(recorded);
+        Ok(Data::Json(Value::Object(output)))
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::Json(v) => Ok(v.clone()),
+            _ => Err(LibError::InvalidInput(
+                "FanOut will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+/// Picks a sub-pipeline based on a document attribute
+/// recorded by an earlier processor (e.g. `"language"`
+/// from `LanguageDetector`), or a request metadata field
+/// if no processor recorded one (e.g. `"country"`)
+///
+/// Documents without a matching route go through `default`,
+/// or are passed through unchanged if there is none.
+/// The output of the route is passed on as is, so routes
+/// should produce the same type if more stages follow
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct Router {
+    attribute: String,
+    routes: BTreeMap<String, Arc<Pipeline>>,
+    default: Option<Arc<Pipeline>>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl Router {
+    /// Every route is either a `ProcPipeline`
+    /// or a list of processors
+    #[new]
+    #[pyo3(signature = (attribute, routes, default = None))]
+    pub fn py_new(
+        py: Python,
+        attribute: String,
+        routes: BTreeMap<String, PyObject>,
+        default: Option<PyObject>,
+    ) -> PyResult<Self> {
+        let routes = routes
+            .into_iter()
+            .map(|(key, route)| Ok((key, crate::python::pipeline_from_py(py, route)?)))
+            .collect::<PyResult<_>>()?;
+        let default = default
+            .map(|route| crate::python::pipeline_from_py(py, route))
+            .transpose()?;
+
+        Ok(Self::new(attribute, routes, default))
+    }
+}
+
+impl Router {
+    pub fn new(
+        attribute: String,
+        routes: BTreeMap<String, Arc<Pipeline>>,
+        default: Option<Arc<Pipeline>>,
+    ) -> Self {
+        Self {
+            attribute,
+            routes,
+            default,
+        }
+    }
+
+    fn route(&self, context: &Context) -> Option<&Arc<Pipeline>> {
+        let key = match context.value(&self.attribute) {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Null) | None => None,
+            Some(v) => Some(v.to_string()),
+        };
+
+        key.and_then(|key| self.routes.get(&key))
+            .or(self.default.as_ref())
+    }
+}
+
+impl Router {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("Router", |params| {
+            Ok(Self::new(
+                params.get("attribute")?,
+                params.pipelines("routes")?,
+                params.opt_pipeline("default")?,
+            ))
+        })
+        .with_description("Picks a sub-pipeline based on a document attribute or metadata field")
+        .with_param(ParamSpec::required(
+            "attribute",
+            ParamKind::String,
+            "Attribute or metadata field to route on",
+        ))
+        .with_param(ParamSpec::required(
+            "routes",
+            ParamKind::PipelineMap,
+            "Sub-pipelines keyed by attribute value",
+        ))
+        .with_param(ParamSpec::optional(
+            "default",
+            ParamKind::Pipeline,
+            Value::Null,
+            "Sub-pipeline for documents without a matching route",
+        ))
+        .with_output(DataType::Any)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for Router {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        self.process_with_context(input, &mut Context::default())
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        match self.route(context) {
+            Some(pipeline) => pipeline.run(input, context),
+            None => Ok(input),
+        }
+    }
+
+    /// Routes may output any type
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        data.to_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_components::{LanguageDetector, PorterStemmer, ToLowerCase, Tokenizer};
+    use serde_json::json;
+
+    fn lowercase() -> Arc<Pipeline> {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Tokenizer::new());
+        pipeline.add_processor(ToLowerCase::new());
+        Arc::new(pipeline)
+    }
+
+    fn stems() -> Arc<Pipeline> {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Tokenizer::new());
+        pipeline.add_processor(PorterStemmer::new());
+        Arc::new(pipeline)
+    }
+
+    #[test]
+    fn test_nested_pipeline() {
+        let mut outer = Pipeline::new();
+        outer.add_processor(lowercase());
+        outer.add_processor(PorterStemmer::new());
+
+        let result = outer
+            .process_to_json(
+                Data::OwnedStr("Connected Connections".to_string()),
+                &mut Context::default(),
+            )
+            .unwrap();
+        assert_eq!(result, json!(["connect", "connect"]));
+    }
+
+    #[test]
+    fn test_fan_out() {
+        let fan_out = FanOut::new(BTreeMap::from([
+            ("lower".to_string(), lowercase()),
+            ("stems".to_string(), stems()),
+        ]));
+
+        let result = fan_out
+            .process(Data::OwnedStr("forced Connections".to_string()))
+            .unwrap();
+        assert_eq!(
+            fan_out.to_json(&result).unwrap(),
+            json!({
+                "lower": ["forced", "connections"],
+                "stems": ["forc", "Connect"],
+            })
+        );
+    }
+
+    #[test]
+    fn test_fan_out_branch_contexts() {
+        let mut detect = Pipeline::new();
+        detect.add_processor(LanguageDetector::embedded());
+        let route = Router::new(
+            "language".to_string(),
+            BTreeMap::from([("en".to_string(), stems())]),
+            Some(lowercase()),
+        );
+        let mut routed = Pipeline::new();
+        routed.add_processor(route);
+
+        let fan_out = FanOut::new(BTreeMap::from([
+            ("detect".to_string(), Arc::new(detect)),
+            ("route".to_string(), Arc::new(routed)),
+        ]));
+
+        let mut context = Context::default();
+        context.set_attribute("source", "report");
+        let result = fan_out
+            .process_with_context(
+                Data::OwnedStr("The forced Connections".to_string()),
+                &mut context,
+            )
+            .unwrap();
+
+        // The language recorded by "detect" isn't visible to "route"
+        assert_eq!(
+            fan_out.to_json(&result).unwrap(),
+            json!({
+                "detect": "The forced Connections",
+                "route": ["the", "forced", "connections"],
+            })
+        );
+        // But is merged back once every branch has run
+        assert_eq!(context.attribute("language"), Some(&json!("en")));
+        assert_eq!(context.attribute("source"), Some(&json!("report")));
+    }
+
+    #[test]
+    fn test_router() {
+        let router = Router::new(
+            "language".to_string(),
+            BTreeMap::from([("en".to_string(), stems())]),
+            Some(lowercase()),
+        );
+
+        let mut context = Context::default();
+        context.set_attribute("language", "en");
+        let result = router
+            .process_with_context(Data::OwnedStr("connections".to_string()), &mut context)
+            .unwrap();
+        assert_eq!(router.to_json(&result).unwrap(), json!(["connect"]));
+
+        // No matching route
+        let mut context = Context::default();
+        context.set_attribute("language", "fr");
+        let result = router
+            .process_with_context(Data::OwnedStr("Connections".to_string()), &mut context)
+            .unwrap();
+        assert_eq!(router.to_json(&result).unwrap(), json!(["connections"]));
+    }
+
+    #[test]
+    fn test_router_pass_through() {
+        let router = Router::new(
+            "language".to_string(),
+            BTreeMap::from([("en".to_string(), stems())]),
+            None,
+        );
+
+        let mut outer = Pipeline::new();
+        outer.add_processor(LanguageDetector::embedded());
+        outer.add_processor(router);
+
+        let mut context = Context::default();
+        let result = outer
+            .process_to_json(
+                Data::OwnedStr("Les enfants travaillent".to_string()),
+                &mut context,
+            )
+            .unwrap();
+        assert_eq!(result, json!("Les enfants travaillent"));
+        assert_eq!(context.attribute("language"), Some(&json!("fr")));
+    }
+
+    #[test]
+    fn test_router_on_metadata() {
+        let router = Router::new(
+            "country".to_string(),
+            BTreeMap::from([("GB".to_string(), stems())]),
+            Some(lowercase()),
+        );
+
+        let mut outer = Pipeline::new();
+        outer.add_processor(router);
+        outer.add_processor(PorterStemmer::new());
+
+        let metadata = Map::from_iter([("country".to_string(), json!("GB"))]);
+        let mut context = Context::new(metadata);
+        let result = outer
+            .process_to_json(Data::OwnedStr("Connected".to_string()), &mut context)
+            .unwrap();
+        assert_eq!(result, json!(["Connect"]));
+
+        // Attributes take precedence over metadata
+        let metadata = Map::from_iter([("country".to_string(), json!("GB"))]);
+        let mut context = Context::new(metadata);
+        context.set_attribute("country", "US");
+        let result = outer
+            .process_to_json(Data::OwnedStr("Connected".to_string()), &mut context)
+            .unwrap();
+        assert_eq!(result, json!(["connect"]));
+        assert_eq!(context.metadata("country"), Some(&json!("GB")));
+    }
+}
@@ -0,0 +1,74 @@
+/// Casing pattern of a word, used to re-apply
+/// the original casing to a replacement found
+/// through a case-insensitive lookup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CasePattern {
+    Lower,
+    Upper,
+    Title,
+    /// Anything else (e.g. `iPhone`, `McDonald`)
+    /// the replacement is returned untouched
+    Mixed,
+}
+
+impl CasePattern {
+    pub(crate) fn detect(word: &str) -> Self {
+        let mut chars = word.chars().filter(|c| c.is_alphabetic());
+
+        let first = match chars.next() {
+            Some(c) => c,
+            None => return CasePattern::Lower,
+        };
+
+        let rest: Vec<char> = chars.collect();
+
+        if first.is_lowercase() && rest.iter().all(|c| !c.is_uppercase()) {
+            CasePattern::Lower
+        } else if first.is_uppercase() && rest.iter().all(|c| !c.is_uppercase()) {
+            // Single letter words like "I" end up here as well
+            CasePattern::Title
+        } else if first.is_uppercase() && rest.iter().all(|c| !c.is_lowercase()) {
+            CasePattern::Upper
+        } else {
+            CasePattern::Mixed
+        }
+    }
+
+    pub(crate) fn apply(&self, word: &str) -> String {
+        match self {
+            CasePattern::Lower | CasePattern::Mixed => word.to_string(),
+            CasePattern::Upper => word.to_uppercase(),
+            CasePattern::Title => {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(CasePattern::detect("color"), CasePattern::Lower);
+        assert_eq!(CasePattern::detect("Color"), CasePattern::Title);
+        assert_eq!(CasePattern::detect("COLOR"), CasePattern::Upper);
+        assert_eq!(CasePattern::detect("iPhone"), CasePattern::Mixed);
+        assert_eq!(CasePattern::detect("I"), CasePattern::Title);
+        assert_eq!(CasePattern::detect("CO-OP"), CasePattern::Upper);
+        assert_eq!(CasePattern::detect("123"), CasePattern::Lower);
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(CasePattern::Title.apply("colour"), "Colour");
+        assert_eq!(CasePattern::Upper.apply("run"), "RUN");
+        assert_eq!(CasePattern::Lower.apply("colour"), "colour");
+        assert_eq!(CasePattern::Mixed.apply("colour"), "colour");
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use fst::{IntoStreamer, Streamer};
+use memmap2::Mmap;
+
+use crate::error::LibError;
+
+/// Identifies a compiled dictionary file
+const MAGIC: &[u8; 8] = b"AFDICT01";
+/// Magic, kind (padded to 8 bytes), FST length, string count
+const HEADER_LEN: usize = 32;
+
+/// What a compiled dictionary was compiled from,
+/// so a lemma file isn't loaded as a spelling map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DictionaryKind {
+    Lemma = 0,
+    Spelling = 1,
+}
+
+impl DictionaryKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(DictionaryKind::Lemma),
+            1 => Some(DictionaryKind::Spelling),
+            _ => None,
+        }
+    }
+}
+
+/// A value stored under a key of a compiled dictionary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Entry {
+    /// e.g. the lemma of a derivative, or the replacement spelling
+    pub target: Option<String>,
+    /// Free for the dictionary kind to use
+    /// (e.g. whether the key is a lemma itself)
+    pub flag: bool,
+}
+
+/// Part of the memory map, so `fst::Map` can own its bytes
+#[derive(Clone)]
+struct MmapSlice {
+    mmap: Arc<Mmap>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.mmap[self.range.clone()]
+    }
+}
+
+/// Read-only dictionary compiled from a lemma or spelling CSV
+///
+/// Layout (integers are little-endian u64):
+///
+/// `AFDICT01 | kind | FST length | string count | FST | string offsets | strings`
+///
+/// Keys live in an FST whose values point into the string table,
+/// `(string id + 1) << 1 | flag`, with 0 as the id of "no target".
+/// The file is memory-mapped, so loading is near-instant and
+/// the pages are shared between processes using the same file
+pub(crate) struct CompiledDictionary {
+    kind: DictionaryKind,
+    map: fst::Map<MmapSlice>,
+    strings: MmapSlice,
+    /// `string count + 1` offsets into `strings`
+    offsets: MmapSlice,
+}
+
+impl std::fmt::Debug for CompiledDictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledDictionary")
+            .field("kind", &self.kind)
+            .field("keys", &self.map.len())
+            .finish()
+    }
+}
+
+impl CompiledDictionary {
+    /// Whether the file starts with the compiled dictionary magic
+    /// (as opposed to being a CSV)
+    pub(crate) fn is_compiled(path: &Path) -> bool {
+        let mut magic = [0u8; 8];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok_and(|_| &magic == MAGIC)
+    }
+
+    pub(crate) fn open(path: &Path, kind: DictionaryKind) -> Result<Self, LibError> {
+        let invalid = |reason: &str| {
+            LibError::IO(format!(
+                "Invalid compiled dictionary {}: {}",
+                path.display(),
+                reason
+            ))
+        };
+
+        let file = File::open(path)
+            .map_err(|e| LibError::IO(format!("Failed to open {}: {}", path.display(), e)))?;
+        // SAFETY: the file is only ever read, and `write` replaces
+        // compiled dictionaries by renaming rather than modifying
+        // them in place
+        let mmap = Arc::new(
+            unsafe { Mmap::map(&file) }
+                .map_err(|e| LibError::IO(format!("Failed to map {}: {}", path.display(), e)))?,
+        );
+
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err(invalid("missing header"));
+        }
+        match DictionaryKind::from_byte(mmap[8]) {
+            Some(found) if found == kind => {}
+            Some(found) => return Err(invalid(&format!("expected {:?}, found {:?}", kind, found))),
+            None => return Err(invalid("unknown dictionary kind")),
+        }
+
+        let read_u64 = |at: usize| u64::from_le_bytes(mmap[at..at + 8].try_into().unwrap());
+        let fst_len = read_u64(16) as usize;
+        let string_count = read_u64(24) as usize;
+
+        let fst_end = HEADER_LEN
+            .checked_add(fst_len)
+            .filter(|end| *end <= mmap.len())
+            .ok_or_else(|| invalid("truncated"))?;
+        let offsets_end = string_count
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(8))
+            .and_then(|n| n.checked_add(fst_end))
+            .filter(|end| *end <= mmap.len())
+            .ok_or_else(|| invalid("truncated"))?;
+
+        let slice = |range: Range<usize>| MmapSlice {
+            mmap: mmap.clone(),
+            range,
+        };
+        let map = fst::Map::new(slice(HEADER_LEN..fst_end)).map_err(|e| invalid(&e.to_string()))?;
+        Ok(Self {
+            kind,
+            map,
+            strings: slice(offsets_end..mmap.len()),
+            offsets: slice(fst_end..offsets_end),
+        })
+    }
+
+    /// Compiles `entries` into a dictionary at `path`
+    /// The dictionary is written next to `path` and renamed
+    /// over it, so mapped or reloading readers of an existing
+    /// dictionary never see a partially written file
+    pub(crate) fn write(
+        path: &Path,
+        kind: DictionaryKind,
+        entries: &BTreeMap<String, Entry>,
+    ) -> Result<(), LibError> {
+        let io_err =
+            |e: std::io::Error| LibError::IO(format!("Failed to write {}: {}", path.display(), e));
+
+        // Targets repeat a lot (every derivative of a lemma),
+        // so each distinct string is stored once
+        let mut string_ids: BTreeMap<&str, u64> = BTreeMap::new();
+        for entry in entries.values() {
+            if let Some(target) = &entry.target {
+                let next_id = string_ids.len() as u64;
+                string_ids.entry(target.as_str()).or_insert(next_id);
+            }
+        }
+        let mut strings: Vec<&str> = vec![""; string_ids.len()];
+        for (string, id) in &string_ids {
+            strings[*id as usize] = string;
+        }
+
+        let mut builder = fst::MapBuilder::memory();
+        for (key, entry) in entries {
+            let id = entry.target.as_deref().map_or(0, |t| string_ids[t] + 1);
+            builder
+                .insert(key, (id << 1) | entry.flag as u64)
+                .map_err(|e| LibError::IO(format!("Failed to build dictionary: {}", e)))?;
+        }
+        let fst_bytes = builder
+            .into_inner()
+            .map_err(|e| LibError::IO(format!("Failed to build dictionary: {}", e)))?;
+
+        let mut partial_path = path.as_os_str().to_os_string();
+        partial_path.push(".partial");
+        let partial_path = PathBuf::from(partial_path);
+
+        Self::write_file(&partial_path, kind, &fst_bytes, &strings)
+            .and_then(|_| fs::rename(&partial_path, path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&partial_path);
+                io_err(e)
+            })
+    }
+
+    fn write_file(
+        path: &Path,
+        kind: DictionaryKind,
+        fst_bytes: &[u8],
+        strings: &[&str],
+    ) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let mut header = [0u8; HEADER_LEN];
+        header[..8].copy_from_slice(MAGIC);
+        header[8] = kind as u8;
+        header[16..24].copy_from_slice(&(fst_bytes.len() as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(strings.len() as u64).to_le_bytes());
+        writer.write_all(&header)?;
+        writer.write_all(fst_bytes)?;
+
+        let mut offset = 0u64;
+        writer.write_all(&offset.to_le_bytes())?;
+        for string in strings {
+            offset += string.len() as u64;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for string in strings {
+            writer.write_all(string.as_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Returns the target (if any) and flag stored under `key`
+    pub(crate) fn get(&self, key: &str) -> Option<(Option<&str>, bool)> {
+        self.map.get(key).map(|value| self.decode(value))
+    }
+
+    /// Every entry whose key starts with `prefix`, with the prefix stripped
+    pub(crate) fn entries_with_prefix(&self, prefix: &str) -> Vec<(String, Option<&str>)> {
+        let mut stream = self.map.range().ge(prefix).into_stream();
+
+        let mut entries = Vec::new();
+        while let Some((key, value)) = stream.next() {
+            let Some(rest) = key.strip_prefix(prefix.as_bytes()) else {
+                break;
+            };
+            entries.push((
+                String::from_utf8_lossy(rest).into_owned(),
+                self.decode(value).0,
+            ));
+        }
+        entries
+    }
+
+    fn decode(&self, value: u64) -> (Option<&str>, bool) {
+        let target = match value >> 1 {
+            0 => None,
+            id => self.string(id as usize - 1),
+        };
+        (target, value & 1 == 1)
+    }
+
+    fn offset(&self, id: usize) -> Option<usize> {
+        let bytes = self.offsets.as_ref().get(id * 8..id * 8 + 8)?;
+        Some(u64::from_le_bytes(bytes.try_into().ok()?) as usize)
+    }
+
+    /// Bounds are checked on lookup rather than on open,
+    /// so opening doesn't have to scan the whole file
+    fn string(&self, id: usize) -> Option<&str> {
+        let range = self.offset(id)?..self.offset(id + 1)?;
+        std::str::from_utf8(self.strings.as_ref().get(range)?).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(target: Option<&str>, flag: bool) -> Entry {
+        Entry {
+            target: target.map(|t| t.to_string()),
+            flag,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lemmas.afdict");
+        let entries = BTreeMap::from([
+            ("be".to_string(), entry(None, true)),
+            ("is".to_string(), entry(Some("be"), false)),
+            ("was".to_string(), entry(Some("be"), false)),
+            ("straße".to_string(), entry(Some("strasse"), true)),
+        ]);
+        CompiledDictionary::write(&path, DictionaryKind::Lemma, &entries).unwrap();
+
+        assert!(CompiledDictionary::is_compiled(&path));
+        let dictionary = CompiledDictionary::open(&path, DictionaryKind::Lemma).unwrap();
+        assert_eq!(dictionary.get("be"), Some((None, true)));
+        assert_eq!(dictionary.get("was"), Some((Some("be"), false)));
+        assert_eq!(dictionary.get("straße"), Some((Some("strasse"), true)));
+        assert_eq!(dictionary.get("bee"), None);
+
+        assert!(CompiledDictionary::open(&path, DictionaryKind::Spelling).is_err());
+    }
+
+    #[test]
+    fn test_entries_with_prefix() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("spelling.afdict");
+        let entries = BTreeMap::from([
+            ("\u{1}a".to_string(), entry(Some("x"), false)),
+            ("\u{2}b".to_string(), entry(Some("y"), false)),
+            ("\u{2}c".to_string(), entry(Some("z"), false)),
+            ("\u{3}d".to_string(), entry(None, false)),
+        ]);
+        CompiledDictionary::write(&path, DictionaryKind::Spelling, &entries).unwrap();
+
+        let dictionary = CompiledDictionary::open(&path, DictionaryKind::Spelling).unwrap();
+        assert_eq!(
+            dictionary.entries_with_prefix("\u{2}"),
+            vec![("b".to_string(), Some("y")), ("c".to_string(), Some("z"))]
+        );
+    }
+
+    #[test]
+    fn test_not_compiled() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lemma_map.csv");
+        std::fs::write(&path, "lemma,derivatives\nbe,is\n").unwrap();
+
+        assert!(!CompiledDictionary::is_compiled(&path));
+        assert!(CompiledDictionary::open(&path, DictionaryKind::Lemma).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::{fs::File, io, path::Path};
+
+use serde::Serialize;
+
+use crate::error::LibError;
+
+/// What kind of problem `validate` found in a dictionary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// An entry that is listed more than once,
+    /// or maps to more than one word
+    Duplicate,
+    Empty,
+    /// A word mapped to itself
+    SelfMapping,
+    /// A lemma that is itself a derivative of another lemma
+    Chain,
+    /// Words that map back to themselves through the map
+    Cycle,
+}
+
+/// A problem in a dictionary that doesn't stop it from loading,
+/// serialized as `{"line": 12, "kind": "duplicate", "message": "..."}`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DictionaryIssue {
+    pub line: u64,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl DictionaryIssue {
+    pub(crate) fn new(line: u64, kind: IssueKind, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// Reads the data rows of a dictionary CSV (after the header) along
+/// with their line numbers. `file` names the source in errors, and
+/// every row must have at least the given `columns`
+pub(crate) fn read_rows(
+    mut source: impl io::Read,
+    file: &str,
+    columns: &[&str],
+) -> Result<Vec<(u64, csv::StringRecord)>, LibError> {
+    let mut content = Vec::new();
+    source
+        .read_to_end(&mut content)
+        .map_err(|e| LibError::IO(format!("Failed to read {}: {}", file, e)))?;
+
+    // The csv reader's own line count skips blank lines,
+    // so lines are counted from byte offsets instead
+    let newlines: Vec<usize> = content
+        .iter()
+        .enumerate()
+        .filter_map(|(i, b)| (*b == b'\n').then_some(i))
+        .collect();
+    let line_of = |position: Option<&csv::Position>| {
+        position.map_or(0, |p| {
+            // Records start after any blank lines preceding them
+            let mut start = p.byte() as usize;
+            while matches!(content.get(start), Some(b'\r' | b'\n')) {
+                start += 1;
+            }
+            newlines.partition_point(|&i| i < start) as u64 + 1
+        })
+    };
+    let error = |line: u64, message: String| LibError::Dictionary {
+        file: file.to_string(),
+        line,
+        message,
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_slice());
+
+    let mut rows = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(|e| error(line_of(e.position()), e.to_string()))?;
+        let line = line_of(record.position());
+
+        if record.len() < columns.len() {
+            return Err(error(
+                line,
+                format!(
+                    "expected {} columns ({}), found {}",
+                    columns.len(),
+                    columns.join(","),
+                    record.len()
+                ),
+            ));
+        }
+        rows.push((line, record));
+    }
+
+    Ok(rows)
+}
+
+/// `read_rows` for a file on disk
+pub(crate) fn read_file_rows(
+    path: &Path,
+    columns: &[&str],
+) -> Result<Vec<(u64, csv::StringRecord)>, LibError> {
+    let file = File::open(path)
+        .map_err(|e| LibError::IO(format!("Failed to read {}: {}", path.display(), e)))?;
+    read_rows(file, &path.display().to_string(), columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_rows() {
+        let csv = "lemma,derivatives\nbe,\"is, was\"\n\nrun,runs\n";
+        let rows = read_rows(csv.as_bytes(), "test.csv", &["lemma", "derivatives"]).unwrap();
+
+        let lines: Vec<_> = rows
+            .iter()
+            .map(|(line, record)| (*line, &record[0]))
+            .collect();
+        assert_eq!(lines, vec![(2, "be"), (4, "run")]);
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let csv = "lemma,derivatives\nbe,is\n\nrun\n";
+        let error = read_rows(csv.as_bytes(), "test.csv", &["lemma", "derivatives"]).unwrap_err();
+
+        match &error {
+            LibError::Dictionary { file, line, .. } => {
+                assert_eq!(file, "test.csv");
+                assert_eq!(*line, 4);
+            }
+            _ => panic!("Expected LibError::Dictionary"),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Invalid dictionary test.csv, line 4: expected 2 columns (lemma,derivatives), found 1"
+        );
+    }
+}
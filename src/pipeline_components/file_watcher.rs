@@ -0,0 +1,175 @@
+use std::{
+    ffi::OsString,
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::error::LibError;
+
+/// How long a file has to stay unchanged before it's reloaded,
+/// so editors writing it in several steps trigger a single reload
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+type Reload = Arc<dyn Fn() -> Result<(), LibError> + Send + Sync>;
+
+/// Calls `reload` whenever the watched file changes,
+/// until the watcher is dropped
+pub(crate) struct FileWatcher {
+    _watcher: RecommendedWatcher,
+    #[cfg(test)]
+    reload: Reload,
+}
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher").finish_non_exhaustive()
+    }
+}
+
+impl FileWatcher {
+    pub(crate) fn new(
+        path: &Path,
+        reload: impl Fn() -> Result<(), LibError> + Send + Sync + 'static,
+    ) -> Result<Self, LibError> {
+        let watch_error =
+            |e: notify::Error| LibError::IO(format!("Failed to watch {}: {}", path.display(), e));
+
+        let path = path
+            .canonicalize()
+            .map_err(|e| LibError::IO(format!("Failed to watch {}: {}", path.display(), e)))?;
+        let (Some(directory), Some(file_name)) = (path.parent(), path.file_name()) else {
+            return Err(LibError::InvalidInput(format!(
+                "Not a file: {}",
+                path.display()
+            )));
+        };
+        let file_name = file_name.to_os_string();
+
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = notify::recommended_watcher(tx).map_err(watch_error)?;
+        // The directory is watched rather than the file, as
+        // editors often save by replacing the file
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+
+        let reload: Reload = Arc::new(reload);
+        let watched = reload.clone();
+        // Exits once the watcher (and with it the sender) is dropped
+        thread::spawn(move || watch_events(rx, &path, file_name, &*watched));
+
+        Ok(Self {
+            _watcher: watcher,
+            #[cfg(test)]
+            reload,
+        })
+    }
+
+    /// Calls the reload callback as a change to the file would
+    #[cfg(test)]
+    pub(crate) fn trigger(&self) -> Result<(), LibError> {
+        (self.reload)()
+    }
+}
+
+/// Calls `reload` once events for `file_name` stop coming in
+/// for `DEBOUNCE`, until the sender of `rx` is dropped
+fn watch_events(
+    rx: Receiver<notify::Result<notify::Event>>,
+    path: &Path,
+    file_name: OsString,
+    reload: &dyn Fn() -> Result<(), LibError>,
+) {
+    let touches_file = |event: &notify::Result<notify::Event>| match event {
+        Ok(event) => {
+            !event.kind.is_access()
+                && event
+                    .paths
+                    .iter()
+                    .any(|p| p.file_name() == Some(file_name.as_os_str()))
+        }
+        Err(_) => false,
+    };
+
+    while let Ok(event) = rx.recv() {
+        if !touches_file(&event) {
+            continue;
+        }
+        // Changes to other files in the directory
+        // don't delay the reload
+        let mut deadline = Instant::now() + DEBOUNCE;
+        loop {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(event) if touches_file(&event) => deadline = Instant::now() + DEBOUNCE,
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        // The previous version stays in use
+        // if the file can't be loaded
+        match reload() {
+            Ok(()) => tracing::info!(path = %path.display(), "Reloaded"),
+            Err(e) => {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to reload")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use notify::{event::ModifyKind, Event, EventKind};
+
+    use super::*;
+
+    fn modified(name: &str) -> notify::Result<Event> {
+        Ok(Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/data").join(name)))
+    }
+
+    #[test]
+    fn test_debounce_ignores_other_files() {
+        let (tx, rx) = mpsc::channel();
+        let reloads = Arc::new(AtomicUsize::new(0));
+        let counted = reloads.clone();
+        let watching = thread::spawn(move || {
+            watch_events(
+                rx,
+                &PathBuf::from("/data/lemma_map.csv"),
+                "lemma_map.csv".into(),
+                &|| {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                },
+            )
+        });
+
+        tx.send(modified("lemma_map.csv")).unwrap();
+        tx.send(modified("lemma_map.csv")).unwrap();
+        // A file next to it changing more often than the
+        // debounce interval doesn't hold back the reload
+        let start = Instant::now();
+        while reloads.load(Ordering::SeqCst) == 0 && start.elapsed() < 10 * DEBOUNCE {
+            tx.send(modified("other.log")).unwrap();
+            thread::sleep(DEBOUNCE / 10);
+        }
+        drop(tx);
+        watching.join().unwrap();
+
+        // A single reload for both changes
+        assert_eq!(reloads.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,300 @@
+use std::{str::FromStr, sync::Arc};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyValueError, pyclass, pymethods, PyErr, PyObject, PyResult, Python};
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Context, Data, DataType},
+    pipeline_builder::{Pipeline, Processor},
+    registry::{ParamKind, ParamSpec, ProcessorSpec},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    /// Array index, or object key made of digits
+    Index(usize),
+    /// Every element of an array or value of an object
+    Wildcard,
+}
+
+/// Dotted path to one or more fields of a JSON document
+/// e.g. `title`, `sections.*.body`, `sections.0.title`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPath(Vec<Segment>);
+
+impl FromStr for FieldPath {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments = s
+            .split('.')
+            .map(|segment| match segment {
+                "" => Err(LibError::InvalidInput(format!(
+                    "Empty segment in field path: {}",
+                    s
+                ))),
+                "*" => Ok(Segment::Wildcard),
+                _ => Ok(segment
+                    .parse()
+                    .map(Segment::Index)
+                    .unwrap_or_else(|_| Segment::Key(segment.to_string()))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self(segments))
+    }
+}
+
+impl FieldPath {
+    /// Calls `f` on every string the path points to
+    /// Missing fields and non-string values are skipped
+    fn visit(
+        value: &mut Value,
+        path: &[Segment],
+        f: &mut impl FnMut(&mut Value) -> Result<(), LibError>,
+    ) -> Result<(), LibError> {
+        let Some((segment, rest)) = path.split_first() else {
+            return match value {
+                Value::String(_) => f(value),
+                _ => Ok(()),
+            };
+        };
+
+        match (segment, value) {
+            (Segment::Key(key), Value::Object(map)) => match map.get_mut(key) {
+                Some(child) => Self::visit(child, rest, f),
+                None => Ok(()),
+            },
+            (Segment::Index(index), Value::Object(map)) => match map.get_mut(&index.to_string()) {
+                Some(child) => Self::visit(child, rest, f),
+                None => Ok(()),
+            },
+            (Segment::Index(index), Value::Array(items)) => match items.get_mut(*index) {
+                Some(child) => Self::visit(child, rest, f),
+                None => Ok(()),
+            },
+            (Segment::Wildcard, Value::Array(items)) => items
+                .iter_mut()
+                .try_for_each(|child| Self::visit(child, rest, f)),
+            (Segment::Wildcard, Value::Object(map)) => map
+                .values_mut()
+                .try_for_each(|child| Self::visit(child, rest, f)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Processes selected fields of structured JSON documents
+///
+/// Takes a JSON document (a JSON string, or `Data::Json`),
+/// runs a sub-pipeline on every string field matching one
+/// of the field paths, and outputs `Data::Json` mirroring
+/// the input with each of those fields replaced by the
+/// (serialized) output of the sub-pipeline
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct JsonFields {
+    fields: Vec<FieldPath>,
+    pipeline: Arc<Pipeline>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl JsonFields {
+    /// `processors` is either a `ProcPipeline`
+    /// or a list of processors
+    #[new]
+    pub fn py_new(py: Python, fields: Vec<String>, processors: PyObject) -> PyResult<Self> {
+        let pipeline = crate::python::pipeline_from_py(py, processors)?;
+        Self::new(&fields, pipeline).map_err(|e| PyErr::new::<PyValueError, _>(format!("{}", e)))
+    }
+}
+
+impl JsonFields {
+    pub fn new(fields: &[String], pipeline: Arc<Pipeline>) -> Result<Self, LibError> {
+        if fields.is_empty() {
+            return Err(LibError::InvalidInput(
+                "JsonFields needs at least one field path".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            fields: fields
+                .iter()
+                .map(|field| field.parse())
+                .collect::<Result<_, _>>()?,
+            pipeline,
+        })
+    }
+
+    fn process_document(
+        &self,
+        mut document: Value,
+        context: &mut Context,
+    ) -> Result<Value, LibError> {
+        for field in &self.fields {
+            FieldPath::visit(&mut document, &field.0, &mut |value| {
+                if let Value::String(text) = value {
+                    *value = self
+                        .pipeline
+                        .process_to_json(Data::OwnedStr(std::mem::take(text)), context)?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(document)
+    }
+}
+
+impl JsonFields {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("JsonFields", |params| {
+            Self::new(
+                &params.get::<Vec<String>>("fields")?,
+                params.pipeline("processors")?,
+            )
+        })
+        .with_description("Runs a sub-pipeline on selected string fields of a JSON document")
+        .with_param(ParamSpec::required(
+            "fields",
+            ParamKind::StringList,
+            "Dotted field paths, e.g. sections.*.body",
+        ))
+        .with_param(ParamSpec::required(
+            "processors",
+            ParamKind::Pipeline,
+            "Sub-pipeline run on every field",
+        ))
+        .with_inputs(&[DataType::Str, DataType::Dict])
+        .with_output(DataType::Dict)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for JsonFields {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        self.process_with_context(input, &mut Context::default())
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        let parse = |s: &str| {
+            serde_json::from_str(s)
+                .map_err(|e| LibError::Json(format!("Invalid JSON document: {}", e)))
+        };
+
+        let document = match input {
+            Data::OwnedStr(s) => parse(&s)?,
+            Data::CowStr(s) => parse(&s)?,
+            Data::Json(v) => v,
+            _ => {
+                return Err(LibError::InvalidInput(
+                    "JsonFields only accepts Data::OwnedStr, Data::CowStr or Data::Json"
+                        .to_string(),
+                ))
+            }
+        };
+
+        Ok(Data::Json(self.process_document(document, context)?))
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::Json(v) => Ok(v.clone()),
+            _ => Err(LibError::InvalidInput(
+                "JsonFields will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline_components::{ToLowerCase, Tokenizer};
+    use serde_json::json;
+
+    fn fields(paths: &[&str]) -> JsonFields {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Tokenizer::new());
+        pipeline.add_processor(ToLowerCase::new());
+
+        let paths: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+        JsonFields::new(&paths, Arc::new(pipeline)).unwrap()
+    }
+
+    #[test]
+    fn test_field_path() {
+        assert_eq!(
+            "sections.*.body".parse::<FieldPath>().unwrap(),
+            FieldPath(vec![
+                Segment::Key("sections".to_string()),
+                Segment::Wildcard,
+                Segment::Key("body".to_string()),
+            ])
+        );
+        assert_eq!(
+            "sections.0".parse::<FieldPath>().unwrap(),
+            FieldPath(vec![
+                Segment::Key("sections".to_string()),
+                Segment::Index(0)
+            ])
+        );
+        assert!("sections..body".parse::<FieldPath>().is_err());
+    }
+
+    #[test]
+    fn test_process_fields() {
+        let processor = fields(&["title", "sections.*.body"]);
+        let input = json!({
+            "title": "Modern Slavery Statement",
+            "year": 2023,
+            "sections": [
+                {"heading": "Our Supply Chain", "body": "Forced Labour"},
+                {"heading": "Training", "body": "Child Labour"},
+            ],
+        });
+
+        let result = processor
+            .process(Data::OwnedStr(input.to_string()))
+            .unwrap();
+        assert_eq!(
+            processor.to_json(&result).unwrap(),
+            json!({
+                "title": ["modern", "slavery", "statement"],
+                "year": 2023,
+                "sections": [
+                    {"heading": "Our Supply Chain", "body": ["forced", "labour"]},
+                    {"heading": "Training", "body": ["child", "labour"]},
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_and_non_string_fields() {
+        let processor = fields(&["summary", "year", "sections.5.body", "tags.*"]);
+        let input = json!({"year": 2023, "sections": [], "tags": ["Audit", null]});
+
+        let result = processor.process(Data::Json(input)).unwrap();
+        assert_eq!(
+            processor.to_json(&result).unwrap(),
+            json!({"year": 2023, "sections": [], "tags": [["audit"], null]})
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let processor = fields(&["title"]);
+        assert!(processor
+            .process(Data::OwnedStr("not json".to_string()))
+            .is_err());
+        assert!(processor.process(Data::VecCowStr(vec![])).is_err());
+    }
+}
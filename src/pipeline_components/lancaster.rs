@@ -0,0 +1,238 @@
+use std::sync::OnceLock;
+
+/// Rule table as used by NLTK's `LancasterStemmer`.
+///
+/// Each rule reads: reversed ending, `*` if the rule only
+/// applies to intact (not yet stemmed) words, number of
+/// characters to remove, string to append, and `>` to
+/// continue stemming or `.` to stop.
+const RULES: &[&str] = &[
+    "ai*2.",
+    "a*1.",
+    "bb1.",
+    "city3s.",
+    "ci2>",
+    "cn1t>",
+    "dd1.",
+    "dei3y>",
+    "deec2ss.",
+    "dee1.",
+    "de2>",
+    "dooh4>",
+    "e1>",
+    "feil1v.",
+    "fi2>",
+    "gni3>",
+    "gai3y.",
+    "ga2>",
+    "gg1.",
+    "ht*2.",
+    "hsiug5ct.",
+    "hsi3>",
+    "i*1.",
+    "i1y>",
+    "ji1d.",
+    "juf1s.",
+    "ju1d.",
+    "jo1d.",
+    "jeh1r.",
+    "jrev1t.",
+    "jsim2t.",
+    "jn1d.",
+    "j1s.",
+    "lbaifi6.",
+    "lbai4y.",
+    "lba3>",
+    "lbi3.",
+    "lib2l>",
+    "lc1.",
+    "lufi4y.",
+    "luf3>",
+    "lu2.",
+    "lai3>",
+    "lau3>",
+    "la2>",
+    "ll1.",
+    "mui3.",
+    "mu*2.",
+    "msi3>",
+    "mm1.",
+    "nois4j>",
+    "noix4ct.",
+    "noi3>",
+    "nai3>",
+    "na2>",
+    "nee0.",
+    "ne2>",
+    "nn1.",
+    "pihs4>",
+    "pp1.",
+    "re2>",
+    "rae0.",
+    "ra2.",
+    "ro2>",
+    "ru2>",
+    "rr1.",
+    "rt1>",
+    "rei3y>",
+    "sei3y>",
+    "sis2.",
+    "si2>",
+    "ssen4>",
+    "ss0.",
+    "suo3>",
+    "su*2.",
+    "s*1>",
+    "s0.",
+    "tacilp4y.",
+    "ta2>",
+    "tnem4>",
+    "tne3>",
+    "tna3>",
+    "tpir2b.",
+    "tpro2b.",
+    "tcud1.",
+    "tpmus2.",
+    "tpec2iv.",
+    "tulo2v.",
+    "tsis0.",
+    "tsi3>",
+    "tt1.",
+    "uqi3.",
+    "ugo1.",
+    "vis3j>",
+    "vie0.",
+    "vi2>",
+    "ylb1>",
+    "yli3y>",
+    "ylp0.",
+    "yl2>",
+    "ygo1.",
+    "yhp1.",
+    "ymo1.",
+    "ypo1.",
+    "yti3>",
+    "yte3>",
+    "ytl2.",
+    "yrtsi5.",
+    "yra3>",
+    "yro3>",
+    "yfi3.",
+    "ycn2t>",
+    "yca3>",
+    "zi2>",
+    "zy1s.",
+];
+
+struct Rule {
+    ending: String,
+    intact_only: bool,
+    remove: usize,
+    append: &'static str,
+    stop: bool,
+}
+
+impl Rule {
+    fn parse(rule: &'static str) -> Rule {
+        let digit = rule
+            .find(|c: char| c.is_ascii_digit())
+            .expect("Lancaster rule without a remove count");
+        let (ending, intact_only) = match rule[..digit].strip_suffix('*') {
+            Some(ending) => (ending, true),
+            None => (&rule[..digit], false),
+        };
+
+        Rule {
+            ending: ending.chars().rev().collect(),
+            intact_only,
+            remove: rule[digit..=digit].parse().unwrap_or(0),
+            append: &rule[digit + 1..rule.len() - 1],
+            stop: rule.ends_with('.'),
+        }
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y')
+}
+
+/// Words starting with a vowel need at least 2 letters left,
+/// words starting with a consonant need at least 3 letters left
+/// and a vowel among the first 3
+fn is_acceptable(word: &[char], remove: usize) -> bool {
+    let remaining = word.len().saturating_sub(remove);
+    match word {
+        [first, ..] if is_vowel(*first) => remaining >= 2,
+        [_, second, third, ..] => remaining >= 3 && (is_vowel(*second) || is_vowel(*third)),
+        _ => false,
+    }
+}
+
+fn rules() -> &'static [Rule] {
+    static PARSED: OnceLock<Vec<Rule>> = OnceLock::new();
+    PARSED.get_or_init(|| RULES.iter().map(|r| Rule::parse(r)).collect())
+}
+
+/// Lancaster (Paice/Husk) stemming algorithm.
+/// Stems a single lowercase word
+///
+/// Based on Paice, C.D. "Another Stemmer"
+/// SIGIR Forum, 24(3), 56-61, 1990.
+pub(crate) fn stem(word: &str) -> String {
+    let rules = rules();
+
+    let mut word: Vec<char> = word.chars().collect();
+    let mut intact = true;
+
+    while let Some(last) = word.last().copied() {
+        let applied = rules
+            .iter()
+            .filter(|rule| rule.ending.ends_with(last))
+            .find(|rule| {
+                let ending: Vec<char> = rule.ending.chars().collect();
+                word.ends_with(&ending)
+                    && (intact || !rule.intact_only)
+                    && is_acceptable(&word, rule.remove)
+            });
+
+        match applied {
+            Some(rule) => {
+                word.truncate(word.len() - rule.remove);
+                word.extend(rule.append.chars());
+                intact = false;
+                if rule.stop {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    word.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lancaster() {
+        let cases = [
+            ("maximum", "maxim"),     // Remove "-um" when word is intact
+            ("presumably", "presum"), // Don't remove "-um" when word is not intact
+            ("multiply", "multiply"), // No action taken if word ends with "-ply"
+            ("provision", "provid"),  // "-sion" -> "-j" triggers the "j" rules
+            ("owed", "ow"),           // Vowel-initial words keep at least 2 letters
+            ("ear", "ear"),
+            ("saying", "say"), // Consonant-initial words keep at least 3 letters
+            ("crying", "cry"), // including a vowel
+            ("string", "string"),
+            ("meant", "meant"),
+            ("cement", "cem"),
+        ];
+
+        for (word, expected) in cases {
+            assert_eq!(stem(word), expected, "{}", word);
+        }
+    }
+}
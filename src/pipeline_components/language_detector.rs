@@ -0,0 +1,594 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyRuntimeError, pyclass, pymethods, PyErr};
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Context, Data, DataType},
+    pipeline_builder::Processor,
+    registry::{ParamKind, ParamSpec, ProcessorSpec},
+};
+
+/// Context attribute holding the detected ISO 639-1 code
+pub(crate) const LANGUAGE_ATTRIBUTE: &str = "language";
+/// Context attribute holding the confidence (0.0 - 1.0)
+pub(crate) const LANGUAGE_CONFIDENCE_ATTRIBUTE: &str = "language_confidence";
+
+/// ISO 639-2 code for "undetermined"
+const UNDETERMINED: &str = "und";
+
+/// Character n-grams of length 1 to `MAX_NGRAM` are used
+const MAX_NGRAM: usize = 3;
+
+/// Only the first `MAX_NGRAMS` n-grams of a document are scored,
+/// which is plenty to tell languages apart
+const MAX_NGRAMS: usize = 3000;
+
+/// Probability given to n-grams missing from a profile, shared by
+/// all profiles so that no language is favoured by its sample size
+const UNSEEN_PROBABILITY: f64 = 1e-5;
+
+/// Scales the gap between the average n-gram log-likelihoods
+/// of the candidates before it's turned into a confidence
+const CONFIDENCE_SHARPNESS: f64 = 7.0;
+
+/// Sample texts the embedded profiles are built from
+/// (Universal Declaration of Human Rights, articles 1-4,
+/// followed by everyday, news and supply chain sentences)
+const EMBEDDED_SAMPLES: &[(&str, &str)] = &[
+    ("da", include_str!("../../data/language_samples/da.txt")),
+    ("de", include_str!("../../data/language_samples/de.txt")),
+    ("en", include_str!("../../data/language_samples/en.txt")),
+    ("es", include_str!("../../data/language_samples/es.txt")),
+    ("fr", include_str!("../../data/language_samples/fr.txt")),
+    ("id", include_str!("../../data/language_samples/id.txt")),
+    ("it", include_str!("../../data/language_samples/it.txt")),
+    ("nl", include_str!("../../data/language_samples/nl.txt")),
+    ("pl", include_str!("../../data/language_samples/pl.txt")),
+    ("pt", include_str!("../../data/language_samples/pt.txt")),
+    ("ru", include_str!("../../data/language_samples/ru.txt")),
+    ("sv", include_str!("../../data/language_samples/sv.txt")),
+    ("tr", include_str!("../../data/language_samples/tr.txt")),
+    ("zh", include_str!("../../data/language_samples/zh.txt")),
+];
+
+/// Character n-gram frequencies of a single language
+#[derive(Debug, Clone)]
+struct LanguageProfile {
+    log_probabilities: HashMap<String, f64>,
+}
+
+impl LanguageProfile {
+    fn from_counts(counts: &HashMap<String, u64>) -> Self {
+        let total = counts.values().sum::<u64>() as f64;
+        let log_probabilities = counts
+            .iter()
+            .map(|(ngram, count)| (ngram.clone(), (*count as f64 / total).ln()))
+            .collect();
+
+        Self { log_probabilities }
+    }
+
+    fn contains(&self, ngram: &str) -> bool {
+        self.log_probabilities.contains_key(ngram)
+    }
+
+    /// Average log probability of the n-grams
+    fn log_likelihood(&self, ngrams: &[&String]) -> f64 {
+        let unseen = UNSEEN_PROBABILITY.ln();
+        let total: f64 = ngrams
+            .iter()
+            .map(|ngram| {
+                self.log_probabilities
+                    .get(*ngram)
+                    .copied()
+                    .unwrap_or(unseen)
+            })
+            .sum();
+        total / ngrams.len() as f64
+    }
+}
+
+/// Chinese and Japanese characters, which aren't separated into words
+fn is_ideograph(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}'
+    )
+}
+
+/// Splits text into words made of letters only,
+/// each ideograph being a word of its own
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        if c.is_alphabetic() && !is_ideograph(c) {
+            start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            words.push(&text[start..i]);
+        }
+        if is_ideograph(c) {
+            words.push(&text[i..i + c.len_utf8()]);
+        }
+    }
+    if let Some(start) = start {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// Returns the n-grams of each padded word (" word ") of the text
+fn ngrams(text: &str) -> Vec<String> {
+    let mut ngrams = Vec::new();
+
+    for word in words(text) {
+        let padded: Vec<char> = std::iter::once(' ')
+            .chain(word.chars().flat_map(|c| c.to_lowercase()))
+            .chain(std::iter::once(' '))
+            .collect();
+
+        for n in 1..=MAX_NGRAM {
+            for window in padded.windows(n) {
+                if n == 1 && window[0] == ' ' {
+                    continue;
+                }
+                ngrams.push(window.iter().collect());
+            }
+        }
+
+        if ngrams.len() >= MAX_NGRAMS {
+            ngrams.truncate(MAX_NGRAMS);
+            break;
+        }
+    }
+
+    ngrams
+}
+
+fn count_ngrams(text: &str) -> HashMap<String, u64> {
+    let mut counts = HashMap::new();
+    // Samples are scored in full, not just the first MAX_NGRAMS
+    for line in text.lines() {
+        for ngram in ngrams(line) {
+            *counts.entry(ngram).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// Language identification using character n-gram profiles
+///
+/// Doesn't modify its input, but records the ISO 639-1 code
+/// of the detected language (`"language"`) and the confidence
+/// (`"language_confidence"`) as document attributes, which are
+/// returned on every `ResultItem`. Documents without any letters,
+/// or below `min_confidence`, are recorded as `"und"`
+///
+/// Languages are scored by the average log probability of the text's
+/// n-grams, ignoring those no profile has seen. The confidence combines
+/// how far the best language is ahead of the others with the share of
+/// the text's bigrams its profile knows, so that short ambiguous texts
+/// and gibberish get a low confidence
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct LanguageDetector {
+    /// Shared between clones, and between detectors
+    /// restricted to different languages
+    profiles: BTreeMap<String, Arc<LanguageProfile>>,
+    min_confidence: f64,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl LanguageDetector {
+    /// Builds a profiles file from sample texts,
+    /// given as a map of language code -> path to a text file
+    #[staticmethod]
+    pub fn build_profiles(
+        samples: HashMap<String, String>,
+        output_path: String,
+    ) -> Result<(), PyErr> {
+        Self::write_profiles(samples, PathBuf::from(output_path))
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+
+    /// Language codes the detector can choose from
+    #[pyo3(name = "languages")]
+    pub fn py_languages(&self) -> Vec<String> {
+        self.languages()
+    }
+}
+
+impl LanguageDetector {
+    /// Language codes the detector can choose from
+    pub fn languages(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Detector using the profiles embedded in the library,
+    /// built on first use and shared by every detector after that
+    pub fn embedded() -> Self {
+        static PROFILES: OnceLock<BTreeMap<String, Arc<LanguageProfile>>> = OnceLock::new();
+        let profiles = PROFILES.get_or_init(|| {
+            EMBEDDED_SAMPLES
+                .iter()
+                .map(|(language, sample)| {
+                    (
+                        language.to_string(),
+                        Arc::new(LanguageProfile::from_counts(&count_ngrams(sample))),
+                    )
+                })
+                .collect()
+        });
+
+        Self {
+            profiles: profiles.clone(),
+            min_confidence: 0.0,
+        }
+    }
+
+    /// Loads profiles from a JSON file
+    /// mapping language code -> n-gram -> count
+    pub fn from_file(path: PathBuf) -> Result<Self, LibError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| LibError::IO(format!("Failed to read language profiles: {}", e)))?;
+        let counts: BTreeMap<String, HashMap<String, u64>> = serde_json::from_str(&content)
+            .map_err(|e| LibError::Json(format!("Invalid language profiles: {}", e)))?;
+
+        if counts.is_empty() {
+            return Err(LibError::InvalidInput(
+                "Language profiles file contains no languages".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            profiles: counts
+                .into_iter()
+                .map(|(language, counts)| {
+                    (language, Arc::new(LanguageProfile::from_counts(&counts)))
+                })
+                .collect(),
+            min_confidence: 0.0,
+        })
+    }
+
+    pub fn write_profiles(
+        samples: HashMap<String, String>,
+        output_path: PathBuf,
+    ) -> Result<(), LibError> {
+        let mut profiles = BTreeMap::new();
+        for (language, path) in samples {
+            let sample = fs::read_to_string(&path)
+                .map_err(|e| LibError::IO(format!("Failed to read sample {}: {}", path, e)))?;
+            profiles.insert(language, count_ngrams(&sample));
+        }
+
+        let content =
+            serde_json::to_string(&profiles).map_err(|e| LibError::Json(e.to_string()))?;
+        fs::write(output_path, content)
+            .map_err(|e| LibError::IO(format!("Failed to write language profiles: {}", e)))
+    }
+
+    /// Restricts detection to the given languages
+    pub fn with_languages(mut self, languages: Option<Vec<String>>) -> Result<Self, LibError> {
+        if let Some(languages) = languages {
+            if let Some(missing) = languages.iter().find(|l| !self.profiles.contains_key(*l)) {
+                return Err(LibError::InvalidInput(format!(
+                    "No profile for language: {}",
+                    missing
+                )));
+            }
+            self.profiles
+                .retain(|language, _| languages.contains(language));
+        }
+        Ok(self)
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// Returns the most likely language and its confidence
+    pub fn detect(&self, text: &str) -> (&str, f64) {
+        let ngrams = ngrams(text);
+        // N-grams no profile has seen are no evidence for any language
+        let known: Vec<&String> = ngrams
+            .iter()
+            .filter(|ngram| {
+                self.profiles
+                    .values()
+                    .any(|profile| profile.contains(ngram))
+            })
+            .collect();
+        if known.is_empty() {
+            return (UNDETERMINED, 0.0);
+        }
+
+        let scores: Vec<(&str, f64)> = self
+            .profiles
+            .iter()
+            .map(|(language, profile)| (language.as_str(), profile.log_likelihood(&known)))
+            .collect();
+
+        let Some((language, best)) = scores
+            .iter()
+            .copied()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        else {
+            return (UNDETERMINED, 0.0);
+        };
+
+        // Softmax, shifted by the best score to avoid underflow
+        let total: f64 = scores
+            .iter()
+            .map(|(_, score)| ((score - best) * CONFIDENCE_SHARPNESS).exp())
+            .sum();
+
+        let profile = &self.profiles[language];
+        let bigrams: Vec<&String> = ngrams.iter().filter(|n| n.chars().count() == 2).collect();
+        let coverage = bigrams.iter().filter(|n| profile.contains(n)).count() as f64
+            / bigrams.len().max(1) as f64;
+
+        let confidence = coverage / total;
+
+        if confidence < self.min_confidence {
+            (UNDETERMINED, confidence)
+        } else {
+            (language, confidence)
+        }
+    }
+
+    fn text<'a>(data: &'a Data<'_>) -> Result<Cow<'a, str>, LibError> {
+        match data {
+            Data::OwnedStr(s) => Ok(Cow::Borrowed(s)),
+            Data::CowStr(s) => Ok(Cow::Borrowed(s)),
+            Data::VecCowStr(v) => Ok(Cow::Owned(v.join(" "))),
+            Data::VecToken(v) => Ok(Cow::Owned(
+                v.iter()
+                    .map(|t| t.text.as_ref())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )),
+            Data::Json(_) => Err(LibError::InvalidInput(
+                "LanguageDetector doesn't accept Data::Json as input".to_string(),
+            )),
+        }
+    }
+}
+
+impl LanguageDetector {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("LanguageDetector", |params| {
+            let detector = match params.get::<Option<String>>("profiles_path")? {
+                Some(path) => Self::from_file(PathBuf::from(path))?,
+                None => Self::embedded(),
+            };
+            Ok(detector
+                .with_languages(params.get("languages")?)?
+                .with_min_confidence(params.get("min_confidence")?))
+        })
+        .with_description("Records the language of the document, passing the input on unchanged")
+        .with_param(ParamSpec::optional(
+            "profiles_path",
+            ParamKind::String,
+            Value::Null,
+            "Profiles file created by build_profiles, the embedded profiles if unset",
+        ))
+        .with_param(ParamSpec::optional(
+            "languages",
+            ParamKind::StringList,
+            Value::Null,
+            "Subset of the profiles to choose from",
+        ))
+        .with_param(ParamSpec::optional(
+            "min_confidence",
+            ParamKind::Float,
+            0.0,
+            "Below this the language is recorded as \"und\"",
+        ))
+        .with_inputs(&[DataType::Str, DataType::ListStr, DataType::ListDict])
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for LanguageDetector {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        self.process_with_context(input, &mut Context::default())
+    }
+
+    fn process_with_context<'a>(
+        &self,
+        input: Data<'a>,
+        context: &mut Context,
+    ) -> Result<Data<'a>, LibError> {
+        let (language, confidence) = self.detect(&Self::text(&input)?);
+        context.set_attribute(LANGUAGE_ATTRIBUTE, language);
+        context.set_attribute(LANGUAGE_CONFIDENCE_ATTRIBUTE, confidence);
+        Ok(input)
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::OwnedStr(s) => Ok(Value::String(s.clone())),
+            Data::CowStr(s) => Ok(Value::String(s.to_string())),
+            Data::VecCowStr(v) => {
+                serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
+            }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
+            Data::Json(_) => Err(LibError::InvalidInput(
+                "LanguageDetector will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_embedded() {
+        let detector = LanguageDetector::embedded();
+        let cases = [
+            (
+                "We are committed to preventing forced labour in our supply chain",
+                "en",
+            ),
+            (
+                "Nous nous engageons à lutter contre le travail forcé dans notre chaîne d'approvisionnement",
+                "fr",
+            ),
+            (
+                "Wir verpflichten uns, Zwangsarbeit in unserer Lieferkette zu verhindern",
+                "de",
+            ),
+            (
+                "Nos comprometemos a prevenir el trabajo forzoso en nuestra cadena de suministro",
+                "es",
+            ),
+            (
+                "Wij zetten ons in om dwangarbeid in onze toeleveringsketen te voorkomen",
+                "nl",
+            ),
+            (
+                "Мы обязуемся не допускать принудительного труда в нашей цепочке поставок",
+                "ru",
+            ),
+            ("我们致力于防止供应链中的强迫劳动", "zh"),
+        ];
+
+        for (text, expected) in cases {
+            let (language, confidence) = detector.detect(text);
+            assert_eq!(language, expected, "{}", text);
+            assert!(confidence > 0.5, "{}: {}", text, confidence);
+        }
+    }
+
+    #[test]
+    fn test_detect_close_languages() {
+        let detector = LanguageDetector::embedded();
+        let cases = [
+            ("Jag har inte tid idag, men vi ses imorgon.", "sv"),
+            ("Vi har ikke tid i dag, men vi ses i morgen.", "da"),
+            (
+                "O governo anunciou novas medidas para reduzir a inflação.",
+                "pt",
+            ),
+            (
+                "El gobierno anunció nuevas medidas para reducir la inflación.",
+                "es",
+            ),
+        ];
+
+        for (text, expected) in cases {
+            let (language, confidence) = detector.detect(text);
+            assert_eq!(language, expected, "{}", text);
+            // Close languages share most n-grams, the confidence doesn't saturate
+            assert!(
+                confidence > 0.5 && confidence < 0.99,
+                "{}: {}",
+                text,
+                confidence
+            );
+        }
+    }
+
+    #[test]
+    fn test_gibberish_low_confidence() {
+        let detector = LanguageDetector::embedded();
+        for text in ["asdf qwer zxcv", "xkcd qwpz", "zzzz"] {
+            let (_, confidence) = detector.detect(text);
+            assert!(confidence < 0.5, "{}: {}", text, confidence);
+        }
+
+        let detector = LanguageDetector::embedded().with_min_confidence(0.5);
+        assert_eq!(detector.detect("asdf qwer zxcv").0, "und");
+        assert_eq!(detector.detect("the children").0, "en");
+    }
+
+    #[test]
+    fn test_undetermined() {
+        let detector = LanguageDetector::embedded();
+        assert_eq!(detector.detect("2023 - 42%"), ("und", 0.0));
+
+        let detector = LanguageDetector::embedded().with_min_confidence(1.1);
+        assert_eq!(detector.detect("forced labour").0, "und");
+    }
+
+    #[test]
+    fn test_records_attributes() {
+        let detector = LanguageDetector::embedded();
+        let mut context = Context::default();
+        let input = vec![Cow::Borrowed("the"), Cow::Borrowed("children")];
+
+        let result = detector
+            .process_with_context(Data::VecCowStr(input), &mut context)
+            .expect("Failed to process input");
+
+        // Input is passed through untouched
+        assert!(matches!(result, Data::VecCowStr(v) if v == vec!["the", "children"]));
+        assert_eq!(
+            context.attribute(LANGUAGE_ATTRIBUTE),
+            Some(&Value::from("en"))
+        );
+        assert!(context.attribute(LANGUAGE_CONFIDENCE_ATTRIBUTE).is_some());
+    }
+
+    #[test]
+    fn test_restrict_languages() {
+        let detector = LanguageDetector::embedded()
+            .with_languages(Some(vec!["da".to_string(), "sv".to_string()]))
+            .unwrap();
+        assert_eq!(detector.languages(), vec!["da", "sv"]);
+
+        let result = LanguageDetector::embedded().with_languages(Some(vec!["xx".to_string()]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_profiles_file() {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let mut samples = HashMap::new();
+        for (language, text) in [("aa", "aaa aab aba"), ("bb", "bbb bba bab")] {
+            let path = dir.path().join(format!("{}.txt", language));
+            let mut file = File::create(&path).expect("Failed to create temp file");
+            write!(file, "{}", text).expect("Failed to write test data");
+            samples.insert(language.to_string(), path.to_string_lossy().to_string());
+        }
+
+        let profiles_path = dir.path().join("profiles.json");
+        LanguageDetector::write_profiles(samples, profiles_path.clone()).unwrap();
+
+        let detector = LanguageDetector::from_file(profiles_path).unwrap();
+        assert_eq!(detector.languages(), vec!["aa", "bb"]);
+        assert_eq!(detector.detect("baba abba").0, "bb");
+        assert_eq!(detector.detect("aaba").0, "aa");
+
+        // Unknown to every profile, rather than going to the smallest one
+        assert_eq!(detector.detect("xyz"), ("und", 0.0));
+    }
+
+    #[test]
+    fn test_embedded_profiles_shared() {
+        let first = LanguageDetector::embedded();
+        let second = LanguageDetector::embedded();
+        assert!(Arc::ptr_eq(&first.profiles["en"], &second.profiles["en"]));
+    }
+}
@@ -1,9 +1,204 @@
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use pyo3::{exceptions::PyRuntimeError, pyclass, pymethods, PyErr};
+use arc_swap::ArcSwap;
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::PyRuntimeError,
+    pyclass, pymethods,
+    types::{PyAnyMethods, PyDict, PyDictMethods},
+    Bound, PyAny, PyErr, PyResult, Python,
+};
+#[cfg(feature = "python")]
+use pythonize::pythonize;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType, Token},
+    pipeline_builder::Processor,
+    registry::{annotated_output, ParamKind, ParamSpec, ProcessorSpec},
+};
+
+use super::{
+    case_pattern::CasePattern,
+    compiled_dictionary::{CompiledDictionary, DictionaryKind, Entry},
+    dictionary::{self, DictionaryIssue, IssueKind},
+    file_watcher::FileWatcher,
+    pos_tagger::{is_verb_tag, POS_TAG},
+};
+
+/// Tag recording which method produced the lemma
+const LEMMA_SOURCE_TAG: &str = "lemma_source";
+
+/// Columns of the lemma map CSV
+const COLUMNS: &[&str] = &["lemma", "derivatives"];
+
+/// Lemma map embedded with the `bundled-data` feature
+#[cfg(feature = "bundled-data")]
+const EMBEDDED_LEMMA_MAP: &str = include_str!("../../data/lemma_map.csv");
+
+/// English inflectional suffixes and their replacements
+/// used by the rule-based fallback, tried in order
+const INFLECTION_RULES: &[(&str, &str)] = &[
+    ("ies", "y"),
+    ("ied", "y"),
+    ("ier", "y"),
+    ("iest", "y"),
+    ("ves", "f"),
+    ("ves", "fe"),
+    ("es", ""),
+    ("es", "e"),
+    ("s", ""),
+    ("ing", ""),
+    ("ing", "e"),
+    ("ed", ""),
+    ("ed", "e"),
+    ("est", ""),
+    ("est", "e"),
+    ("er", ""),
+    ("er", "e"),
+];
+
+/// Which method produced the lemma of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LemmaSource {
+    /// The token is a lemma itself
+    Lemma,
+    /// The token is a known derivative
+    Derivative,
+    /// Inflectional suffix rules produced a known lemma
+    Rule,
+    /// Porter stemmer fallback
+    Porter,
+    /// Left unchanged
+    Unknown,
+}
+
+impl LemmaSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LemmaSource::Lemma => "lemma",
+            LemmaSource::Derivative => "derivative",
+            LemmaSource::Rule => "rule",
+            LemmaSource::Porter => "porter",
+            LemmaSource::Unknown => "unknown",
+        }
+    }
+}
+
+/// Everything `Lemmatizer::reload` replaces at once
+#[derive(Debug, Default)]
+struct LemmaDictionary {
+    lemma_map: HashMap<String, Vec<String>>,
+    /// Having a derivative map for reverse lookup
+    /// takes this from O(n * m) to O(n)
+    derivative_map: HashMap<String, String>,
+    /// Used instead of the maps above (which are then empty)
+    /// when the lemma map is a file created by `compile`
+    compiled: Option<Arc<CompiledDictionary>>,
+}
+
+impl LemmaDictionary {
+    /// A repeated lemma keeps the derivatives of its last pair. A
+    /// derivative of several lemmas (the bundled map lists "was" under
+    /// both "be" and "wa") maps to the first of them, in the order of
+    /// `pairs`, i.e. file order for CSVs
+    fn from_pairs(pairs: impl IntoIterator<Item = (String, Vec<String>)>) -> Self {
+        let mut lemmas = Vec::new();
+        let mut lemma_map: HashMap<String, Vec<String>> = HashMap::new();
+        for (lemma, derivatives) in pairs {
+            if lemma_map.insert(lemma.clone(), derivatives).is_none() {
+                lemmas.push(lemma);
+            }
+        }
+
+        let mut derivative_map = HashMap::new();
+        for lemma in &lemmas {
+            for derivative in &lemma_map[lemma] {
+                derivative_map
+                    .entry(derivative.clone())
+                    .or_insert_with(|| lemma.clone());
+            }
+        }
+
+        Self {
+            lemma_map,
+            derivative_map,
+            compiled: None,
+        }
+    }
+
+    /// Loads either a CSV or a dictionary created by `Lemmatizer::compile`
+    fn load(path: &Path) -> Result<Self, LibError> {
+        if CompiledDictionary::is_compiled(path) {
+            return Ok(Self {
+                compiled: Some(Arc::new(CompiledDictionary::open(
+                    path,
+                    DictionaryKind::Lemma,
+                )?)),
+                ..Default::default()
+            });
+        }
+
+        Ok(Self::from_pairs(Lemmatizer::load_map(path.to_path_buf())?))
+    }
+
+    /// Copy keyed by lowercased words, the lemmas
+    /// themselves keep their casing (e.g. proper nouns)
+    fn lowercased(&self) -> Self {
+        let mut lemma_map: HashMap<String, Vec<String>> =
+            HashMap::with_capacity(self.lemma_map.len());
+        for (lemma, derivatives) in &self.lemma_map {
+            lemma_map
+                .entry(lemma.to_lowercase())
+                .or_default()
+                .extend(derivatives.iter().cloned());
+        }
+
+        // Of derivatives differing only by case, the lowercase
+        // one wins, then the first in alphabetical order
+        let mut derivatives: Vec<(&String, &String)> = self.derivative_map.iter().collect();
+        derivatives.sort_by_key(|(derivative, _)| {
+            (
+                derivative.chars().any(char::is_uppercase),
+                derivative.as_str(),
+            )
+        });
+
+        let mut derivative_map = HashMap::with_capacity(self.derivative_map.len());
+        for (derivative, lemma) in derivatives {
+            derivative_map
+                .entry(derivative.to_lowercase())
+                .or_insert_with(|| lemma.clone());
+        }
+
+        Self {
+            lemma_map,
+            derivative_map,
+            compiled: self.compiled.clone(),
+        }
+    }
+
+    fn is_lemma(&self, key: &str) -> bool {
+        match &self.compiled {
+            Some(dictionary) => dictionary.get(key).is_some_and(|(_, is_lemma)| is_lemma),
+            None => self.lemma_map.contains_key(key),
+        }
+    }
+
+    /// Lemma of the word if it's a known derivative
+    fn lemma_of(&self, key: &str) -> Option<&str> {
+        match &self.compiled {
+            Some(dictionary) => dictionary.get(key).and_then(|(lemma, _)| lemma),
+            None => self.derivative_map.get(key).map(String::as_str),
+        }
+    }
+}
 
 /// Lemmatizer using:
 ///
@@ -11,92 +206,537 @@ use crate::{error::LibError, model::Data, pipeline_builder::Processor};
 /// Compiled by Referencing British National Corpus
 /// ASSUMES USAGE OF BRITISH ENGLISH
 /// SOURCE: https://github.com/skywind3000/lemma.en
-#[pyclass]
+///
+/// The dictionary is shared between clones, so every pipeline
+/// built with the same `Lemmatizer` uses one copy of it, and
+/// picks up new versions of it from `reload`
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct Lemmatizer {
-    lemma_map: HashMap<String, Vec<String>>,
-    /// Having a derivative map for reverse lookup
-    /// takes this from O(n * m) to O(n)
-    derivative_map: HashMap<String, String>,
+    dictionary: Arc<ArcSwap<LemmaDictionary>>,
+    /// File the dictionary was loaded from, for `reload`
+    path: Option<PathBuf>,
+    /// Reloads the dictionary when the file changes,
+    /// for as long as any clone is alive
+    watcher: Option<Arc<FileWatcher>>,
+    /// When set, both maps are keyed by lowercased words
+    /// and the casing of the input word is re-applied
+    /// to the lemma (RUNNING -> RUN, Running -> Run)
+    case_insensitive: bool,
+    /// For words in neither map, strip inflectional
+    /// suffixes and accept the result if it's a known lemma
+    rules_fallback: bool,
+    /// For words nothing else matched, use the Porter stemmer
+    porter_fallback: bool,
+    /// When set, the output is `Data::VecToken` with every token
+    /// tagged with the method that produced it (`"lemma_source"`)
+    annotate: bool,
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl Lemmatizer {
-    #[new]
-    pub fn new(lemma_map_path: String) -> Result<Self, pyo3::PyErr> {
-        let lemma_map = Self::load_map(PathBuf::from(lemma_map_path))
+    /// Reloads the lemma map from its file. Documents already
+    /// being lemmatized finish with the previous version
+    #[pyo3(name = "reload")]
+    pub fn py_reload(&self) -> Result<(), PyErr> {
+        self.reload()
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+
+    /// Lemmatizer from a `lemma -> derivatives` dict, a derivative of
+    /// several lemmas maps to the first of them in the dict
+    #[staticmethod]
+    #[pyo3(
+        name = "from_dict",
+        signature = (
+            lemma_map,
+            case_insensitive = false,
+            rules_fallback = false,
+            porter_fallback = false,
+            annotate = false,
+        )
+    )]
+    pub fn py_from_dict(
+        lemma_map: &Bound<'_, PyDict>,
+        case_insensitive: bool,
+        rules_fallback: bool,
+        porter_fallback: bool,
+        annotate: bool,
+    ) -> PyResult<Self> {
+        // In dict order, which decides shared derivatives
+        let pairs = lemma_map
+            .iter()
+            .map(|(lemma, derivatives)| Ok((lemma.extract()?, derivatives.extract()?)))
+            .collect::<PyResult<Vec<(String, Vec<String>)>>>()?;
+
+        Ok(Self::from_pairs(pairs)
+            .with_case_insensitive(case_insensitive)
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))?
+            .with_fallbacks(rules_fallback, porter_fallback)
+            .with_annotations(annotate))
+    }
+
+    /// Checks a lemma map CSV, returning a list of
+    /// `{"line": 12, "kind": "chain", "message": "..."}`
+    #[staticmethod]
+    #[pyo3(name = "validate")]
+    pub fn py_validate(py: Python, lemma_map_path: String) -> PyResult<Bound<PyAny>> {
+        let issues = Self::validate(Path::new(&lemma_map_path))
             .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))?;
+        Ok(pythonize(py, &issues)?)
+    }
 
-        let mut derivative_map = HashMap::new();
-        for (lemma, derivatives) in &lemma_map {
+    /// Compiles a lemma map CSV into a memory-mapped dictionary,
+    /// which can be passed as `lemma_map_path` in place of the CSV
+    #[staticmethod]
+    #[pyo3(name = "compile")]
+    pub fn py_compile(lemma_map_path: String, output_path: String) -> Result<(), PyErr> {
+        Self::compile(Path::new(&lemma_map_path), Path::new(&output_path))
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+}
+
+impl Lemmatizer {
+    /// `lemma_map_path` is either a CSV or a dictionary created by `compile`
+    pub fn new(lemma_map_path: String) -> Result<Self, LibError> {
+        let path = PathBuf::from(lemma_map_path);
+        let dictionary = LemmaDictionary::load(&path)?;
+
+        let mut lemmatizer = Self::from_dictionary(dictionary);
+        lemmatizer.path = Some(path);
+        Ok(lemmatizer)
+    }
+
+    /// Lemmatizer using the lemma map embedded with the `bundled-data`
+    /// feature. The map is only parsed once, and shared by every
+    /// lemmatizer created this way
+    pub fn embedded() -> Result<Self, LibError> {
+        #[cfg(feature = "bundled-data")]
+        {
+            static EMBEDDED: std::sync::OnceLock<Lemmatizer> = std::sync::OnceLock::new();
+            Ok(EMBEDDED
+                .get_or_init(|| {
+                    let lemma_map = Self::read_map(EMBEDDED_LEMMA_MAP.as_bytes(), "lemma_map.csv")
+                        .expect("Embedded lemma map is valid");
+                    Self::from_pairs(lemma_map)
+                })
+                .clone())
+        }
+
+        #[cfg(not(feature = "bundled-data"))]
+        Err(LibError::InvalidInput(
+            "algoforge was built without the bundled-data feature".to_string(),
+        ))
+    }
+
+    /// Lemmatizer from (lemma, derivatives) pairs, e.g. generated
+    /// at runtime. For repeated lemmas the last pair wins, and a
+    /// derivative of several lemmas maps to the first of them
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (String, Vec<String>)>) -> Self {
+        Self::from_dictionary(LemmaDictionary::from_pairs(pairs))
+    }
+
+    /// Compiles a lemma map CSV into a dictionary for `new`
+    /// A derivative of several lemmas maps to the first
+    /// of them in the file, as with the CSV itself
+    pub fn compile(lemma_map_path: &Path, output_path: &Path) -> Result<(), LibError> {
+        let dictionary = LemmaDictionary::from_pairs(Self::load_map(lemma_map_path.to_path_buf())?);
+
+        let mut entries: BTreeMap<String, Entry> = BTreeMap::new();
+        for lemma in dictionary.lemma_map.keys() {
+            entries.entry(lemma.clone()).or_default().flag = true;
+        }
+        for (derivative, lemma) in dictionary.derivative_map {
+            entries.entry(derivative).or_default().target = Some(lemma);
+        }
+
+        CompiledDictionary::write(output_path, DictionaryKind::Lemma, &entries)
+    }
+
+    fn from_dictionary(dictionary: LemmaDictionary) -> Self {
+        Self {
+            dictionary: Arc::new(ArcSwap::from_pointee(dictionary)),
+            path: None,
+            watcher: None,
+            case_insensitive: false,
+            rules_fallback: false,
+            porter_fallback: false,
+            annotate: false,
+        }
+    }
+
+    pub fn with_fallbacks(mut self, rules_fallback: bool, porter_fallback: bool) -> Self {
+        self.rules_fallback = rules_fallback;
+        self.porter_fallback = porter_fallback;
+        self
+    }
+
+    pub fn with_annotations(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    /// Compiled dictionaries are looked up with the lowercased
+    /// word, so their source CSV should be lowercase
+    ///
+    /// The lowercased dictionary isn't shared with earlier clones,
+    /// so a watcher set before is moved over to it
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Result<Self, LibError> {
+        if case_insensitive && !self.case_insensitive {
+            let lowered = self.dictionary.load().lowercased();
+            self.dictionary = Arc::new(ArcSwap::from_pointee(lowered));
+            self.case_insensitive = true;
+            let watch = self.watcher.is_some();
+            return self.with_watch(watch);
+        }
+        self.case_insensitive = case_insensitive;
+        Ok(self)
+    }
+
+    /// Reloads the lemma map whenever its file changes
+    pub fn with_watch(mut self, watch: bool) -> Result<Self, LibError> {
+        self.watcher = None;
+        if watch {
+            let path = self.path.clone().ok_or_else(|| {
+                LibError::InvalidInput(
+                    "Only lemma maps loaded from a file can be watched".to_string(),
+                )
+            })?;
+            let lemmatizer = self.clone();
+            self.watcher = Some(Arc::new(FileWatcher::new(&path, move || {
+                lemmatizer.reload()
+            })?));
+        }
+        Ok(self)
+    }
+
+    /// Reloads the lemma map from its file and swaps it in for
+    /// every clone of this lemmatizer. Documents already being
+    /// lemmatized finish with the previous version. If the file
+    /// can't be loaded, the previous version stays in use
+    pub fn reload(&self) -> Result<(), LibError> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            LibError::InvalidInput("Only lemma maps loaded from a file can be reloaded".to_string())
+        })?;
+
+        let mut dictionary = LemmaDictionary::load(path)?;
+        if self.case_insensitive {
+            dictionary = dictionary.lowercased();
+        }
+        self.dictionary.store(Arc::new(dictionary));
+        Ok(())
+    }
+
+    /// Whether the word is either a lemma or a derivative
+    pub(crate) fn is_known(&self, word: &str) -> bool {
+        let key = if self.case_insensitive {
+            Cow::Owned(word.to_lowercase())
+        } else {
+            Cow::Borrowed(word)
+        };
+        let dictionary = self.dictionary.load();
+        dictionary.is_lemma(&key) || dictionary.lemma_of(&key).is_some()
+    }
+
+    /// `verb` is set for tokens a `PosTagger` tagged as verbs,
+    /// in which case a derivative reading wins over the word
+    /// being a lemma itself (saw -> see, but the saw -> saw)
+    ///
+    /// That's the only use of the tag: the lemma map has a single
+    /// lemma per derivative, so derivatives with a different lemma
+    /// per POS (leaves -> leaf or leave) always get the same one
+    fn lemmatize<'a>(
+        &self,
+        dictionary: &LemmaDictionary,
+        word: Cow<'a, str>,
+        verb: bool,
+    ) -> (Cow<'a, str>, LemmaSource) {
+        let lowered;
+        let key: &str = if self.case_insensitive {
+            lowered = word.to_lowercase();
+            &lowered
+        } else {
+            &word
+        };
+
+        // Keep the original Cow if it's already a lemma
+        if dictionary.is_lemma(key) && !(verb && dictionary.lemma_of(key).is_some()) {
+            return (word, LemmaSource::Lemma);
+        }
+
+        let (lemma, source) = if let Some(lemma) = dictionary.lemma_of(key) {
+            (lemma.to_string(), LemmaSource::Derivative)
+        } else if let Some(lemma) = self
+            .rules_fallback
+            .then(|| Self::apply_rules(dictionary, key))
+            .flatten()
+        {
+            (lemma, LemmaSource::Rule)
+        } else if self.porter_fallback {
+            (porter_stemmer::stem(key), LemmaSource::Porter)
+        } else {
+            // If not found, keep the original
+            return (word, LemmaSource::Unknown);
+        };
+
+        if self.case_insensitive {
+            (Cow::Owned(CasePattern::detect(&word).apply(&lemma)), source)
+        } else {
+            (Cow::Owned(lemma), source)
+        }
+    }
+
+    /// Strips inflectional suffixes (subcontractors, onboarded, stopped)
+    /// and returns the first candidate that is a known lemma
+    fn apply_rules(dictionary: &LemmaDictionary, word: &str) -> Option<String> {
+        INFLECTION_RULES.iter().find_map(|(suffix, replacement)| {
+            let stem = word.strip_suffix(suffix)?;
+            if stem.chars().count() < 2 || (*suffix == "s" && stem.ends_with('s')) {
+                return None;
+            }
+
+            let candidate = format!("{}{}", stem, replacement);
+            if dictionary.is_lemma(&candidate) {
+                return Some(candidate);
+            }
+
+            // Undo consonant doubling (stopped -> stop, running -> run)
+            let mut chars = stem.chars().rev();
+            match (chars.next(), chars.next()) {
+                (Some(last), Some(before))
+                    if replacement.is_empty()
+                        && last == before
+                        && !matches!(last, 'a' | 'e' | 'i' | 'o' | 'u' | 's' | 'l') =>
+                {
+                    let candidate = &stem[..stem.len() - last.len_utf8()];
+                    dictionary
+                        .is_lemma(candidate)
+                        .then(|| candidate.to_string())
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn lemmatize_token<'a>(&self, dictionary: &LemmaDictionary, token: Token<'a>) -> Token<'a> {
+        let Token { text, mut tags } = token;
+        let verb = tags.get(POS_TAG).is_some_and(|tag| is_verb_tag(tag));
+        let (text, source) = self.lemmatize(dictionary, text, verb);
+        tags.insert(LEMMA_SOURCE_TAG, source.as_str().to_string());
+        Token { text, tags }
+    }
+
+    /// (lemma, derivatives) pairs in file order
+    fn load_map(path: PathBuf) -> Result<Vec<(String, Vec<String>)>, LibError> {
+        let rows = dictionary::read_file_rows(&path, COLUMNS)?;
+        Ok(Self::parse_rows(rows)
+            .into_iter()
+            .map(|(_, lemma, derivatives)| (lemma, derivatives))
+            .collect())
+    }
+
+    #[cfg(feature = "bundled-data")]
+    fn read_map(
+        source: impl std::io::Read,
+        file: &str,
+    ) -> Result<Vec<(String, Vec<String>)>, LibError> {
+        let rows = dictionary::read_rows(source, file, COLUMNS)?;
+        Ok(Self::parse_rows(rows)
+            .into_iter()
+            .map(|(_, lemma, derivatives)| (lemma, derivatives))
+            .collect())
+    }
+
+    /// (line, lemma, derivatives) of every row
+    fn parse_rows(rows: Vec<(u64, csv::StringRecord)>) -> Vec<(u64, String, Vec<String>)> {
+        rows.into_iter()
+            .map(|(line, record)| {
+                let lemma = record[0].to_string();
+                let derivatives = &record[1];
+
+                let split_derivatives = if derivatives.contains(",") {
+                    derivatives
+                        .split(",")
+                        .map(|s| s.trim().to_string())
+                        .collect()
+                } else {
+                    vec![derivatives.to_string()]
+                };
+
+                (line, lemma, split_derivatives)
+            })
+            .collect()
+    }
+
+    /// Checks a lemma map CSV for duplicates, empty entries,
+    /// self-mappings and chains (a lemma that is itself a
+    /// derivative of another lemma), in line order.
+    /// None of these stop the map from loading
+    pub fn validate(lemma_map_path: &Path) -> Result<Vec<DictionaryIssue>, LibError> {
+        let rows = Self::parse_rows(dictionary::read_file_rows(lemma_map_path, COLUMNS)?);
+
+        let mut issues = Vec::new();
+        let mut lemmas: HashMap<&str, u64> = HashMap::new();
+        // derivative -> (line, lemma) where it was first listed
+        let mut derivatives_seen: HashMap<&str, (u64, &str)> = HashMap::new();
+
+        for (line, lemma, derivatives) in &rows {
+            let line = *line;
+            if lemma.trim().is_empty() {
+                issues.push(DictionaryIssue::new(line, IssueKind::Empty, "Empty lemma"));
+            }
+            if let Some(first) = lemmas.insert(lemma, line) {
+                issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::Duplicate,
+                    format!(
+                        "Lemma `{}` is also on line {}, the last row wins",
+                        lemma, first
+                    ),
+                ));
+            }
+
+            let mut listed = HashSet::new();
             for derivative in derivatives {
-                derivative_map.insert(derivative.clone(), lemma.clone());
+                if derivative.trim().is_empty() {
+                    issues.push(DictionaryIssue::new(
+                        line,
+                        IssueKind::Empty,
+                        format!("Empty derivative of `{}`", lemma),
+                    ));
+                } else if !listed.insert(derivative) {
+                    issues.push(DictionaryIssue::new(
+                        line,
+                        IssueKind::Duplicate,
+                        format!("`{}` is listed twice for `{}`", derivative, lemma),
+                    ));
+                } else if derivative == lemma {
+                    issues.push(DictionaryIssue::new(
+                        line,
+                        IssueKind::SelfMapping,
+                        format!("`{}` is listed as its own derivative", lemma),
+                    ));
+                } else {
+                    match derivatives_seen.get(derivative.as_str()) {
+                        Some((first, other)) if other != lemma => {
+                            issues.push(DictionaryIssue::new(
+                                line,
+                                IssueKind::Duplicate,
+                                format!(
+                                    "`{}` is a derivative of both `{}` (line {}) and `{}`",
+                                    derivative, other, first, lemma
+                                ),
+                            ));
+                        }
+                        Some(_) => {}
+                        None => {
+                            derivatives_seen.insert(derivative, (line, lemma));
+                        }
+                    }
+                }
             }
         }
-        Ok(Self {
-            lemma_map,
-            derivative_map,
-        })
+
+        for (line, lemma, _) in &rows {
+            if let Some((first, other)) = derivatives_seen.get(lemma.as_str()) {
+                issues.push(DictionaryIssue::new(
+                    *line,
+                    IssueKind::Chain,
+                    format!(
+                        "Lemma `{}` is also a derivative of `{}` (line {})",
+                        lemma, other, first
+                    ),
+                ));
+            }
+        }
+
+        issues.sort_by_key(|issue| issue.line);
+        Ok(issues)
     }
 }
 
 impl Lemmatizer {
-    fn load_map(path: PathBuf) -> Result<HashMap<String, Vec<String>>, LibError> {
-        let mut reader = csv::Reader::from_path(path)
-            .map_err(|e| LibError::IO(format!("Failed to read spelling map: {}", e)))?;
-
-        let mut spelling_map = HashMap::new();
-
-        for result in reader.records() {
-            let record =
-                result.map_err(|e| LibError::IO(format!("Failed to read record: {}", e)))?;
-
-            let lemma = record
-                .get(0)
-                .expect("Failed to get target word")
-                .to_string();
-
-            let derivatives = record
-                .get(1)
-                .expect("Failed to get derivatives")
-                .to_string();
-
-            let split_derivatives = if derivatives.contains(",") {
-                derivatives
-                    .split(",")
-                    .map(|s| s.trim().to_string())
-                    .collect()
-            } else {
-                vec![derivatives.to_string()]
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("Lemmatizer", |params| {
+            let lemmatizer = match params.get::<Option<String>>("lemma_map_path")? {
+                Some(path) => Self::new(path)?,
+                None => Self::embedded()?,
             };
-
-            spelling_map.insert(lemma, split_derivatives);
-        }
-
-        Ok(spelling_map)
+            lemmatizer
+                .with_case_insensitive(params.get("case_insensitive")?)?
+                .with_fallbacks(
+                    params.get("rules_fallback")?,
+                    params.get("porter_fallback")?,
+                )
+                .with_annotations(params.get("annotate")?)
+                .with_watch(params.get("watch")?)
+        })
+        .with_description("Replaces every token with its lemma")
+        .with_param(ParamSpec::optional(
+            "lemma_map_path",
+            ParamKind::String,
+            Value::Null,
+            "CSV or compiled lemma map, the bundled one if unset",
+        ))
+        .with_param(ParamSpec::optional(
+            "case_insensitive",
+            ParamKind::Bool,
+            false,
+            "Look words up regardless of their casing",
+        ))
+        .with_param(ParamSpec::optional(
+            "rules_fallback",
+            ParamKind::Bool,
+            false,
+            "Strip inflectional suffixes of unknown words",
+        ))
+        .with_param(ParamSpec::optional(
+            "porter_fallback",
+            ParamKind::Bool,
+            false,
+            "Stem words nothing else matched",
+        ))
+        .with_param(ParamSpec::optional(
+            "annotate",
+            ParamKind::Bool,
+            false,
+            "Tag every token with the method that produced its lemma",
+        ))
+        .with_param(ParamSpec::optional(
+            "watch",
+            ParamKind::Bool,
+            false,
+            "Reload the lemma map when its file changes",
+        ))
+        .with_inputs(&[DataType::ListStr, DataType::ListDict])
+        .with_output_fn(annotated_output)
+        .with_python::<Self>()
     }
 }
 
 impl Processor for Lemmatizer {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        // The whole document uses the same version of the dictionary
+        let dictionary = self.dictionary.load();
         match input {
+            Data::VecCowStr(v) if self.annotate => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|word| self.lemmatize_token(&dictionary, Token::new(word)))
+                    .collect(),
+            )),
             Data::VecCowStr(v) => Ok(Data::VecCowStr(
                 v.into_iter()
-                    .map(|word| {
-                        // Keep the original Cow if it's already a lemma
-                        if self.lemma_map.contains_key(word.as_ref()) {
-                            word
-                        } else if self.derivative_map.contains_key(word.as_ref()) {
-                            Cow::Owned(self.derivative_map.get(word.as_ref()).unwrap().clone())
-                        } else {
-                            // If not found, keep the original
-                            word
-                        }
-                    })
+                    .map(|word| self.lemmatize(&dictionary, word, false).0)
+                    .collect(),
+            )),
+            Data::VecToken(v) => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|token| self.lemmatize_token(&dictionary, token))
                     .collect(),
             )),
             _ => Err(LibError::InvalidInput(
-                "Lemmatizer only accepts Data::VecCowStr as input".to_string(),
+                "Lemmatizer only accepts Data::VecCowStr or Data::VecToken as input".to_string(),
             )),
         }
     }
@@ -106,6 +746,7 @@ impl Processor for Lemmatizer {
             Data::VecCowStr(v) => {
                 serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
             }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
             _ => Err(LibError::InvalidInput(
                 "Lemmatizer will never output this type".to_string(),
             )),
@@ -171,7 +812,8 @@ mod tests {
         let lemmatizer = Lemmatizer::new(path).unwrap();
 
         // Check internal map structure
-        let be_derivatives = lemmatizer.lemma_map.get("be").unwrap();
+        let dictionary = lemmatizer.dictionary.load();
+        let be_derivatives = dictionary.lemma_map.get("be").unwrap();
         assert!(be_derivatives.contains(&"is".to_string()));
         assert!(be_derivatives.contains(&"was".to_string()));
         assert!(be_derivatives.contains(&"are".to_string()));
@@ -331,4 +973,424 @@ mod tests {
             panic!("Expected Data::VecCowStr");
         }
     }
+
+    #[test]
+    fn test_case_insensitive_keeps_lemma_casing() {
+        let csv_content = "lemma,derivatives\nEuropean,Europeans\nrun,Runs";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let input = vec![
+            Cow::Borrowed("europeans"),
+            Cow::Borrowed("EUROPEANS"),
+            Cow::Borrowed("runs"),
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["European", "EUROPEAN", "run"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_case_insensitive_restores_casing() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was, are\"\nrun,\"runs, ran, running\"";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let input = vec![
+            Cow::Borrowed("RUNNING"),
+            Cow::Borrowed("Ran"),
+            Cow::Borrowed("is"),
+            Cow::Borrowed("Be"), // already a lemma - should stay borrowed
+            Cow::Borrowed("Unknown"),
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert!(matches!(&output_vec[0], Cow::Owned(s) if s == "RUN"));
+            assert!(matches!(&output_vec[1], Cow::Owned(s) if s == "Run"));
+            assert!(matches!(&output_vec[2], Cow::Owned(s) if s == "be"));
+            assert!(matches!(&output_vec[3], Cow::Borrowed(s) if *s == "Be"));
+            assert!(matches!(&output_vec[4], Cow::Borrowed(s) if *s == "Unknown"));
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_rules_fallback() {
+        let csv_content = "lemma,derivatives\nsubcontractor,subcontractor\nonboard,onboard\nstop,stops\nsubsidiary,subsidiary\nrule,rules";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path).unwrap().with_fallbacks(true, false);
+        let input = vec![
+            Cow::Borrowed("subcontractors"),
+            Cow::Borrowed("onboarded"),
+            Cow::Borrowed("stopped"),
+            Cow::Borrowed("subsidiaries"),
+            Cow::Borrowed("ruling"),
+            Cow::Borrowed("unknowns"), // "unknown" is not a lemma
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(
+                output_vec,
+                vec![
+                    "subcontractor",
+                    "onboard",
+                    "stop",
+                    "subsidiary",
+                    "rule",
+                    "unknowns"
+                ]
+            );
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_annotated_sources() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was\"\nsubcontractor,subcontractor";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path)
+            .unwrap()
+            .with_fallbacks(true, true)
+            .with_annotations(true);
+        let input = vec![
+            Cow::Borrowed("be"),
+            Cow::Borrowed("was"),
+            Cow::Borrowed("subcontractors"),
+            Cow::Borrowed("connections"),
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecToken(output_vec) = result {
+            let sources: Vec<_> = output_vec
+                .iter()
+                .map(|t| (t.text.as_ref(), t.tag(LEMMA_SOURCE_TAG).unwrap()))
+                .collect();
+            assert_eq!(
+                sources,
+                vec![
+                    ("be", "lemma"),
+                    ("be", "derivative"),
+                    ("subcontractor", "rule"),
+                    ("connect", "porter"),
+                ]
+            );
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+
+    #[test]
+    fn test_annotated_unknown() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was\"";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path).unwrap().with_annotations(true);
+        let input = vec![Token::new(Cow::Borrowed("xyzzy")).with_tag("spelling", "rule")];
+
+        let result = lemmatizer
+            .process(Data::VecToken(input))
+            .expect("Failed to process input");
+        if let Data::VecToken(output_vec) = result {
+            assert_eq!(output_vec[0].tag(LEMMA_SOURCE_TAG), Some("unknown"));
+            // Existing tags are kept
+            assert_eq!(output_vec[0].tag("spelling"), Some("rule"));
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+
+    #[test]
+    fn test_pos_specific_lemmas() {
+        let csv_content = "lemma,derivatives\nsee,\"sees, saw, seen\"\nsaw,\"saws, sawed\"";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path).unwrap();
+        let input = vec![
+            Token::new(Cow::Borrowed("saw")).with_tag(POS_TAG, "VERB"),
+            Token::new(Cow::Borrowed("saw")).with_tag(POS_TAG, "NOUN"),
+            Token::new(Cow::Borrowed("saw")).with_tag(POS_TAG, "VBD"),
+            Token::new(Cow::Borrowed("saw")),
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecToken(input))
+            .expect("Failed to process input");
+        if let Data::VecToken(output_vec) = result {
+            let lemmas: Vec<_> = output_vec.iter().map(|t| t.text.as_ref()).collect();
+            assert_eq!(lemmas, vec!["see", "saw", "see", "saw"]);
+            assert_eq!(output_vec[0].tag(LEMMA_SOURCE_TAG), Some("derivative"));
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+
+    #[test]
+    fn test_compiled_lemma_map() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was, are\"\nsee,\"sees, saw, seen\"\nsaw,\"saws, sawed\"\nstop,stops";
+        let (dir, path) = create_test_csv(csv_content);
+        let compiled_path = dir.path().join("lemma_map.afdict");
+        Lemmatizer::compile(Path::new(&path), &compiled_path).unwrap();
+
+        let from_csv = Lemmatizer::new(path).unwrap().with_fallbacks(true, false);
+        let compiled = Lemmatizer::new(compiled_path.to_string_lossy().to_string())
+            .unwrap()
+            .with_fallbacks(true, false);
+        assert!(compiled.dictionary.load().lemma_map.is_empty());
+
+        let input = vec![
+            Token::new(Cow::Borrowed("is")),
+            Token::new(Cow::Borrowed("be")),
+            Token::new(Cow::Borrowed("saw")).with_tag(POS_TAG, "VERB"),
+            Token::new(Cow::Borrowed("saw")),
+            Token::new(Cow::Borrowed("stopped")),
+            Token::new(Cow::Borrowed("unknown")),
+        ];
+        assert_eq!(
+            compiled
+                .process(Data::VecToken(input.clone()))
+                .unwrap()
+                .to_value()
+                .unwrap(),
+            from_csv
+                .process(Data::VecToken(input))
+                .unwrap()
+                .to_value()
+                .unwrap()
+        );
+        assert!(compiled.is_known("sees"));
+        assert!(!compiled.is_known("unknown"));
+    }
+
+    #[test]
+    fn test_clones_share_maps() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was, are\"";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let lemmatizer = Lemmatizer::new(path).unwrap();
+        let clone = lemmatizer.clone().with_fallbacks(true, true);
+        assert!(Arc::ptr_eq(&lemmatizer.dictionary, &clone.dictionary));
+
+        // Only rebuilt for the clone that needs lowercased keys
+        let lowered = clone.with_case_insensitive(true).unwrap();
+        assert!(!Arc::ptr_eq(&lemmatizer.dictionary, &lowered.dictionary));
+    }
+
+    #[cfg(feature = "bundled-data")]
+    #[test]
+    fn test_embedded() {
+        let lemmatizer = Lemmatizer::embedded().unwrap();
+        let input = vec![Cow::Borrowed("was"), Cow::Borrowed("went")];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["be", "go"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        // Parsed once
+        let other = Lemmatizer::embedded().unwrap();
+        assert!(Arc::ptr_eq(&lemmatizer.dictionary, &other.dictionary));
+    }
+
+    #[cfg(not(feature = "bundled-data"))]
+    #[test]
+    fn test_embedded_requires_feature() {
+        assert!(Lemmatizer::embedded().is_err());
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let lemmatizer = Lemmatizer::from_pairs([
+            ("be".to_string(), vec!["is".to_string(), "was".to_string()]),
+            ("onboard".to_string(), vec!["onboarded".to_string()]),
+        ]);
+        let input = vec![
+            Cow::Borrowed("was"),
+            Cow::Borrowed("onboarded"),
+            Cow::Borrowed("be"),
+        ];
+
+        let result = lemmatizer
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["be", "onboard", "be"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_shared_derivative() {
+        // Same as the bundled map, which lists "was" under "be" and "wa"
+        let pairs = || {
+            [
+                ("wa".to_string(), vec!["was".to_string()]),
+                ("be".to_string(), vec!["is".to_string(), "was".to_string()]),
+            ]
+        };
+        let (dir, path) = create_test_csv("lemma,derivatives\nbe,\"is, was\"\nwa,was");
+        let compiled = dir.path().join("lemmas.fst");
+        Lemmatizer::compile(Path::new(&path), &compiled).unwrap();
+
+        for (lemmatizer, expected) in [
+            (Lemmatizer::from_pairs(pairs()), "wa"),
+            (Lemmatizer::new(path).unwrap(), "be"),
+            (
+                Lemmatizer::new(compiled.to_string_lossy().to_string()).unwrap(),
+                "be",
+            ),
+        ] {
+            let result = lemmatizer
+                .process(Data::VecCowStr(vec![Cow::Borrowed("was")]))
+                .expect("Failed to process input");
+            if let Data::VecCowStr(output_vec) = result {
+                assert_eq!(output_vec, vec![expected]);
+            } else {
+                panic!("Expected Data::VecCowStr");
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_row() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was\"\nrun\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let error = Lemmatizer::load_map(PathBuf::from(path)).unwrap_err();
+        assert!(error.to_string().contains("line 3: expected 2 columns"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let csv_content = "lemma,derivatives\nbe,\"is, was, is\"\nrun,\"runs, running,\"\nrunning,runnings\nsubcontractor,subcontractor\nbe,are\nrace,runs";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let issues: Vec<_> = Lemmatizer::validate(Path::new(&path))
+            .unwrap()
+            .into_iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (2, IssueKind::Duplicate),
+                (3, IssueKind::Empty),
+                (4, IssueKind::Chain),
+                (5, IssueKind::SelfMapping),
+                (6, IssueKind::Duplicate),
+                (7, IssueKind::Duplicate),
+            ]
+        );
+    }
+
+    fn lemmatize_words(lemmatizer: &Lemmatizer, words: &[&'static str]) -> Vec<String> {
+        let input = words.iter().map(|w| Cow::Borrowed(*w)).collect();
+        match lemmatizer.process(Data::VecCowStr(input)) {
+            Ok(Data::VecCowStr(output)) => output.into_iter().map(Cow::into_owned).collect(),
+            _ => panic!("Expected Data::VecCowStr"),
+        }
+    }
+
+    #[test]
+    fn test_reload() {
+        let (_dir, path) = create_test_csv(
+            "lemma,derivatives
+be,\"is, was\"\n",
+        );
+        let lemmatizer = Lemmatizer::new(path.clone())
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let clone = lemmatizer.clone();
+        assert_eq!(
+            lemmatize_words(&clone, &["Was", "Went"]),
+            vec!["Be", "Went"]
+        );
+
+        std::fs::write(
+            &path,
+            "lemma,derivatives\nbe,\"is, was\"\ngo,\"GOES, went\"\n",
+        )
+        .unwrap();
+        lemmatizer.reload().unwrap();
+        assert_eq!(
+            lemmatize_words(&clone, &["Was", "Went", "goes"]),
+            vec!["Be", "Go", "go"]
+        );
+
+        // A broken file leaves the current version in place
+        std::fs::write(&path, "lemma,derivatives\nbe\n").unwrap();
+        assert!(lemmatizer.reload().is_err());
+        assert_eq!(lemmatize_words(&clone, &["went"]), vec!["go"]);
+
+        let from_pairs = Lemmatizer::from_pairs([("be".to_string(), vec!["is".to_string()])]);
+        assert!(from_pairs.reload().is_err());
+        assert!(from_pairs.with_watch(true).is_err());
+    }
+
+    #[test]
+    fn test_watch() {
+        let (dir, path) = create_test_csv("lemma,derivatives\nbe,\"is, was\"\n");
+        let lemmatizer = Lemmatizer::new(path).unwrap().with_watch(true).unwrap();
+
+        // Replaced rather than written in place, as editors do
+        let replacement = dir.path().join("lemma_map.csv.new");
+        std::fs::write(&replacement, "lemma,derivatives\ngo,went\n").unwrap();
+        std::fs::rename(&replacement, dir.path().join("lemma_map.csv")).unwrap();
+
+        let reloaded = (0..100).any(|_| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            lemmatize_words(&lemmatizer, &["went"]) == vec!["go"]
+        });
+        assert!(reloaded);
+    }
+
+    #[test]
+    fn test_watch_then_case_insensitive() {
+        let (dir, path) = create_test_csv("lemma,derivatives\nbe,\"is, was\"\n");
+        let lemmatizer = Lemmatizer::new(path)
+            .unwrap()
+            .with_watch(true)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+
+        std::fs::write(
+            dir.path().join("lemma_map.csv"),
+            "lemma,derivatives\ngo,went\n",
+        )
+        .unwrap();
+
+        // The watcher reloads the lowercased dictionary in use
+        lemmatizer.watcher.as_ref().unwrap().trigger().unwrap();
+        assert_eq!(lemmatize_words(&lemmatizer, &["Went"]), vec!["Go"]);
+    }
 }
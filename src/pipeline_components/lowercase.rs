@@ -1,17 +1,21 @@
 use std::borrow::Cow;
 
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::pyclass;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct ToLowerCase;
 
-#[pymethods]
 impl ToLowerCase {
-    #[new]
     pub fn new() -> Self {
         Self
     }
@@ -23,6 +27,15 @@ impl Default for ToLowerCase {
     }
 }
 
+impl ToLowerCase {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("ToLowerCase", |_| Ok(Self::new()))
+            .with_description("Lowercases a string or every token")
+            .with_inputs(&[DataType::Str, DataType::ListStr])
+            .with_python::<Self>()
+    }
+}
+
 impl Processor for ToLowerCase {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
         match input {
@@ -1,9 +1,15 @@
 use std::borrow::Cow;
 
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::pyclass;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
 
 /// Porter Stemming Algorithm.
 /// Reduces words to their base or root form (stem)
@@ -14,13 +20,11 @@ use crate::{error::LibError, model::Data, pipeline_builder::Processor};
 /// Program, 14(3), 130-137, 1980.
 /// Uses the `porter_stemmer` crate.
 /// https://crates.io/crates/porter_stemmer
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct PorterStemmer;
 
-#[pymethods]
 impl PorterStemmer {
-    #[new]
     pub fn new() -> Self {
         Self
     }
@@ -32,6 +36,16 @@ impl Default for PorterStemmer {
     }
 }
 
+impl PorterStemmer {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("PorterStemmer", |_| Ok(Self::new()))
+            .with_description("Stems every token with the Porter algorithm")
+            .with_inputs(&[DataType::ListStr])
+            .with_output(DataType::ListStr)
+            .with_python::<Self>()
+    }
+}
+
 impl Processor for PorterStemmer {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
         match input {
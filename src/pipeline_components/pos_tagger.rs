@@ -0,0 +1,570 @@
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyRuntimeError, pyclass, pymethods, PyErr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Data, DataType, Token},
+    pipeline_builder::Processor,
+    registry::{ParamKind, ParamSpec, ProcessorSpec},
+};
+
+/// Tag holding the part-of-speech of a token
+pub(crate) const POS_TAG: &str = "pos";
+
+const START: [&str; 2] = ["-START-", "-START2-"];
+const END: [&str; 2] = ["-END-", "-END2-"];
+
+/// Words seen at least this many times in training...
+const TAGDICT_MIN_FREQUENCY: usize = 20;
+/// ...with (almost) always the same tag are tagged
+/// by lookup instead of by the model
+const TAGDICT_MIN_AMBIGUITY: f64 = 0.97;
+
+/// Averaged perceptron model, serialized to JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PerceptronModel {
+    /// feature -> tag -> weight
+    weights: HashMap<String, HashMap<String, f64>>,
+    /// Unambiguous frequent words -> tag
+    tagdict: HashMap<String, String>,
+    classes: Vec<String>,
+}
+
+impl PerceptronModel {
+    fn predict(&self, features: &HashMap<String, usize>) -> String {
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        for (feature, count) in features {
+            if let Some(weights) = self.weights.get(feature) {
+                for (tag, weight) in weights {
+                    *scores.entry(tag.as_str()).or_insert(0.0) += *count as f64 * weight;
+                }
+            }
+        }
+
+        self.classes
+            .iter()
+            .map(|tag| (scores.get(tag.as_str()).copied().unwrap_or(0.0), tag))
+            .max_by(|(a_score, a_tag), (b_score, b_tag)| {
+                a_score.total_cmp(b_score).then_with(|| a_tag.cmp(b_tag))
+            })
+            .map(|(_, tag)| tag.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Keeps running totals of the weights so the
+/// final weights can be averaged over every update
+#[derive(Default)]
+struct PerceptronTrainer {
+    model: PerceptronModel,
+    totals: HashMap<(String, String), f64>,
+    timestamps: HashMap<(String, String), usize>,
+    instances: usize,
+}
+
+impl PerceptronTrainer {
+    fn update(&mut self, truth: &str, guess: &str, features: &HashMap<String, usize>) {
+        self.instances += 1;
+        if truth == guess {
+            return;
+        }
+
+        for feature in features.keys() {
+            self.update_feature(truth, feature, 1.0);
+            self.update_feature(guess, feature, -1.0);
+        }
+    }
+
+    fn update_feature(&mut self, tag: &str, feature: &str, value: f64) {
+        let weight = self
+            .model
+            .weights
+            .entry(feature.to_string())
+            .or_default()
+            .entry(tag.to_string())
+            .or_insert(0.0);
+
+        let param = (feature.to_string(), tag.to_string());
+        let timestamp = self.timestamps.entry(param.clone()).or_insert(0);
+        *self.totals.entry(param).or_insert(0.0) += (self.instances - *timestamp) as f64 * *weight;
+        *timestamp = self.instances;
+        *weight += value;
+    }
+
+    fn average(mut self) -> PerceptronModel {
+        for (feature, weights) in self.model.weights.iter_mut() {
+            weights.retain(|tag, weight| {
+                let param = (feature.clone(), tag.clone());
+                let timestamp = self.timestamps.get(&param).copied().unwrap_or(0);
+                let total = self.totals.get(&param).copied().unwrap_or(0.0)
+                    + (self.instances - timestamp) as f64 * *weight;
+                *weight = (total / self.instances as f64 * 1000.0).round() / 1000.0;
+                *weight != 0.0
+            });
+        }
+        self.model.weights.retain(|_, weights| !weights.is_empty());
+        self.model
+    }
+}
+
+/// Part-of-speech tagger using an averaged perceptron
+///
+/// Tags `Data::VecCowStr` / `Data::VecToken` tokens
+/// and outputs `Data::VecToken` with a `"pos"` tag.
+/// The tagset is whatever the model was trained on
+/// (e.g. Universal Dependencies UPOS or Penn Treebank).
+///
+/// Based on Matthew Honnibal's "A Good Part-of-Speech Tagger
+/// in about 200 Lines of Python" (as used by NLTK and TextBlob)
+/// https://explosion.ai/blog/part-of-speech-pos-tagger-in-python
+///
+/// The model is shared between clones
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct PosTagger {
+    model: Arc<PerceptronModel>,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl PosTagger {
+    /// Trains a new tagger on a CoNLL-format corpus
+    /// (one token per line, blank lines between sentences)
+    /// and saves the model to `model_path` if provided
+    #[staticmethod]
+    #[pyo3(signature = (corpus_path, model_path = None, iterations = 5, word_column = 1, tag_column = 3))]
+    pub fn train(
+        corpus_path: String,
+        model_path: Option<String>,
+        iterations: usize,
+        word_column: usize,
+        tag_column: usize,
+    ) -> Result<Self, PyErr> {
+        let to_py_err = |e: LibError| PyErr::new::<PyRuntimeError, _>(format!("{}", e));
+
+        let sentences = Self::load_conll(PathBuf::from(corpus_path), word_column, tag_column)
+            .map_err(to_py_err)?;
+        let tagger = Self::train_on(&sentences, iterations);
+
+        if let Some(model_path) = model_path {
+            tagger.save(PathBuf::from(model_path)).map_err(to_py_err)?;
+        }
+
+        Ok(tagger)
+    }
+
+    #[pyo3(name = "save")]
+    pub fn py_save(&self, model_path: String) -> Result<(), PyErr> {
+        self.save(PathBuf::from(model_path))
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+}
+
+impl PosTagger {
+    pub fn load(path: PathBuf) -> Result<Self, LibError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| LibError::IO(format!("Failed to read tagger model: {}", e)))?;
+        let model = serde_json::from_str(&content)
+            .map_err(|e| LibError::Json(format!("Invalid tagger model: {}", e)))?;
+        Ok(Self {
+            model: Arc::new(model),
+        })
+    }
+
+    pub fn save(&self, path: PathBuf) -> Result<(), LibError> {
+        let content = serde_json::to_string(self.model.as_ref())
+            .map_err(|e| LibError::Json(e.to_string()))?;
+        fs::write(path, content)
+            .map_err(|e| LibError::IO(format!("Failed to write tagger model: {}", e)))
+    }
+
+    /// Reads (word, tag) sentences from a CoNLL file
+    /// Columns are tab separated (CoNLL-U / CoNLL-X),
+    /// or whitespace separated if a line has no tabs (CoNLL-2000)
+    /// Comment lines and multiword token ranges are skipped
+    pub fn load_conll(
+        path: PathBuf,
+        word_column: usize,
+        tag_column: usize,
+    ) -> Result<Vec<Vec<(String, String)>>, LibError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| LibError::IO(format!("Failed to read corpus: {}", e)))?;
+
+        let mut sentences = Vec::new();
+        let mut sentence = Vec::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                if !sentence.is_empty() {
+                    sentences.push(std::mem::take(&mut sentence));
+                }
+                continue;
+            }
+
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let columns: Vec<&str> = if line.contains('\t') {
+                line.split('\t').collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+
+            // CoNLL-U multiword tokens (1-2) and empty nodes (1.1)
+            if columns
+                .first()
+                .is_some_and(|id| id.contains('-') || id.contains('.'))
+                && columns
+                    .first()
+                    .is_some_and(|id| id.starts_with(char::is_numeric))
+            {
+                continue;
+            }
+
+            match (columns.get(word_column), columns.get(tag_column)) {
+                (Some(word), Some(tag)) => sentence.push((word.to_string(), tag.to_string())),
+                _ => {
+                    return Err(LibError::InvalidInput(format!(
+                        "Line {}: expected at least {} columns",
+                        line_number + 1,
+                        word_column.max(tag_column) + 1
+                    )))
+                }
+            }
+        }
+
+        if !sentence.is_empty() {
+            sentences.push(sentence);
+        }
+
+        Ok(sentences)
+    }
+
+    pub fn train_on(sentences: &[Vec<(String, String)>], iterations: usize) -> Self {
+        let mut trainer = PerceptronTrainer::default();
+        trainer.model.tagdict = Self::build_tagdict(sentences);
+
+        let mut classes: Vec<String> = sentences
+            .iter()
+            .flatten()
+            .map(|(_, tag)| tag.clone())
+            .collect();
+        classes.sort();
+        classes.dedup();
+        trainer.model.classes = classes;
+
+        let mut order: Vec<usize> = (0..sentences.len()).collect();
+        // Fixed seed so training is reproducible
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+
+        for _ in 0..iterations {
+            for (words, tags) in order.iter().map(|i| {
+                let (words, tags): (Vec<&str>, Vec<&str>) = sentences[*i]
+                    .iter()
+                    .map(|(w, t)| (w.as_str(), t.as_str()))
+                    .unzip();
+                (words, tags)
+            }) {
+                let context = Self::context(&words);
+                let (mut prev, mut prev2) = (START[0].to_string(), START[1].to_string());
+
+                for (i, (word, truth)) in words.iter().zip(&tags).enumerate() {
+                    let guess = match trainer.model.tagdict.get(*word) {
+                        Some(tag) => tag.clone(),
+                        None => {
+                            let features = Self::features(i, word, &context, &prev, &prev2);
+                            let guess = trainer.model.predict(&features);
+                            trainer.update(truth, &guess, &features);
+                            guess
+                        }
+                    };
+                    prev2 = std::mem::replace(&mut prev, guess);
+                }
+            }
+
+            // Fisher-Yates with xorshift
+            for i in (1..order.len()).rev() {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                order.swap(i, (seed % (i as u64 + 1)) as usize);
+            }
+        }
+
+        Self {
+            model: Arc::new(trainer.average()),
+        }
+    }
+
+    fn build_tagdict(sentences: &[Vec<(String, String)>]) -> HashMap<String, String> {
+        let mut counts: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+        for (word, tag) in sentences.iter().flatten() {
+            *counts
+                .entry(word.as_str())
+                .or_default()
+                .entry(tag.as_str())
+                .or_insert(0) += 1;
+        }
+
+        counts
+            .into_iter()
+            .filter_map(|(word, tags)| {
+                let total: usize = tags.values().sum();
+                let (tag, count) = tags.into_iter().max_by_key(|(tag, count)| (*count, *tag))?;
+                (total >= TAGDICT_MIN_FREQUENCY
+                    && count as f64 / total as f64 >= TAGDICT_MIN_AMBIGUITY)
+                    .then(|| (word.to_string(), tag.to_string()))
+            })
+            .collect()
+    }
+
+    fn normalize(word: &str) -> String {
+        if word.contains('-') && !word.starts_with('-') {
+            "!HYPHEN".to_string()
+        } else if word.chars().count() == 4 && word.chars().all(|c| c.is_ascii_digit()) {
+            "!YEAR".to_string()
+        } else if word.starts_with(|c: char| c.is_ascii_digit()) {
+            "!DIGITS".to_string()
+        } else {
+            word.to_lowercase()
+        }
+    }
+
+    fn context(words: &[&str]) -> Vec<String> {
+        START
+            .iter()
+            .map(|s| s.to_string())
+            .chain(words.iter().map(|w| Self::normalize(w)))
+            .chain(END.iter().map(|s| s.to_string()))
+            .collect()
+    }
+
+    fn features(
+        i: usize,
+        word: &str,
+        context: &[String],
+        prev: &str,
+        prev2: &str,
+    ) -> HashMap<String, usize> {
+        fn suffix(word: &str) -> &str {
+            let start = word
+                .char_indices()
+                .rev()
+                .nth(2)
+                .map(|(index, _)| index)
+                .unwrap_or(0);
+            &word[start..]
+        }
+
+        let i = i + START.len();
+        let first_char: String = word.chars().take(1).collect();
+
+        let mut features = HashMap::new();
+        for feature in [
+            "bias".to_string(),
+            format!("i suffix {}", suffix(word)),
+            format!("i pref1 {}", first_char),
+            format!("i-1 tag {}", prev),
+            format!("i-2 tag {}", prev2),
+            format!("i tag+i-2 tag {} {}", prev, prev2),
+            format!("i word {}", context[i]),
+            format!("i-1 tag+i word {} {}", prev, context[i]),
+            format!("i-1 word {}", context[i - 1]),
+            format!("i-1 suffix {}", suffix(&context[i - 1])),
+            format!("i-2 word {}", context[i - 2]),
+            format!("i+1 word {}", context[i + 1]),
+            format!("i+1 suffix {}", suffix(&context[i + 1])),
+            format!("i+2 word {}", context[i + 2]),
+        ] {
+            *features.entry(feature).or_insert(0) += 1;
+        }
+        features
+    }
+
+    fn tag<'a>(&self, mut tokens: Vec<Token<'a>>) -> Vec<Token<'a>> {
+        let words: Vec<&str> = tokens.iter().map(|t| t.text.as_ref()).collect();
+        let context = Self::context(&words);
+        let (mut prev, mut prev2) = (START[0].to_string(), START[1].to_string());
+
+        let mut tags = Vec::with_capacity(words.len());
+        for (i, word) in words.iter().enumerate() {
+            let tag = match self.model.tagdict.get(*word) {
+                Some(tag) => tag.clone(),
+                None => self
+                    .model
+                    .predict(&Self::features(i, word, &context, &prev, &prev2)),
+            };
+            prev2 = std::mem::replace(&mut prev, tag.clone());
+            tags.push(tag);
+        }
+
+        for (token, tag) in tokens.iter_mut().zip(tags) {
+            token.tags.insert(POS_TAG, tag);
+        }
+        tokens
+    }
+}
+
+impl PosTagger {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("PosTagger", |params| {
+            Self::load(PathBuf::from(params.get::<String>("model_path")?))
+        })
+        .with_description("Tags every token with its part-of-speech")
+        .with_param(ParamSpec::required(
+            "model_path",
+            ParamKind::String,
+            "Model saved by PosTagger.train",
+        ))
+        .with_inputs(&[DataType::ListStr, DataType::ListDict])
+        .with_output(DataType::ListDict)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for PosTagger {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        match input {
+            Data::VecCowStr(v) => Ok(Data::VecToken(
+                self.tag(v.into_iter().map(Token::new).collect()),
+            )),
+            Data::VecToken(v) => Ok(Data::VecToken(self.tag(v))),
+            _ => Err(LibError::InvalidInput(
+                "PosTagger only accepts Data::VecCowStr or Data::VecToken as input".to_string(),
+            )),
+        }
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
+            _ => Err(LibError::InvalidInput(
+                "PosTagger will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+/// Whether a POS tag (UPOS or Penn Treebank) marks a verb
+pub(crate) fn is_verb_tag(tag: &str) -> bool {
+    tag == "VERB" || tag == "AUX" || tag.starts_with("VB")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    const CORPUS: &str = "# sent_id = 1
+1\tI\tI\tPRON\tPRP
+2\tsaw\tsee\tVERB\tVBD
+3\tthe\tthe\tDET\tDT
+4\tsaw\tsaw\tNOUN\tNN
+
+1\tThe\tthe\tDET\tDT
+2\tchildren\tchild\tNOUN\tNNS
+3\twork\twork\tVERB\tVBP
+
+1-2\tdon't\t_\t_\t_
+1\tdo\tdo\tAUX\tVBP
+2\tn't\tnot\tPART\tRB
+3\twork\twork\tVERB\tVB
+
+1\tWe\twe\tPRON\tPRP
+2\tsaw\tsee\tVERB\tVBD
+3\ta\ta\tDET\tDT
+4\tfactory\tfactory\tNOUN\tNN
+";
+
+    fn create_corpus(content: &str) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = dir.path().join("corpus.conllu");
+        let mut file = File::create(&file_path).expect("Failed to create temp file");
+        write!(file, "{}", content).expect("Failed to write test data");
+        file.flush().expect("Failed to flush file");
+        (dir, file_path)
+    }
+
+    fn pos_tags(tagger: &PosTagger, words: &[&'static str]) -> Vec<String> {
+        let input = words.iter().map(|w| Cow::Borrowed(*w)).collect();
+        match tagger.process(Data::VecCowStr(input)) {
+            Ok(Data::VecToken(tokens)) => tokens
+                .iter()
+                .map(|t| t.tag(POS_TAG).unwrap().to_string())
+                .collect(),
+            _ => panic!("Expected Data::VecToken"),
+        }
+    }
+
+    #[test]
+    fn test_load_conll() {
+        let (_dir, path) = create_corpus(CORPUS);
+        let sentences = PosTagger::load_conll(path, 1, 3).unwrap();
+
+        assert_eq!(sentences.len(), 4);
+        assert_eq!(sentences[0][1], ("saw".to_string(), "VERB".to_string()));
+        // Multiword token range is skipped
+        assert_eq!(sentences[2][0], ("do".to_string(), "AUX".to_string()));
+    }
+
+    #[test]
+    fn test_load_conll_whitespace_columns() {
+        let (_dir, path) = create_corpus("Children NNS B-NP\nwork VBP B-VP\n");
+        let sentences = PosTagger::load_conll(path, 0, 1).unwrap();
+        assert_eq!(sentences[0][0], ("Children".to_string(), "NNS".to_string()));
+
+        let (_dir, path) = create_corpus("Children\n");
+        assert!(PosTagger::load_conll(path, 0, 1).is_err());
+    }
+
+    #[test]
+    fn test_train_and_tag() {
+        let (_dir, path) = create_corpus(CORPUS);
+        let sentences = PosTagger::load_conll(path, 1, 3).unwrap();
+        let tagger = PosTagger::train_on(&sentences, 10);
+
+        assert_eq!(
+            pos_tags(&tagger, &["I", "saw", "the", "saw"]),
+            vec!["PRON", "VERB", "DET", "NOUN"]
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let (dir, path) = create_corpus(CORPUS);
+        let sentences = PosTagger::load_conll(path, 1, 4).unwrap();
+        let tagger = PosTagger::train_on(&sentences, 10);
+
+        let model_path = dir.path().join("model.json");
+        tagger.save(model_path.clone()).unwrap();
+        let loaded = PosTagger::load(model_path).unwrap();
+
+        let words = ["We", "saw", "the", "children"];
+        assert_eq!(pos_tags(&tagger, &words), pos_tags(&loaded, &words));
+    }
+
+    #[test]
+    fn test_keeps_existing_tags() {
+        let (_dir, path) = create_corpus(CORPUS);
+        let sentences = PosTagger::load_conll(path, 1, 3).unwrap();
+        let tagger = PosTagger::train_on(&sentences, 5);
+
+        let input = vec![Token::new(Cow::Borrowed("saw")).with_tag("spelling", "map")];
+        if let Data::VecToken(tokens) = tagger.process(Data::VecToken(input)).unwrap() {
+            assert_eq!(tokens[0].tag("spelling"), Some("map"));
+            assert!(tokens[0].tag(POS_TAG).is_some());
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+}
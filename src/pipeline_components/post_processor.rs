@@ -1,15 +1,19 @@
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::pyclass;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct PostProcessor;
 
-#[pymethods]
 impl PostProcessor {
-    #[new]
     pub fn new() -> Self {
         Self
     }
@@ -21,10 +25,20 @@ impl Default for PostProcessor {
     }
 }
 
+impl PostProcessor {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("PostProcessor", |_| Ok(Self::new()))
+            .with_description("Passes tokens on unchanged")
+            .with_inputs(&[DataType::ListStr, DataType::ListDict])
+            .with_python::<Self>()
+    }
+}
+
 impl Processor for PostProcessor {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
         match input {
             Data::VecCowStr(v) => Ok(Data::VecCowStr(v)),
+            Data::VecToken(v) => Ok(Data::VecToken(v)),
             _ => Err(LibError::InvalidInput("Invalid input type".to_string())),
         }
     }
@@ -34,6 +48,7 @@ impl Processor for PostProcessor {
             Data::VecCowStr(v) => {
                 serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
             }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
             _ => Err(LibError::InvalidInput(
                 "PostProcessor should never output this type".to_string(),
             )),
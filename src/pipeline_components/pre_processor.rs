@@ -1,21 +1,25 @@
 use std::borrow::Cow;
 
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::pyclass;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
 
 /// This is a pre-processor that does not modify the input
 /// but instead returns an owned string
 /// This is needed for correct python interop
 /// while saving a bunch of headaches
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct PreProcessor;
 
-#[pymethods]
 impl PreProcessor {
-    #[new]
     pub fn new() -> Self {
         Self
     }
@@ -27,6 +31,15 @@ impl Default for PreProcessor {
     }
 }
 
+impl PreProcessor {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("PreProcessor", |_| Ok(Self::new()))
+            .with_description("Passes the raw input string on")
+            .with_inputs(&[DataType::Str])
+            .with_python::<Self>()
+    }
+}
+
 impl Processor for PreProcessor {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
         match input {
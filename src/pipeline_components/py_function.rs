@@ -0,0 +1,252 @@
+use std::{borrow::Cow, str::FromStr, sync::Arc};
+
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    pyclass, pymethods,
+    types::PyAnyMethods,
+    PyErr, PyObject, PyResult, Python,
+};
+use pythonize::{depythonize, pythonize};
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
+
+/// Wraps a python callable as a pipeline stage, so a step can be
+/// prototyped in python before being ported to a Rust processor
+///
+/// The GIL is only held while the callable runs, so documents
+/// still go through the other stages in parallel
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct PyFunctionProcessor {
+    function: Arc<PyObject>,
+    /// Used in error messages
+    function_name: String,
+    input: DataType,
+    output: DataType,
+}
+
+#[pymethods]
+impl PyFunctionProcessor {
+    #[new]
+    #[pyo3(signature = (function, input = "list[str]", output = "list[str]"))]
+    pub fn py_new(py: Python, function: PyObject, input: &str, output: &str) -> PyResult<Self> {
+        if !function.bind(py).is_callable() {
+            return Err(PyErr::new::<PyTypeError, _>(
+                "PyFunctionProcessor expects a callable",
+            ));
+        }
+
+        let function_name = function
+            .bind(py)
+            .getattr("__qualname__")
+            .and_then(|name| name.extract::<String>())
+            .unwrap_or_else(|_| "<callable>".to_string());
+
+        Self::new(function, function_name, input, output)
+            .map_err(|e| PyErr::new::<PyValueError, _>(format!("{}", e)))
+    }
+}
+
+impl PyFunctionProcessor {
+    pub fn new(
+        function: PyObject,
+        function_name: String,
+        input: &str,
+        output: &str,
+    ) -> Result<Self, LibError> {
+        let output = DataType::from_str(output)?;
+        if output == DataType::ListDict {
+            return Err(LibError::InvalidInput(
+                "list[dict] is only supported as an input type".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            function: Arc::new(function),
+            function_name,
+            input: DataType::from_str(input)?,
+            output,
+        })
+    }
+
+    /// Calls the function with the GIL held only for the call
+    /// and the conversions to and from python objects
+    fn call(&self, input: Value) -> Result<Value, LibError> {
+        Python::with_gil(|py| {
+            let input = pythonize(py, &input).map_err(|e| LibError::Python(e.to_string()))?;
+            let output = self
+                .function
+                .call1(py, (input,))
+                .map_err(|e| LibError::Python(format!("{}: {}", self.function_name, e)))?;
+            depythonize(output.bind(py)).map_err(|e| {
+                LibError::Python(format!(
+                    "{} returned a value that can't be converted: {}",
+                    self.function_name, e
+                ))
+            })
+        })
+    }
+
+    fn to_data<'a>(&self, output: Value) -> Result<Data<'a>, LibError> {
+        let mismatch = || {
+            LibError::InvalidInput(format!(
+                "{} didn't return the declared output type",
+                self.function_name
+            ))
+        };
+
+        match (self.output, output) {
+            (DataType::Str, Value::String(s)) => Ok(Data::OwnedStr(s)),
+            (DataType::ListStr, Value::Array(items)) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(s) => Ok(Cow::Owned(s)),
+                    _ => Err(mismatch()),
+                })
+                .collect::<Result<_, _>>()
+                .map(Data::VecCowStr),
+            (DataType::Dict, output @ Value::Object(_)) | (DataType::Any, output) => {
+                Ok(Data::Json(output))
+            }
+            _ => Err(mismatch()),
+        }
+    }
+}
+
+impl PyFunctionProcessor {
+    /// Wraps a python callable, so it can't be built from a config
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("PyFunctionProcessor", |_| -> Result<Self, LibError> {
+            Err(LibError::InvalidInput(
+                "PyFunctionProcessor can only be created from python".to_string(),
+            ))
+        })
+        .with_description("Runs a python callable")
+        .with_output(DataType::Any)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for PyFunctionProcessor {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        if !self.input.accepts(input.data_type()) {
+            return Err(LibError::InvalidInput(format!(
+                "{} does not accept {}",
+                self.function_name,
+                input.pytype()
+            )));
+        }
+
+        let output = self.call(input.to_value()?)?;
+        self.to_data(output)
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        data.to_value()
+    }
+
+    fn declared_types(&self) -> Option<(DataType, DataType)> {
+        Some((self.input, self.output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn processor(code: &str, input: &str, output: &str) -> PyFunctionProcessor {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let function = py
+                .eval(&std::ffi::CString::new(code).unwrap(), None, None)
+                .unwrap()
+                .unbind();
+            PyFunctionProcessor::new(function, "test".to_string(), input, output).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_list_str() {
+        let processor = processor(
+            "lambda words: [w[::-1] for w in words]",
+            "list[str]",
+            "list[str]",
+        );
+        let input = Data::VecCowStr(vec![Cow::Borrowed("abc"), Cow::Borrowed("de")]);
+
+        match processor.process(input).unwrap() {
+            Data::VecCowStr(output) => assert_eq!(output, vec!["cba", "ed"]),
+            _ => panic!("Expected Data::VecCowStr"),
+        }
+        assert!(processor
+            .process(Data::OwnedStr("abc".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parallel_calls() {
+        let processor = processor("lambda text: {'length': len(text)}", "str", "dict");
+
+        let outputs: Vec<Value> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|i| {
+                    let processor = processor.clone();
+                    scope.spawn(move || {
+                        let output = processor.process(Data::OwnedStr("a".repeat(i))).unwrap();
+                        processor.to_json(&output).unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for (i, output) in outputs.iter().enumerate() {
+            assert_eq!(output["length"], i);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let raises = processor("lambda words: 1 / 0", "list[str]", "list[str]");
+        let error = raises.process(Data::VecCowStr(Vec::new())).unwrap_err();
+        assert!(error.to_string().contains("ZeroDivisionError"));
+
+        let wrong_output = processor("lambda words: len(words)", "list[str]", "list[str]");
+        assert!(wrong_output.process(Data::VecCowStr(Vec::new())).is_err());
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let function = || py.eval(c"len", None, None).unwrap().unbind();
+            assert!(
+                PyFunctionProcessor::py_new(py, function(), "list[str]", "list[dict]").is_err()
+            );
+            assert!(PyFunctionProcessor::py_new(py, function(), "bytes", "str").is_err());
+
+            let error = PyFunctionProcessor::py_new(py, py.None(), "str", "str").unwrap_err();
+            assert!(error.is_instance_of::<PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn test_declared_types() {
+        let registry = crate::registry::Registry::global();
+        let spec = PyFunctionProcessor::spec();
+        let processor = processor("lambda words: ' '.join(words)", "list[str]", "str");
+
+        assert_eq!(
+            registry
+                .check_py(&spec, &processor, DataType::ListStr)
+                .unwrap(),
+            DataType::Str
+        );
+        let error = registry
+            .check_py(&spec, &processor, DataType::Str)
+            .unwrap_err();
+        assert!(error.to_string().contains("does not accept str"));
+    }
+}
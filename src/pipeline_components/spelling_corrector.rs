@@ -0,0 +1,495 @@
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::Arc,
+};
+
+#[cfg(feature = "python")]
+use pyo3::{exceptions::PyTypeError, pyclass, pymethods, PyErr, PyObject, PyResult, Python};
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Data, DataType, Token},
+    pipeline_builder::Processor,
+    registry::{annotated_output, ParamKind, ParamSpec, ProcessorSpec},
+};
+
+#[cfg(feature = "python")]
+use crate::registry::ConfiguredProcessor;
+
+use super::{case_pattern::CasePattern, dictionary, lemmatizer::Lemmatizer};
+
+/// Tag added to corrected tokens when annotating,
+/// holding the original (uncorrected) token
+const CORRECTION_TAG: &str = "corrected_from";
+
+/// Fuzzy spelling correction for noisy (e.g. OCR) input
+///
+/// Uses a SymSpell-style symmetric deletion index
+/// built from a word-frequency list, so lookups only
+/// need to generate deletions of the input token
+/// rather than every possible edit.
+/// Candidates within `max_edit_distance` are ranked
+/// by edit distance first, then by frequency.
+/// SOURCE: https://github.com/wolfgarbe/SymSpell
+///
+/// The index is shared between clones
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct SpellingCorrector {
+    /// word -> frequency
+    frequencies: Arc<HashMap<String, u64>>,
+    /// deletion of a word's prefix -> words producing it
+    deletes: Arc<HashMap<String, Vec<String>>>,
+    max_edit_distance: usize,
+    /// Only the first `prefix_length` characters
+    /// are indexed, which keeps the index small
+    /// without losing much precision
+    prefix_length: usize,
+    /// Tokens shorter than this are left alone
+    min_length: usize,
+    /// Tokens that are valid lemmas or derivatives
+    /// are never corrected
+    lemmatizer: Option<Lemmatizer>,
+    /// When set, the output is `Data::VecToken` with
+    /// corrected tokens tagged with the original token
+    annotate: bool,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl SpellingCorrector {
+    /// Leaves tokens that are valid lemmas or derivatives
+    /// in `lemmatizer` (a `Lemmatizer` or a `Processor`
+    /// constructing one) alone
+    #[pyo3(name = "set_lemmatizer")]
+    pub fn py_set_lemmatizer(&mut self, py: Python, lemmatizer: PyObject) -> PyResult<()> {
+        let lemmatizer = ConfiguredProcessor::extract_class::<Lemmatizer>(py, &lemmatizer)
+            .ok_or_else(|| PyErr::new::<PyTypeError, _>("Expected a Lemmatizer"))?;
+        self.lemmatizer = Some(lemmatizer);
+        Ok(())
+    }
+}
+
+impl SpellingCorrector {
+    pub fn new(
+        frequency_path: String,
+        max_edit_distance: usize,
+        prefix_length: usize,
+    ) -> Result<Self, LibError> {
+        let frequencies = Self::load_frequencies(PathBuf::from(frequency_path))?;
+
+        if prefix_length <= max_edit_distance {
+            return Err(LibError::InvalidInput(
+                "prefix_length must be greater than max_edit_distance".to_string(),
+            ));
+        }
+
+        let mut deletes: HashMap<String, Vec<String>> = HashMap::new();
+        for word in frequencies.keys() {
+            let prefix: String = word.chars().take(prefix_length).collect();
+            for deletion in Self::deletions(&prefix, max_edit_distance) {
+                deletes.entry(deletion).or_default().push(word.clone());
+            }
+        }
+
+        Ok(Self {
+            frequencies: Arc::new(frequencies),
+            deletes: Arc::new(deletes),
+            max_edit_distance,
+            prefix_length,
+            min_length: 3,
+            lemmatizer: None,
+            annotate: false,
+        })
+    }
+
+    pub fn with_min_length(mut self, min_length: usize) -> Self {
+        self.min_length = min_length;
+        self
+    }
+
+    pub fn with_lemmatizer(mut self, lemmatizer: Lemmatizer) -> Self {
+        self.lemmatizer = Some(lemmatizer);
+        self
+    }
+
+    pub fn with_annotations(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    fn load_frequencies(path: PathBuf) -> Result<HashMap<String, u64>, LibError> {
+        let mut frequencies = HashMap::new();
+
+        for (line, record) in dictionary::read_file_rows(&path, &["word", "frequency"])? {
+            let word = &record[0];
+            let frequency = record[1]
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| LibError::Dictionary {
+                    file: path.display().to_string(),
+                    line,
+                    message: format!("Invalid frequency for word {}: {}", word, e),
+                })?;
+
+            *frequencies.entry(word.trim().to_lowercase()).or_insert(0) += frequency;
+        }
+
+        Ok(frequencies)
+    }
+
+    /// Every string obtainable by deleting up to
+    /// `max_distance` characters, including the word itself
+    fn deletions(word: &str, max_distance: usize) -> HashSet<String> {
+        let mut result = HashSet::from([word.to_string()]);
+        let mut frontier = vec![word.to_string()];
+
+        for _ in 0..max_distance {
+            let mut next = Vec::new();
+            for candidate in &frontier {
+                let chars: Vec<char> = candidate.chars().collect();
+                for i in 0..chars.len() {
+                    let deletion: String = chars[..i].iter().chain(&chars[i + 1..]).collect();
+                    if result.insert(deletion.clone()) {
+                        next.push(deletion);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        result
+    }
+
+    /// Returns the best correction for a lowercased word,
+    /// or `None` if the word is valid or nothing is close enough
+    fn lookup(&self, word: &str) -> Option<&str> {
+        if self.frequencies.contains_key(word) {
+            return None;
+        }
+
+        let input: Vec<char> = word.chars().collect();
+        let prefix: String = input.iter().take(self.prefix_length).collect();
+
+        let mut seen = HashSet::new();
+        let mut best: Option<(usize, u64, &str)> = None;
+
+        for deletion in Self::deletions(&prefix, self.max_edit_distance) {
+            let Some(suggestions) = self.deletes.get(&deletion) else {
+                continue;
+            };
+
+            for suggestion in suggestions {
+                if !seen.insert(suggestion.as_str()) {
+                    continue;
+                }
+
+                let candidate: Vec<char> = suggestion.chars().collect();
+                if candidate.len().abs_diff(input.len()) > self.max_edit_distance {
+                    continue;
+                }
+
+                let Some(distance) = edit_distance(&input, &candidate, self.max_edit_distance)
+                else {
+                    continue;
+                };
+
+                let frequency = self.frequencies[suggestion];
+                let is_better = match best {
+                    None => true,
+                    Some((best_distance, best_frequency, best_word)) => {
+                        (distance, std::cmp::Reverse(frequency), suggestion.as_str())
+                            < (best_distance, std::cmp::Reverse(best_frequency), best_word)
+                    }
+                };
+
+                if is_better {
+                    best = Some((distance, frequency, suggestion));
+                }
+            }
+        }
+
+        best.map(|(_, _, word)| word)
+    }
+
+    /// Returns the corrected word, if it was corrected
+    fn correct(&self, word: &str) -> Option<String> {
+        if word.chars().count() < self.min_length || !word.chars().all(char::is_alphabetic) {
+            return None;
+        }
+
+        if let Some(lemmatizer) = &self.lemmatizer {
+            if lemmatizer.is_known(word) {
+                return None;
+            }
+        }
+
+        let lowered = word.to_lowercase();
+        self.lookup(&lowered)
+            .map(|correction| CasePattern::detect(word).apply(correction))
+    }
+
+    fn correct_token<'a>(&self, token: Token<'a>) -> Token<'a> {
+        match self.correct(&token.text) {
+            Some(correction) => {
+                let Token { text, mut tags } = token;
+                tags.insert(CORRECTION_TAG, text.into_owned());
+                Token {
+                    text: Cow::Owned(correction),
+                    tags,
+                }
+            }
+            None => token,
+        }
+    }
+}
+
+/// Optimal string alignment distance
+/// (Levenshtein + adjacent transpositions)
+/// Returns `None` if the distance exceeds `max_distance`
+fn edit_distance(a: &[char], b: &[char], max_distance: usize) -> Option<usize> {
+    let (n, m) = (a.len(), b.len());
+    let mut prev_prev: Vec<usize> = vec![0; m + 1];
+    let mut prev: Vec<usize> = (0..=m).collect();
+    let mut current: Vec<usize> = vec![0; m + 1];
+
+    for i in 1..=n {
+        current[0] = i;
+        let mut row_min = current[0];
+
+        for j in 1..=m {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (prev[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(prev[j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(prev_prev[j - 2] + 1);
+            }
+
+            row_min = row_min.min(current[j]);
+        }
+
+        if row_min > max_distance {
+            return None;
+        }
+
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut current);
+    }
+
+    Some(prev[m]).filter(|distance| *distance <= max_distance)
+}
+
+impl SpellingCorrector {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("SpellingCorrector", |params| {
+            let mut corrector = Self::new(
+                params.get("frequency_path")?,
+                params.get("max_edit_distance")?,
+                params.get("prefix_length")?,
+            )?
+            .with_min_length(params.get("min_length")?)
+            .with_annotations(params.get("annotate")?);
+
+            if let Some(path) = params.get::<Option<String>>("lemma_map_path")? {
+                corrector = corrector.with_lemmatizer(Lemmatizer::new(path)?);
+            }
+            Ok(corrector)
+        })
+        .with_description("Corrects misspelled tokens using a word frequency list")
+        .with_param(ParamSpec::required(
+            "frequency_path",
+            ParamKind::String,
+            "CSV of word,frequency",
+        ))
+        .with_param(ParamSpec::optional(
+            "max_edit_distance",
+            ParamKind::Int,
+            2,
+            "Largest edit distance corrected",
+        ))
+        .with_param(ParamSpec::optional(
+            "prefix_length",
+            ParamKind::Int,
+            7,
+            "Characters of every word that are indexed",
+        ))
+        .with_param(ParamSpec::optional(
+            "min_length",
+            ParamKind::Int,
+            3,
+            "Shorter tokens are left alone",
+        ))
+        .with_param(ParamSpec::optional(
+            "lemma_map_path",
+            ParamKind::String,
+            Value::Null,
+            "Lemma map whose words are never corrected",
+        ))
+        .with_param(ParamSpec::optional(
+            "annotate",
+            ParamKind::Bool,
+            false,
+            "Tag corrected tokens with the original token",
+        ))
+        .with_inputs(&[DataType::ListStr, DataType::ListDict])
+        .with_output_fn(annotated_output)
+        .with_python::<Self>()
+    }
+}
+
+impl Processor for SpellingCorrector {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        match input {
+            Data::VecCowStr(v) if self.annotate => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|word| self.correct_token(Token::new(word)))
+                    .collect(),
+            )),
+            Data::VecCowStr(v) => Ok(Data::VecCowStr(
+                v.into_iter()
+                    .map(|word| match self.correct(&word) {
+                        Some(correction) => Cow::Owned(correction),
+                        None => word,
+                    })
+                    .collect(),
+            )),
+            Data::VecToken(v) => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|token| self.correct_token(token))
+                    .collect(),
+            )),
+            _ => Err(LibError::InvalidInput(
+                "SpellingCorrector only accepts Data::VecCowStr or Data::VecToken as input"
+                    .to_string(),
+            )),
+        }
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::VecCowStr(v) => {
+                serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
+            }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
+            _ => Err(LibError::InvalidInput(
+                "SpellingCorrector will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn create_test_csv(name: &str, content: &str) -> (TempDir, String) {
+        let dir = TempDir::new().expect("Failed to create temp dir");
+        let file_path = dir.path().join(name);
+        let mut file = File::create(&file_path).expect("Failed to create temp file");
+        write!(file, "{}", content).expect("Failed to write test data");
+        file.flush().expect("Failed to flush file");
+        (dir, file_path.to_string_lossy().to_string())
+    }
+
+    const FREQUENCIES: &str =
+        "word,frequency\nchild,5000\nlabour,3000\nthe,100000\nten,800\nchilled,40\n";
+
+    fn process(corrector: &SpellingCorrector, input: Vec<&'static str>) -> Vec<Cow<'static, str>> {
+        let input = input.into_iter().map(Cow::Borrowed).collect();
+        match corrector.process(Data::VecCowStr(input)) {
+            Ok(Data::VecCowStr(output_vec)) => output_vec,
+            _ => panic!("Expected Data::VecCowStr"),
+        }
+    }
+
+    #[test]
+    fn test_ocr_corrections() {
+        let (_dir, path) = create_test_csv("frequencies.csv", FREQUENCIES);
+        let corrector = SpellingCorrector::new(path, 2, 7).unwrap();
+
+        let output_vec = process(&corrector, vec!["chlld", "labuor", "Chlld", "teh"]);
+        assert_eq!(output_vec, vec!["child", "labour", "Child", "the"]);
+    }
+
+    #[test]
+    fn test_valid_and_distant_tokens_untouched() {
+        let (_dir, path) = create_test_csv("frequencies.csv", FREQUENCIES);
+        let corrector = SpellingCorrector::new(path, 1, 7).unwrap();
+
+        let output_vec = process(&corrector, vec!["child", "xyzzy", "2023", "th", "chlled"]);
+        assert!(matches!(&output_vec[0], Cow::Borrowed(s) if *s == "child"));
+        assert!(matches!(&output_vec[1], Cow::Borrowed(s) if *s == "xyzzy"));
+        assert!(matches!(&output_vec[2], Cow::Borrowed(s) if *s == "2023"));
+        // Shorter than min_length
+        assert!(matches!(&output_vec[3], Cow::Borrowed(s) if *s == "th"));
+        assert_eq!(output_vec[4], "chilled");
+    }
+
+    #[test]
+    fn test_prefers_frequent_candidates() {
+        let (_dir, path) = create_test_csv("frequencies.csv", FREQUENCIES);
+        let corrector = SpellingCorrector::new(path, 2, 7).unwrap();
+
+        // "thn" is one edit away from both "ten" and "the",
+        // "the" is far more frequent
+        assert_eq!(corrector.lookup("thn"), Some("the"));
+        // Closer candidates win regardless of frequency
+        assert_eq!(corrector.lookup("tex"), Some("ten"));
+    }
+
+    #[test]
+    fn test_skips_known_lemmas() {
+        let (_dir, path) = create_test_csv("frequencies.csv", FREQUENCIES);
+        let (_lemma_dir, lemma_path) = create_test_csv(
+            "lemma_map.csv",
+            "lemma,derivatives\nlabor,\"labors, labored\"",
+        );
+
+        let corrector = SpellingCorrector::new(path, 2, 7)
+            .unwrap()
+            .with_lemmatizer(Lemmatizer::new(lemma_path).unwrap());
+
+        let output_vec = process(&corrector, vec!["labor", "labored", "labuor"]);
+        assert_eq!(output_vec, vec!["labor", "labored", "labour"]);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let (_dir, path) = create_test_csv("frequencies.csv", FREQUENCIES);
+        let corrector = SpellingCorrector::new(path, 2, 7)
+            .unwrap()
+            .with_annotations(true);
+
+        let input = vec![Cow::Borrowed("chlld"), Cow::Borrowed("child")];
+        if let Data::VecToken(output_vec) = corrector.process(Data::VecCowStr(input)).unwrap() {
+            assert_eq!(output_vec[0].text, "child");
+            assert_eq!(output_vec[0].tag(CORRECTION_TAG), Some("chlld"));
+            assert_eq!(output_vec[1].tag(CORRECTION_TAG), None);
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(
+            edit_distance(&chars("labuor"), &chars("labour"), 2),
+            Some(1)
+        );
+        assert_eq!(edit_distance(&chars("chlld"), &chars("child"), 2), Some(1));
+        assert_eq!(
+            edit_distance(&chars("kitten"), &chars("sitting"), 3),
+            Some(3)
+        );
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting"), 2), None);
+    }
+}
@@ -1,75 +1,848 @@
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use pyo3::{exceptions::PyRuntimeError, pyclass, pymethods, PyErr};
+use arc_swap::ArcSwap;
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    pyclass, pymethods,
+    types::{PyAnyMethods, PyDict, PyDictMethods},
+    Bound, PyAny, PyErr, PyResult, Python,
+};
+#[cfg(feature = "python")]
+use pythonize::pythonize;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType, Token},
+    pipeline_builder::Processor,
+    registry::{annotated_output, ParamKind, ParamSpec, ProcessorSpec},
+};
+
+use super::{
+    case_pattern::CasePattern,
+    compiled_dictionary::{CompiledDictionary, DictionaryKind, Entry},
+    dictionary::{self, DictionaryIssue, IssueKind},
+    file_watcher::FileWatcher,
+    spelling_rules::SpellingRules,
+};
+
+/// Tag added to replaced tokens when annotating
+const SPELLING_TAG: &str = "spelling";
+
+/// Key prefixes of compiled spelling maps, which hold
+/// both directions and the conflicts in a single file
+const US_TO_UK_PREFIX: &str = "\u{0}";
+const UK_TO_US_PREFIX: &str = "\u{1}";
+const CONFLICT_PREFIX: &str = "\u{2}";
+/// Separates the counterparts of a compiled conflict
+const CONFLICT_SEPARATOR: char = '\t';
+
+/// Columns of the spelling map CSV
+const COLUMNS: &[&str] = &["target", "alternative_spelling"];
+
+/// Spelling map embedded with the `bundled-data` feature
+#[cfg(feature = "bundled-data")]
+const EMBEDDED_SPELLING_MAP: &str = include_str!("../../data/spelling_map.csv");
+
+/// Which column of the spelling map is
+/// looked up and which one it is replaced with
+/// The bundled map has British spellings in the
+/// first column and American ones in the second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpellingDirection {
+    /// Column 1 -> column 0
+    #[default]
+    UsToUk,
+    /// Column 0 -> column 1
+    UkToUs,
+}
+
+impl SpellingDirection {
+    fn compiled_prefix(&self) -> &'static str {
+        match self {
+            SpellingDirection::UsToUk => US_TO_UK_PREFIX,
+            SpellingDirection::UkToUs => UK_TO_US_PREFIX,
+        }
+    }
+}
+
+impl FromStr for SpellingDirection {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "us_to_uk" => Ok(SpellingDirection::UsToUk),
+            "uk_to_us" => Ok(SpellingDirection::UkToUs),
+            _ => Err(LibError::InvalidInput(format!(
+                "Unknown spelling direction: {} (expected \"us_to_uk\" or \"uk_to_us\")",
+                s
+            ))),
+        }
+    }
+}
+
+/// Everything `SpellingMapper::reload` replaces at once
+#[derive(Debug, Default)]
+struct SpellingDictionary {
+    /// Words to look up -> their replacement,
+    /// in the direction the mapper was created with
+    spelling_map: HashMap<String, String>,
+    /// Words that have more than one counterpart
+    /// in the map, i.e. where the mapping can't be
+    /// reversed one-to-one. For words on the lookup
+    /// side the last row in the file wins
+    conflicts: HashMap<String, Vec<String>>,
+    /// Used instead of `spelling_map` and `conflicts` (which
+    /// are then empty) when the spelling map is a file
+    /// created by `SpellingMapper::compile`
+    compiled: Option<Arc<CompiledDictionary>>,
+}
+
+impl SpellingDictionary {
+    fn from_pairs(pairs: Vec<(String, String)>, direction: SpellingDirection) -> Self {
+        let mut spelling_map = HashMap::with_capacity(pairs.len());
+        let conflicts = SpellingMapper::find_conflicts(&pairs);
+
+        for (target_word, alternative_spelling) in pairs {
+            // NOTE: For US -> UK these are reversed intentionally
+            // as we want to look for keys in the map
+            // to then replace them with the values
+            match direction {
+                SpellingDirection::UsToUk => spelling_map.insert(alternative_spelling, target_word),
+                SpellingDirection::UkToUs => spelling_map.insert(target_word, alternative_spelling),
+            };
+        }
+
+        Self {
+            spelling_map,
+            conflicts,
+            compiled: None,
+        }
+    }
+
+    /// Loads either a CSV or a dictionary created by `SpellingMapper::compile`
+    fn load(path: &Path, direction: SpellingDirection) -> Result<Self, LibError> {
+        if CompiledDictionary::is_compiled(path) {
+            return Ok(Self {
+                compiled: Some(Arc::new(CompiledDictionary::open(
+                    path,
+                    DictionaryKind::Spelling,
+                )?)),
+                ..Default::default()
+            });
+        }
+
+        let pairs = SpellingMapper::load_spelling_map(path.to_path_buf())?;
+        Ok(Self::from_pairs(pairs, direction))
+    }
+
+    /// Copy with lowercased lookup keys
+    fn lowercased(&self) -> Self {
+        let mut spelling_map = HashMap::with_capacity(self.spelling_map.len());
+        for (alternative_spelling, target_word) in &self.spelling_map {
+            spelling_map
+                .entry(alternative_spelling.to_lowercase())
+                .or_insert_with(|| target_word.clone());
+        }
+
+        Self {
+            spelling_map,
+            conflicts: self.conflicts.clone(),
+            compiled: self.compiled.clone(),
+        }
+    }
+
+    fn lookup(&self, key: &str, direction: SpellingDirection) -> Option<&str> {
+        match &self.compiled {
+            Some(dictionary) => dictionary
+                .get(&format!("{}{}", direction.compiled_prefix(), key))
+                .and_then(|(replacement, _)| replacement),
+            None => self.spelling_map.get(key).map(String::as_str),
+        }
+    }
+
+    fn conflicts(&self) -> HashMap<String, Vec<String>> {
+        match &self.compiled {
+            Some(dictionary) => dictionary
+                .entries_with_prefix(CONFLICT_PREFIX)
+                .into_iter()
+                .map(|(word, counterparts)| {
+                    let counterparts = counterparts
+                        .unwrap_or_default()
+                        .split(CONFLICT_SEPARATOR)
+                        .map(|w| w.to_string())
+                        .collect();
+                    (word, counterparts)
+                })
+                .collect(),
+            None => self.conflicts.clone(),
+        }
+    }
+}
 
 /// Maps the spelling of a provided word
 /// to the target spelling provided as
 /// keys in the dictionary
 /// SOURCE: Breame project
 /// https://github.com/cdpierse/breame/blob/main/breame/data/spelling_constants.py
-#[pyclass]
+///
+/// The dictionary is shared between clones, so every pipeline
+/// built with the same `SpellingMapper` uses one copy of it, and
+/// picks up new versions of it from `reload`
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct SpellingMapper {
-    spelling_map: HashMap<String, String>,
+    dictionary: Arc<ArcSwap<SpellingDictionary>>,
+    /// File the dictionary was loaded from, for `reload`
+    path: Option<PathBuf>,
+    /// Reloads the dictionary when the file changes,
+    /// for as long as any clone is alive
+    watcher: Option<Arc<FileWatcher>>,
+    /// When set, keys are stored lowercased and the
+    /// casing of the input word is re-applied to the
+    /// replacement (Color -> Colour, COLOR -> COLOUR)
+    case_insensitive: bool,
+    direction: SpellingDirection,
+    /// Fallback for words missing from the map
+    rules: Option<Arc<SpellingRules>>,
+    /// When set, the output is `Data::VecToken` with
+    /// replaced tokens tagged as `"spelling": "map" | "rule"`
+    annotate: bool,
 }
 
+#[cfg(feature = "python")]
 #[pymethods]
 impl SpellingMapper {
-    #[new]
-    pub fn new(spelling_map_path: String) -> Result<Self, pyo3::PyErr> {
-        let spelling_map = Self::load_spelling_map(PathBuf::from(spelling_map_path))
+    /// Reloads the spelling map from its file. Documents already
+    /// being mapped finish with the previous version
+    #[pyo3(name = "reload")]
+    pub fn py_reload(&self) -> Result<(), PyErr> {
+        self.reload()
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+
+    /// Spelling mapper from a `target -> alternative_spelling` dict,
+    /// read in insertion order as if it were the rows of the CSV
+    #[staticmethod]
+    #[pyo3(
+        name = "from_dict",
+        signature = (
+            spelling_map,
+            case_insensitive = false,
+            direction = "us_to_uk",
+            rules = false,
+            exceptions = Vec::new(),
+            vocabulary_path = None,
+            annotate = false,
+        )
+    )]
+    pub fn py_from_dict(
+        spelling_map: &Bound<'_, PyDict>,
+        case_insensitive: bool,
+        direction: &str,
+        rules: bool,
+        exceptions: Vec<String>,
+        vocabulary_path: Option<String>,
+        annotate: bool,
+    ) -> Result<Self, PyErr> {
+        let direction = SpellingDirection::from_str(direction)
+            .map_err(|e| PyErr::new::<PyValueError, _>(format!("{}", e)))?;
+        let pairs = spelling_map
+            .iter()
+            .map(|(target_word, alternative_spelling)| {
+                Ok((target_word.extract()?, alternative_spelling.extract()?))
+            })
+            .collect::<PyResult<Vec<(String, String)>>>()?;
+
+        Self::from_pairs(pairs, direction).with_py_options(
+            case_insensitive,
+            rules,
+            exceptions,
+            vocabulary_path,
+            annotate,
+        )
+    }
+
+    /// Returns every word with more than one counterpart
+    /// in the spelling map, along with its counterparts
+    #[pyo3(name = "conflicts")]
+    pub fn py_conflicts(&self) -> HashMap<String, Vec<String>> {
+        self.conflicts()
+    }
+
+    /// Checks a spelling map CSV, returning a list of
+    /// `{"line": 12, "kind": "cycle", "message": "..."}`
+    #[staticmethod]
+    #[pyo3(name = "validate")]
+    pub fn py_validate(py: Python, spelling_map_path: String) -> PyResult<Bound<PyAny>> {
+        let issues = Self::validate(Path::new(&spelling_map_path))
             .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))?;
-        Ok(Self { spelling_map })
+        Ok(pythonize(py, &issues)?)
+    }
+
+    /// Compiles a spelling map CSV into a memory-mapped dictionary,
+    /// which can be passed as `spelling_map_path` in place of the CSV
+    /// (in either direction)
+    #[staticmethod]
+    #[pyo3(name = "compile")]
+    pub fn py_compile(spelling_map_path: String, output_path: String) -> Result<(), PyErr> {
+        Self::compile(Path::new(&spelling_map_path), Path::new(&output_path))
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
     }
 }
 
 impl SpellingMapper {
-    fn load_spelling_map(path: PathBuf) -> Result<HashMap<String, String>, LibError> {
-        let mut reader = csv::Reader::from_path(path)
-            .map_err(|e| LibError::IO(format!("Failed to read spelling map: {}", e)))?;
+    /// Returns every word with more than one counterpart
+    /// in the spelling map, along with its counterparts
+    pub fn conflicts(&self) -> HashMap<String, Vec<String>> {
+        self.dictionary.load().conflicts()
+    }
 
-        let mut spelling_map = HashMap::new();
+    pub fn new(spelling_map_path: String) -> Result<Self, LibError> {
+        Self::new_with_direction(spelling_map_path, SpellingDirection::default())
+    }
 
-        for result in reader.records() {
-            let record =
-                result.map_err(|e| LibError::IO(format!("Failed to read record: {}", e)))?;
+    /// `spelling_map_path` is either a CSV or a dictionary created by `compile`
+    pub fn new_with_direction(
+        spelling_map_path: String,
+        direction: SpellingDirection,
+    ) -> Result<Self, LibError> {
+        let path = PathBuf::from(spelling_map_path);
+        let dictionary = SpellingDictionary::load(&path, direction)?;
 
-            let target_word = record
-                .get(0)
-                .expect("Failed to get target word")
-                .to_string();
+        let mut mapper = Self::from_dictionary(dictionary, direction);
+        mapper.path = Some(path);
+        Ok(mapper)
+    }
 
-            let alternative_spelling = record
-                .get(1)
-                .expect("Failed to get alternative spelling")
-                .to_string();
+    /// Spelling mapper using the spelling map embedded with the
+    /// `bundled-data` feature. The map is only parsed once per
+    /// direction, and shared by every mapper created this way
+    pub fn embedded(direction: SpellingDirection) -> Result<Self, LibError> {
+        #[cfg(feature = "bundled-data")]
+        {
+            use std::sync::OnceLock;
 
-            // NOTE: These are reversed intentionally
-            // as we want to look for keys in the map
-            // to then replace them with the values
-            spelling_map.insert(alternative_spelling, target_word);
+            static US_TO_UK: OnceLock<SpellingMapper> = OnceLock::new();
+            static UK_TO_US: OnceLock<SpellingMapper> = OnceLock::new();
+
+            let embedded = match direction {
+                SpellingDirection::UsToUk => &US_TO_UK,
+                SpellingDirection::UkToUs => &UK_TO_US,
+            };
+            Ok(embedded
+                .get_or_init(|| {
+                    let pairs = Self::read_spelling_map(
+                        EMBEDDED_SPELLING_MAP.as_bytes(),
+                        "spelling_map.csv",
+                    )
+                    .expect("Embedded spelling map is valid");
+                    Self::from_pairs(pairs, direction)
+                })
+                .clone())
         }
 
-        Ok(spelling_map)
+        #[cfg(not(feature = "bundled-data"))]
+        {
+            let _ = direction;
+            Err(LibError::InvalidInput(
+                "algoforge was built without the bundled-data feature".to_string(),
+            ))
+        }
+    }
+
+    /// Spelling mapper from (target, alternative_spelling) pairs,
+    /// e.g. generated at runtime, as if they were rows of the CSV
+    pub fn from_pairs(
+        pairs: impl IntoIterator<Item = (String, String)>,
+        direction: SpellingDirection,
+    ) -> Self {
+        let dictionary = SpellingDictionary::from_pairs(pairs.into_iter().collect(), direction);
+        Self::from_dictionary(dictionary, direction)
+    }
+
+    /// Compiles a spelling map CSV into a dictionary for `new`,
+    /// holding both directions along with the conflicts
+    pub fn compile(spelling_map_path: &Path, output_path: &Path) -> Result<(), LibError> {
+        let pairs = Self::load_spelling_map(spelling_map_path.to_path_buf())?;
+
+        let mut entries: BTreeMap<String, Entry> = BTreeMap::new();
+        let mut insert = |key: String, target: String| {
+            entries.insert(
+                key,
+                Entry {
+                    target: Some(target),
+                    flag: false,
+                },
+            );
+        };
+
+        for (word, counterparts) in Self::find_conflicts(&pairs) {
+            insert(
+                format!("{}{}", CONFLICT_PREFIX, word),
+                counterparts.join(&CONFLICT_SEPARATOR.to_string()),
+            );
+        }
+        // Later rows overwrite earlier ones, as in `new`
+        for (target_word, alternative_spelling) in pairs {
+            insert(
+                format!("{}{}", US_TO_UK_PREFIX, alternative_spelling),
+                target_word.clone(),
+            );
+            insert(
+                format!("{}{}", UK_TO_US_PREFIX, target_word),
+                alternative_spelling,
+            );
+        }
+
+        CompiledDictionary::write(output_path, DictionaryKind::Spelling, &entries)
+    }
+
+    fn from_dictionary(dictionary: SpellingDictionary, direction: SpellingDirection) -> Self {
+        Self {
+            dictionary: Arc::new(ArcSwap::from_pointee(dictionary)),
+            path: None,
+            watcher: None,
+            case_insensitive: false,
+            direction,
+            rules: None,
+            annotate: false,
+        }
+    }
+
+    /// Words with more than one counterpart in the
+    /// (target, alternative_spelling) pairs
+    fn find_conflicts(pairs: &[(String, String)]) -> HashMap<String, Vec<String>> {
+        let mut counterparts: HashMap<String, Vec<String>> = HashMap::new();
+
+        for (target_word, alternative_spelling) in pairs {
+            for (word, counterpart) in [
+                (target_word, alternative_spelling),
+                (alternative_spelling, target_word),
+            ] {
+                let entry = counterparts.entry(word.clone()).or_default();
+                if !entry.contains(counterpart) {
+                    entry.push(counterpart.clone());
+                }
+            }
+        }
+
+        counterparts.retain(|_, words| words.len() > 1);
+        counterparts
+    }
+
+    /// Applies the options shared by the python constructors
+    #[cfg(feature = "python")]
+    fn with_py_options(
+        self,
+        case_insensitive: bool,
+        rules: bool,
+        exceptions: Vec<String>,
+        vocabulary_path: Option<String>,
+        annotate: bool,
+    ) -> Result<Self, PyErr> {
+        if !rules && (!exceptions.is_empty() || vocabulary_path.is_some()) {
+            return Err(PyErr::new::<PyValueError, _>(
+                "exceptions and vocabulary_path only apply with rules=True",
+            ));
+        }
+
+        let mut mapper = self
+            .with_case_insensitive(case_insensitive)
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))?
+            .with_annotations(annotate);
+
+        if rules {
+            let vocabulary = vocabulary_path
+                .map(|path| SpellingRules::load_vocabulary(PathBuf::from(path)))
+                .transpose()
+                .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))?;
+            mapper = mapper.with_rules(exceptions, vocabulary);
+        }
+
+        Ok(mapper)
+    }
+
+    /// Enables rule-based conversion (-ize -> -ise, -or -> -our, ...)
+    /// for words missing from the spelling map
+    /// `exceptions` are added to the built-in exceptions list,
+    /// and if a `vocabulary` is provided, only conversions
+    /// resulting in a known word are accepted
+    pub fn with_rules(
+        mut self,
+        exceptions: Vec<String>,
+        vocabulary: Option<HashSet<String>>,
+    ) -> Self {
+        self.rules = Some(Arc::new(SpellingRules::new(
+            self.direction,
+            exceptions,
+            vocabulary,
+        )));
+        self
+    }
+
+    pub fn with_annotations(mut self, annotate: bool) -> Self {
+        self.annotate = annotate;
+        self
+    }
+
+    /// Compiled dictionaries are looked up with the lowercased
+    /// word, so their source CSV should be lowercase
+    ///
+    /// The lowercased dictionary isn't shared with earlier clones,
+    /// so a watcher set before is moved over to it
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Result<Self, LibError> {
+        if case_insensitive && !self.case_insensitive {
+            let lowered = self.dictionary.load().lowercased();
+            self.dictionary = Arc::new(ArcSwap::from_pointee(lowered));
+            self.case_insensitive = true;
+            let watch = self.watcher.is_some();
+            return self.with_watch(watch);
+        }
+        self.case_insensitive = case_insensitive;
+        Ok(self)
+    }
+
+    /// Reloads the spelling map whenever its file changes
+    pub fn with_watch(mut self, watch: bool) -> Result<Self, LibError> {
+        self.watcher = None;
+        if watch {
+            let path = self.path.clone().ok_or_else(|| {
+                LibError::InvalidInput(
+                    "Only spelling maps loaded from a file can be watched".to_string(),
+                )
+            })?;
+            let mapper = self.clone();
+            self.watcher = Some(Arc::new(FileWatcher::new(&path, move || mapper.reload())?));
+        }
+        Ok(self)
+    }
+
+    /// Reloads the spelling map from its file and swaps it in for
+    /// every clone of this mapper. Documents already being mapped
+    /// finish with the previous version. If the file can't be
+    /// loaded, the previous version stays in use
+    pub fn reload(&self) -> Result<(), LibError> {
+        let path = self.path.as_ref().ok_or_else(|| {
+            LibError::InvalidInput(
+                "Only spelling maps loaded from a file can be reloaded".to_string(),
+            )
+        })?;
+
+        let mut dictionary = SpellingDictionary::load(path, self.direction)?;
+        if self.case_insensitive {
+            dictionary = dictionary.lowercased();
+        }
+        self.dictionary.store(Arc::new(dictionary));
+        Ok(())
+    }
+
+    /// Returns the mapped word and, if it was replaced,
+    /// where the replacement came from
+    fn map_word<'a>(
+        &self,
+        dictionary: &SpellingDictionary,
+        word: Cow<'a, str>,
+    ) -> (Cow<'a, str>, Option<&'static str>) {
+        let lowered;
+        let key: &str = if self.case_insensitive {
+            lowered = word.to_lowercase();
+            &lowered
+        } else {
+            &word
+        };
+
+        let (replacement, source) =
+            if let Some(alternative_spelling) = dictionary.lookup(key, self.direction) {
+                (alternative_spelling.to_string(), "map")
+            } else if let Some(converted) = self.rules.as_ref().and_then(|r| r.convert(key)) {
+                (converted, "rule")
+            } else {
+                return (word, None);
+            };
+
+        if self.case_insensitive {
+            (
+                Cow::Owned(CasePattern::detect(&word).apply(&replacement)),
+                Some(source),
+            )
+        } else {
+            (Cow::Owned(replacement), Some(source))
+        }
+    }
+
+    fn map_token<'a>(&self, dictionary: &SpellingDictionary, token: Token<'a>) -> Token<'a> {
+        let Token { text, mut tags } = token;
+        let (text, source) = self.map_word(dictionary, text);
+        if let Some(source) = source {
+            tags.insert(SPELLING_TAG, source.to_string());
+        }
+        Token { text, tags }
+    }
+
+    /// Reads the spelling map as (target, alternative_spelling) pairs
+    /// in file order
+    fn load_spelling_map(path: PathBuf) -> Result<Vec<(String, String)>, LibError> {
+        let rows = dictionary::read_file_rows(&path, COLUMNS)?;
+        Ok(Self::parse_rows(rows)
+            .into_iter()
+            .map(|(_, target_word, alternative_spelling)| (target_word, alternative_spelling))
+            .collect())
+    }
+
+    #[cfg(feature = "bundled-data")]
+    fn read_spelling_map(
+        source: impl std::io::Read,
+        file: &str,
+    ) -> Result<Vec<(String, String)>, LibError> {
+        let rows = dictionary::read_rows(source, file, COLUMNS)?;
+        Ok(Self::parse_rows(rows)
+            .into_iter()
+            .map(|(_, target_word, alternative_spelling)| (target_word, alternative_spelling))
+            .collect())
+    }
+
+    /// (line, target, alternative_spelling) of every row
+    fn parse_rows(rows: Vec<(u64, csv::StringRecord)>) -> Vec<(u64, String, String)> {
+        rows.into_iter()
+            .map(|(line, record)| (line, record[0].to_string(), record[1].to_string()))
+            .collect()
+    }
+
+    /// Checks a spelling map CSV for duplicates, empty entries,
+    /// self-mappings and cycles (words that are converted back
+    /// to themselves), in line order.
+    /// None of these stop the map from loading
+    pub fn validate(spelling_map_path: &Path) -> Result<Vec<DictionaryIssue>, LibError> {
+        let rows = Self::parse_rows(dictionary::read_file_rows(spelling_map_path, COLUMNS)?);
+
+        let mut issues = Vec::new();
+        let mut pairs: HashMap<(&str, &str), u64> = HashMap::new();
+        // word -> (line, counterpart) where it was first listed
+        let mut targets: HashMap<&str, (u64, &str)> = HashMap::new();
+        let mut alternatives: HashMap<&str, (u64, &str)> = HashMap::new();
+        // alternative_spelling -> target_word, i.e. US -> UK
+        let mut conversions: BTreeMap<&str, Vec<(&str, u64)>> = BTreeMap::new();
+
+        for (line, target_word, alternative_spelling) in &rows {
+            let (line, target_word, alternative_spelling) =
+                (*line, target_word.as_str(), alternative_spelling.as_str());
+
+            if target_word.trim().is_empty() || alternative_spelling.trim().is_empty() {
+                issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::Empty,
+                    "Empty target or alternative spelling",
+                ));
+                continue;
+            }
+            if target_word == alternative_spelling {
+                issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::SelfMapping,
+                    format!("`{}` is mapped to itself", target_word),
+                ));
+                continue;
+            }
+            if let Some(first) = pairs.insert((target_word, alternative_spelling), line) {
+                issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::Duplicate,
+                    format!("Repeats line {}", first),
+                ));
+                continue;
+            }
+
+            match targets.get(target_word) {
+                Some((first, other)) => issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::Duplicate,
+                    format!(
+                        "`{}` is mapped to both `{}` (line {}) and `{}`, the last row wins for uk_to_us",
+                        target_word, other, first, alternative_spelling
+                    ),
+                )),
+                None => {
+                    targets.insert(target_word, (line, alternative_spelling));
+                }
+            }
+            match alternatives.get(alternative_spelling) {
+                Some((first, other)) => issues.push(DictionaryIssue::new(
+                    line,
+                    IssueKind::Duplicate,
+                    format!(
+                        "`{}` is mapped from both `{}` (line {}) and `{}`, the last row wins for us_to_uk",
+                        alternative_spelling, other, first, target_word
+                    ),
+                )),
+                None => {
+                    alternatives.insert(alternative_spelling, (line, target_word));
+                }
+            }
+
+            conversions
+                .entry(alternative_spelling)
+                .or_default()
+                .push((target_word, line));
+        }
+
+        for (line, cycle) in find_cycles(&conversions) {
+            issues.push(DictionaryIssue::new(
+                line,
+                IssueKind::Cycle,
+                format!("Spelling map cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+
+        issues.sort_by_key(|issue| issue.line);
+        Ok(issues)
+    }
+}
+
+/// Cycles in a graph of word -> (word, line) edges, each as the
+/// line of the edge closing it and the words along it
+fn find_cycles<'a>(edges: &BTreeMap<&'a str, Vec<(&'a str, u64)>>) -> Vec<(u64, Vec<&'a str>)> {
+    fn visit<'a>(
+        word: &'a str,
+        edges: &BTreeMap<&'a str, Vec<(&'a str, u64)>>,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+        cycles: &mut Vec<(u64, Vec<&'a str>)>,
+    ) {
+        path.push(word);
+        for (next, line) in edges.get(word).into_iter().flatten() {
+            if let Some(start) = path.iter().position(|w| w == next) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(next);
+                cycles.push((*line, cycle));
+            } else if !done.contains(next) {
+                visit(next, edges, path, done, cycles);
+            }
+        }
+        path.pop();
+        done.insert(word);
+    }
+
+    let mut done = HashSet::new();
+    let mut cycles = Vec::new();
+    for word in edges.keys() {
+        if !done.contains(word) {
+            visit(word, edges, &mut Vec::new(), &mut done, &mut cycles);
+        }
+    }
+    cycles
+}
+
+impl SpellingMapper {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("SpellingMapper", |params| {
+            let direction = SpellingDirection::from_str(&params.get::<String>("direction")?)?;
+            let mut mapper = match params.get::<Option<String>>("spelling_map_path")? {
+                Some(path) => Self::new_with_direction(path, direction)?,
+                None => Self::embedded(direction)?,
+            }
+            .with_case_insensitive(params.get("case_insensitive")?)?
+            .with_annotations(params.get("annotate")?);
+
+            let exceptions: Vec<String> = params.get("exceptions")?;
+            let vocabulary_path = params.get::<Option<String>>("vocabulary_path")?;
+            if params.get("rules")? {
+                let vocabulary = vocabulary_path
+                    .map(|path| SpellingRules::load_vocabulary(PathBuf::from(path)))
+                    .transpose()?;
+                mapper = mapper.with_rules(exceptions, vocabulary);
+            } else if !exceptions.is_empty() || vocabulary_path.is_some() {
+                return Err(LibError::Config(
+                    "exceptions and vocabulary_path only apply with rules=true".to_string(),
+                ));
+            }
+            mapper.with_watch(params.get("watch")?)
+        })
+        .with_description("Replaces US spellings with UK ones, or the other way around")
+        .with_param(ParamSpec::optional(
+            "spelling_map_path",
+            ParamKind::String,
+            Value::Null,
+            "CSV or compiled spelling map, the bundled one if unset",
+        ))
+        .with_param(ParamSpec::optional(
+            "case_insensitive",
+            ParamKind::Bool,
+            false,
+            "Look words up regardless of their casing",
+        ))
+        .with_param(ParamSpec::optional(
+            "direction",
+            ParamKind::String,
+            "us_to_uk",
+            "\"us_to_uk\" or \"uk_to_us\"",
+        ))
+        .with_param(ParamSpec::optional(
+            "rules",
+            ParamKind::Bool,
+            false,
+            "Convert words missing from the map with morphological rules",
+        ))
+        .with_param(ParamSpec::optional(
+            "exceptions",
+            ParamKind::StringList,
+            Value::Array(Vec::new()),
+            "Words the rules leave alone",
+        ))
+        .with_param(ParamSpec::optional(
+            "vocabulary_path",
+            ParamKind::String,
+            Value::Null,
+            "Only accept rule conversions resulting in a word of this list",
+        ))
+        .with_param(ParamSpec::optional(
+            "annotate",
+            ParamKind::Bool,
+            false,
+            "Tag replaced tokens with where the replacement came from",
+        ))
+        .with_param(ParamSpec::optional(
+            "watch",
+            ParamKind::Bool,
+            false,
+            "Reload the spelling map when its file changes",
+        ))
+        .with_inputs(&[DataType::ListStr, DataType::ListDict])
+        .with_output_fn(annotated_output)
+        .with_python::<Self>()
     }
 }
 
 impl Processor for SpellingMapper {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        // The whole document uses the same version of the dictionary
+        let dictionary = self.dictionary.load();
         match input {
+            Data::VecCowStr(v) if self.annotate => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|word| self.map_token(&dictionary, Token::new(word)))
+                    .collect(),
+            )),
             Data::VecCowStr(v) => Ok(Data::VecCowStr(
                 v.into_iter()
-                    .map(|word| match self.spelling_map.get(&word.to_string()) {
-                        Some(alternative_spelling) => Cow::Owned(alternative_spelling.to_string()),
-                        None => word,
-                    })
+                    .map(|word| self.map_word(&dictionary, word).0)
+                    .collect(),
+            )),
+            Data::VecToken(v) => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|token| self.map_token(&dictionary, token))
                     .collect(),
             )),
             _ => Err(LibError::InvalidInput(
-                "SpellingMapper only accepts Data::VecCowStr as input".to_string(),
+                "SpellingMapper only accepts Data::VecCowStr or Data::VecToken as input"
+                    .to_string(),
             )),
         }
     }
@@ -79,6 +852,7 @@ impl Processor for SpellingMapper {
             Data::VecCowStr(v) => {
                 serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
             }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
             _ => Err(LibError::InvalidInput(
                 "SpellingMapper will never output this type".to_string(),
             )),
@@ -120,15 +894,16 @@ mod tests {
         println!("File content:\n{}", file_content);
 
         let mapper = SpellingMapper::new(path).unwrap();
-        println!("Mapping contents: {:?}", mapper.spelling_map);
+        let dictionary = mapper.dictionary.load();
+        println!("Mapping contents: {:?}", dictionary.spelling_map);
 
         // Test specific mappings
         assert_eq!(
-            mapper.spelling_map.get("color"),
+            dictionary.spelling_map.get("color"),
             Some(&"colour".to_string())
         );
         assert_eq!(
-            mapper.spelling_map.get("flavor"),
+            dictionary.spelling_map.get("flavor"),
             Some(&"flavour".to_string())
         );
     }
@@ -248,4 +1023,378 @@ mod tests {
             panic!("Expected Data::VecCowStr");
         }
     }
+
+    #[test]
+    fn test_case_insensitive_restores_casing() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new(path)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let input = vec![
+            Cow::Borrowed("color"),
+            Cow::Borrowed("Color"),
+            Cow::Borrowed("COLOR"),
+            Cow::Borrowed("cOLoR"), // Mixed casing - replacement is used as-is
+            Cow::Borrowed("Flavor"),
+        ];
+
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+
+        if let Data::VecCowStr(output_vec) = result {
+            assert!(matches!(&output_vec[0], Cow::Owned(s) if s == "colour"));
+            assert!(matches!(&output_vec[1], Cow::Owned(s) if s == "Colour"));
+            assert!(matches!(&output_vec[2], Cow::Owned(s) if s == "COLOUR"));
+            assert!(matches!(&output_vec[3], Cow::Owned(s) if s == "colour"));
+            assert!(matches!(&output_vec[4], Cow::Borrowed(s) if *s == "Flavor"));
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_uk_to_us_direction() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\nflavour,flavor\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new_with_direction(path, SpellingDirection::UkToUs).unwrap();
+        let input = vec![Cow::Borrowed("colour"), Cow::Borrowed("color")];
+
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+
+        if let Data::VecCowStr(output_vec) = result {
+            assert!(matches!(&output_vec[0], Cow::Owned(s) if s == "color"));
+            assert!(matches!(&output_vec[1], Cow::Borrowed(s) if *s == "color"));
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_direction_parsing() {
+        assert_eq!(
+            SpellingDirection::from_str("uk_to_us").unwrap(),
+            SpellingDirection::UkToUs
+        );
+        assert_eq!(
+            SpellingDirection::from_str("US_TO_UK").unwrap(),
+            SpellingDirection::UsToUk
+        );
+        assert!(SpellingDirection::from_str("sideways").is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let csv_content = "target,alternative_spelling\r\nsnowplough,snowplow\r\nsnowploughs,snowplow\r\ncolour,color\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new_with_direction(path, SpellingDirection::UkToUs).unwrap();
+
+        let conflicts = mapper.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts.get("snowplow"),
+            Some(&vec!["snowplough".to_string(), "snowploughs".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_rule_fallback_with_annotations() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new(path)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap()
+            .with_rules(vec!["organize".to_string()], None)
+            .with_annotations(true);
+        let input = vec![
+            Cow::Borrowed("Color"),
+            Cow::Borrowed("Decentralization"),
+            Cow::Borrowed("organized"), // Listed as an exception
+            Cow::Borrowed("unchanged"),
+        ];
+
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+
+        if let Data::VecToken(output_vec) = result {
+            assert_eq!(output_vec[0].text, "Colour");
+            assert_eq!(output_vec[0].tag(SPELLING_TAG), Some("map"));
+            assert_eq!(output_vec[1].text, "Decentralisation");
+            assert_eq!(output_vec[1].tag(SPELLING_TAG), Some("rule"));
+            assert_eq!(output_vec[2].text, "organized");
+            assert_eq!(output_vec[2].tag(SPELLING_TAG), None);
+            assert!(matches!(&output_vec[3].text, Cow::Borrowed(s) if *s == "unchanged"));
+
+            assert_eq!(
+                mapper.to_json(&Data::VecToken(output_vec)).unwrap()[1],
+                serde_json::json!({"text": "Decentralisation", "spelling": "rule"})
+            );
+        } else {
+            panic!("Expected Data::VecToken");
+        }
+    }
+
+    #[test]
+    fn test_rules_disabled_by_default() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new(path).unwrap();
+        let input = vec![Cow::Borrowed("decentralization")];
+
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+
+        if let Data::VecCowStr(output_vec) = result {
+            assert!(matches!(&output_vec[0], Cow::Borrowed(s) if *s == "decentralization"));
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[test]
+    fn test_compiled_spelling_map() {
+        let csv_content = "target,alternative_spelling\r\nsnowplough,snowplow\r\nsnowploughs,snowplow\r\ncolour,color\r\n";
+        let (dir, path) = create_test_csv(csv_content);
+        let compiled_path = dir.path().join("spelling_map.afdict");
+        SpellingMapper::compile(Path::new(&path), &compiled_path).unwrap();
+        let compiled_path = compiled_path.to_string_lossy().to_string();
+
+        let mapper = SpellingMapper::new(compiled_path.clone())
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let input = vec![
+            Cow::Borrowed("Color"),
+            Cow::Borrowed("snowplow"),
+            Cow::Borrowed("colour"),
+        ];
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            // The last row wins, as with the CSV
+            assert_eq!(output_vec, vec!["Colour", "snowploughs", "colour"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        let mapper =
+            SpellingMapper::new_with_direction(compiled_path, SpellingDirection::UkToUs).unwrap();
+        let result = mapper
+            .process(Data::VecCowStr(vec![Cow::Borrowed("snowplough")]))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["snowplow"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        assert_eq!(
+            mapper.conflicts(),
+            HashMap::from([(
+                "snowplow".to_string(),
+                vec!["snowplough".to_string(), "snowploughs".to_string()]
+            )])
+        );
+    }
+
+    #[test]
+    fn test_compiled_lemma_map_rejected() {
+        let dir = TempDir::new().unwrap();
+        let lemma_csv = dir.path().join("lemma_map.csv");
+        std::fs::write(&lemma_csv, "lemma,derivatives\nbe,is\n").unwrap();
+        let compiled_path = dir.path().join("lemma_map.afdict");
+        crate::pipeline_components::Lemmatizer::compile(&lemma_csv, &compiled_path).unwrap();
+
+        assert!(SpellingMapper::new(compiled_path.to_string_lossy().to_string()).is_err());
+    }
+
+    #[test]
+    fn test_clones_share_map() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let mapper = SpellingMapper::new(path)
+            .unwrap()
+            .with_rules(Vec::new(), None);
+        let clone = mapper.clone().with_annotations(true);
+        assert!(Arc::ptr_eq(&mapper.dictionary, &clone.dictionary));
+        assert!(Arc::ptr_eq(
+            mapper.rules.as_ref().unwrap(),
+            clone.rules.as_ref().unwrap()
+        ));
+    }
+
+    #[cfg(feature = "bundled-data")]
+    #[test]
+    fn test_embedded() {
+        let input = vec![Cow::Borrowed("color"), Cow::Borrowed("organise")];
+
+        let mapper = SpellingMapper::embedded(SpellingDirection::UsToUk).unwrap();
+        let result = mapper
+            .process(Data::VecCowStr(input.clone()))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["colour", "organise"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        let mapper = SpellingMapper::embedded(SpellingDirection::UkToUs).unwrap();
+        let result = mapper
+            .process(Data::VecCowStr(input))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["color", "organize"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+    }
+
+    #[cfg(not(feature = "bundled-data"))]
+    #[test]
+    fn test_embedded_requires_feature() {
+        assert!(SpellingMapper::embedded(SpellingDirection::UsToUk).is_err());
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let pairs = [
+            ("colour".to_string(), "color".to_string()),
+            ("programme".to_string(), "program".to_string()),
+        ];
+
+        let mapper = SpellingMapper::from_pairs(pairs.clone(), SpellingDirection::UsToUk);
+        let result = mapper
+            .process(Data::VecCowStr(vec![Cow::Borrowed("program")]))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["programme"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        let mapper = SpellingMapper::from_pairs(pairs, SpellingDirection::UkToUs);
+        assert_eq!(
+            mapper.dictionary.load().spelling_map.get("colour"),
+            Some(&"color".to_string())
+        );
+    }
+
+    #[test]
+    fn test_malformed_row() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\nflavour\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let error = SpellingMapper::load_spelling_map(PathBuf::from(path)).unwrap_err();
+        assert!(error.to_string().contains("line 3: expected 2 columns"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let csv_content = "target,alternative_spelling\r\ncolour,color\r\ncolour,color\r\ngrey,gray\r\ngrey,grey\r\n,ax\r\nsnowplough,snowplow\r\nsnowploughs,snowplow\r\ngray,grey\r\n";
+        let (_dir, path) = create_test_csv(csv_content);
+
+        let issues = SpellingMapper::validate(Path::new(&path)).unwrap();
+        let kinds: Vec<_> = issues
+            .iter()
+            .map(|issue| (issue.line, issue.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (3, IssueKind::Duplicate),
+                (5, IssueKind::SelfMapping),
+                (6, IssueKind::Empty),
+                (8, IssueKind::Duplicate),
+                (9, IssueKind::Cycle),
+            ]
+        );
+        assert_eq!(
+            issues[4].message,
+            "Spelling map cycle: gray -> grey -> gray"
+        );
+    }
+
+    #[test]
+    fn test_reload() {
+        let (_dir, path) = create_test_csv("target,alternative_spelling\r\ncolour,color\r\n");
+        let mapper = SpellingMapper::new(path.clone())
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+        let clone = mapper.clone();
+
+        std::fs::write(
+            &path,
+            "target,alternative_spelling\r\ncolour,color\r\nflavour,FLAVOR\r\n",
+        )
+        .unwrap();
+        mapper.reload().unwrap();
+        let result = clone
+            .process(Data::VecCowStr(vec![
+                Cow::Borrowed("Color"),
+                Cow::Borrowed("Flavor"),
+            ]))
+            .expect("Failed to process input");
+        if let Data::VecCowStr(output_vec) = result {
+            assert_eq!(output_vec, vec!["Colour", "Flavour"]);
+        } else {
+            panic!("Expected Data::VecCowStr");
+        }
+
+        // Reloading a compiled map picks up the new file
+        let compiled_path = format!("{}.afdict", path);
+        SpellingMapper::compile(Path::new(&path), Path::new(&compiled_path)).unwrap();
+        let compiled = SpellingMapper::new(compiled_path.clone()).unwrap();
+        std::fs::write(&path, "target,alternative_spelling\r\nmould,mold\r\n").unwrap();
+        SpellingMapper::compile(Path::new(&path), Path::new(&compiled_path)).unwrap();
+        assert_eq!(
+            compiled
+                .dictionary
+                .load()
+                .lookup("mold", SpellingDirection::UsToUk),
+            None
+        );
+        compiled.reload().unwrap();
+        assert_eq!(
+            compiled
+                .dictionary
+                .load()
+                .lookup("mold", SpellingDirection::UsToUk),
+            Some("mould")
+        );
+    }
+
+    #[test]
+    fn test_watch_then_case_insensitive() {
+        let (_dir, path) = create_test_csv("target,alternative_spelling\r\ncolour,color\r\n");
+        let mapper = SpellingMapper::new(path.clone())
+            .unwrap()
+            .with_watch(true)
+            .unwrap()
+            .with_case_insensitive(true)
+            .unwrap();
+
+        std::fs::write(&path, "target,alternative_spelling\r\nflavour,flavor\r\n").unwrap();
+
+        // The watcher reloads the lowercased dictionary in use
+        mapper.watcher.as_ref().unwrap().trigger().unwrap();
+        let result = mapper
+            .process(Data::VecCowStr(vec![Cow::Borrowed("Flavor")]))
+            .expect("Failed to process input");
+        assert!(matches!(result, Data::VecCowStr(v) if v == vec!["Flavour"]));
+    }
 }
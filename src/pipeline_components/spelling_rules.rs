@@ -0,0 +1,485 @@
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use crate::error::LibError;
+
+use super::spelling_mapper::SpellingDirection;
+
+/// Words (base forms) that look like they follow
+/// one of the rules below but are spelled the same
+/// in both US and UK English
+const DEFAULT_EXCEPTIONS: &[&str] = &[
+    // -ize / -ise
+    "size",
+    "seize",
+    "prize",
+    "capsize",
+    "downsize",
+    "upsize",
+    "oversize",
+    "undersize",
+    "resize",
+    "outsize",
+    "supersize",
+    "maize",
+    "baize",
+    "assize",
+    "advertise",
+    "advise",
+    "apprise",
+    "arise",
+    "braise",
+    "bruise",
+    "chastise",
+    "circumcise",
+    "comprise",
+    "compromise",
+    "concise",
+    "cruise",
+    "demise",
+    "despise",
+    "devise",
+    "disguise",
+    "enterprise",
+    "excise",
+    "exercise",
+    "expertise",
+    "franchise",
+    "guise",
+    "improvise",
+    "incise",
+    "merchandise",
+    "mortise",
+    "noise",
+    "paradise",
+    "poise",
+    "praise",
+    "precise",
+    "premise",
+    "promise",
+    "raise",
+    "reprise",
+    "revise",
+    "rise",
+    "supervise",
+    "surmise",
+    "surprise",
+    "televise",
+    "treatise",
+    "valise",
+    "wise",
+    "sunrise",
+    "uprise",
+    "chemise",
+    "anise",
+    "cerise",
+    // UK verb whose US spelling is "practice"
+    "practise",
+    // Plurals of -sis nouns
+    "crises",
+    "analyses",
+    "paralyses",
+    "catalyses",
+    "hydrolyses",
+    // -or / -our
+    "donor",
+    "condor",
+    "vendor",
+    "dolor",
+    "amoral",
+    "amorally",
+    "humoral",
+    // -l / -ll
+    "exhale",
+    "inhale",
+    "impale",
+    "regale",
+    "compel",
+    "excel",
+    "propel",
+    "rebel",
+    "repel",
+    "dispel",
+    "expel",
+    "impel",
+    "install",
+    "appal",
+    "appall",
+    "befall",
+    "recall",
+    "enthral",
+    "enthrall",
+    "forestall",
+    "parallel",
+];
+
+/// Endings of base forms that are spelled the same in US
+/// and UK English whatever comes before them (noise,
+/// tortoise, appraise, otherwise). Endings shared with
+/// -ize verbs (-rise as in theorise, -mise as in minimise)
+/// can't be listed, those words are exceptions of their own
+const EXCEPTION_SUFFIXES: &[&str] = &["wise", "prise", "aise", "oise", "uise", "vise", "chise"];
+
+/// Words ending in -l after a vowel (which usually means a long
+/// sound, as in reveal) that still double it in UK English
+const DOUBLE_L_WORDS: &[&str] = &["dial", "duel", "fuel", "trial", "vial"];
+
+/// Stems (ending in -or) that take -our in British English
+const OUR_STEMS: &[&str] = &[
+    "avor", "abor", "umor", "onor", "olor", "avior", "ighbor", "arbor", "apor", "igor", "valor",
+    "rmor", "amor", "ndor", "rvor", "ncor", "rdor", "odor", "arlor",
+];
+
+#[derive(Debug, Clone, Copy)]
+enum RuleKind {
+    /// analyze -> analyse, realize -> realise
+    Ize,
+    /// color -> colour
+    Our,
+    /// traveling -> travelling
+    DoubleL,
+    /// defense -> defence
+    Ense,
+}
+
+/// A single US -> UK rule:
+/// `stem + us + ending` <-> `stem + uk + ending`
+#[derive(Debug)]
+struct SuffixRule {
+    kind: RuleKind,
+    us: &'static str,
+    uk: &'static str,
+    /// Appended to `stem + us` to get the base form
+    /// that exceptions are checked against
+    base_suffix: &'static str,
+    endings: &'static [&'static str],
+}
+
+const RULES: &[SuffixRule] = &[
+    SuffixRule {
+        kind: RuleKind::Ize,
+        us: "iz",
+        uk: "is",
+        base_suffix: "e",
+        endings: &[
+            "e", "es", "ed", "ing", "er", "ers", "ation", "ations", "ational", "able",
+        ],
+    },
+    SuffixRule {
+        kind: RuleKind::Ize,
+        us: "yz",
+        uk: "ys",
+        base_suffix: "e",
+        endings: &["e", "es", "ed", "ing", "er", "ers"],
+    },
+    SuffixRule {
+        kind: RuleKind::Our,
+        us: "or",
+        uk: "our",
+        base_suffix: "",
+        endings: &[
+            "", "s", "ed", "ing", "ful", "fully", "able", "ably", "ite", "ites", "hood", "hoods",
+            "ly", "less", "al", "ally", "er", "ers", "y",
+        ],
+    },
+    SuffixRule {
+        kind: RuleKind::DoubleL,
+        us: "l",
+        uk: "ll",
+        base_suffix: "",
+        endings: &["ing", "ed", "er", "ers"],
+    },
+    SuffixRule {
+        kind: RuleKind::Ense,
+        us: "ens",
+        uk: "enc",
+        base_suffix: "e",
+        endings: &["e", "es"],
+    },
+];
+
+/// Morphological US <-> UK spelling rules
+/// used as a fallback for words missing
+/// from the spelling map
+///
+/// Rules only produce a candidate, which is then
+/// rejected if the word is listed as an exception
+/// or (if a vocabulary is provided) if the candidate
+/// isn't a known word
+#[derive(Debug, Clone)]
+pub(crate) struct SpellingRules {
+    direction: SpellingDirection,
+    exceptions: HashSet<String>,
+    vocabulary: Option<HashSet<String>>,
+}
+
+impl SpellingRules {
+    pub(crate) fn new(
+        direction: SpellingDirection,
+        exceptions: impl IntoIterator<Item = String>,
+        vocabulary: Option<HashSet<String>>,
+    ) -> Self {
+        let exceptions = DEFAULT_EXCEPTIONS
+            .iter()
+            .map(|s| s.to_string())
+            .chain(exceptions.into_iter().map(|s| s.to_lowercase()))
+            .collect();
+
+        Self {
+            direction,
+            exceptions,
+            vocabulary,
+        }
+    }
+
+    /// Loads a vocabulary file with one word per line
+    pub(crate) fn load_vocabulary(path: PathBuf) -> Result<HashSet<String>, LibError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| LibError::IO(format!("Failed to read vocabulary: {}", e)))?;
+
+        Ok(content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    /// Returns the converted spelling of `word`
+    /// if one of the rules applies to it
+    pub(crate) fn convert(&self, word: &str) -> Option<String> {
+        RULES.iter().find_map(|rule| self.apply_rule(rule, word))
+    }
+
+    fn apply_rule(&self, rule: &SuffixRule, word: &str) -> Option<String> {
+        let (from, to) = match self.direction {
+            SpellingDirection::UsToUk => (rule.us, rule.uk),
+            SpellingDirection::UkToUs => (rule.uk, rule.us),
+        };
+
+        rule.endings.iter().find_map(|ending| {
+            let stem = word.strip_suffix(ending)?.strip_suffix(from)?;
+
+            // US -> UK: "travelled" ends in "l" + "ed" too
+            // but is already doubled
+            if matches!(rule.kind, RuleKind::DoubleL) && stem.ends_with('l') {
+                return None;
+            }
+
+            if !Self::stem_matches(rule, stem) {
+                return None;
+            }
+
+            let candidate = format!("{}{}{}", stem, to, ending);
+            let us_base = format!("{}{}{}", stem, rule.us, rule.base_suffix);
+            let uk_base = format!("{}{}{}", stem, rule.uk, rule.base_suffix);
+
+            let is_exception = [
+                word,
+                &candidate,
+                &us_base,
+                &uk_base,
+                &format!("{}e", us_base),
+            ]
+            .iter()
+            .any(|w| self.exceptions.contains(*w))
+                || [&us_base, &uk_base].iter().any(|base| {
+                    EXCEPTION_SUFFIXES
+                        .iter()
+                        .any(|suffix| base.ends_with(suffix))
+                });
+
+            if is_exception {
+                return None;
+            }
+
+            match &self.vocabulary {
+                Some(vocabulary) if !vocabulary.contains(&candidate) => None,
+                _ => Some(candidate),
+            }
+        })
+    }
+
+    fn stem_matches(rule: &SuffixRule, stem: &str) -> bool {
+        match rule.kind {
+            RuleKind::Ize => stem.chars().count() >= 2,
+            RuleKind::Our => {
+                let us_base = format!("{}{}", stem, rule.us);
+                OUR_STEMS.iter().any(|s| us_base.ends_with(s))
+            }
+            RuleKind::DoubleL => {
+                let us_base = format!("{}{}", stem, rule.us);
+                if DOUBLE_L_WORDS.iter().any(|w| us_base.ends_with(w)) {
+                    return true;
+                }
+
+                // Only unstressed -el / -al endings double in UK English
+                // (travel, model, signal), and a vowel right before
+                // them means a long sound (reveal, appeal)
+                let chars: Vec<char> = stem.chars().collect();
+                match chars.as_slice() {
+                    [.., before, 'e' | 'a'] => {
+                        let before_is_vowel = is_vowel(*before)
+                            && !(*before == 'u'
+                                && chars.len() > 2
+                                && chars[chars.len() - 3] == 'q');
+                        !before_is_vowel && vowel_groups(stem) >= 2
+                    }
+                    _ => false,
+                }
+            }
+            // defense, offense, pretense, license
+            // but not fence, sense, tense
+            RuleKind::Ense => {
+                (stem.ends_with('f') && stem.chars().count() >= 2)
+                    || stem == "pret"
+                    || stem == "lic"
+            }
+        }
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn vowel_groups(word: &str) -> usize {
+    let mut groups = 0;
+    let mut in_group = false;
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+        if vowel && !in_group {
+            groups += 1;
+        }
+        in_group = vowel;
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn us_to_uk() -> SpellingRules {
+        SpellingRules::new(SpellingDirection::UsToUk, Vec::new(), None)
+    }
+
+    fn uk_to_us() -> SpellingRules {
+        SpellingRules::new(SpellingDirection::UkToUs, Vec::new(), None)
+    }
+
+    #[test]
+    fn test_us_to_uk_rules() {
+        let rules = us_to_uk();
+        let cases = [
+            ("decentralization", Some("decentralisation")),
+            ("organizing", Some("organising")),
+            ("paralyzed", Some("paralysed")),
+            ("colorful", Some("colourful")),
+            ("neighborhood", Some("neighbourhood")),
+            ("traveling", Some("travelling")),
+            ("equaled", Some("equalled")),
+            ("defense", Some("defence")),
+            ("offenses", Some("offences")),
+            // Not affected by the rules
+            ("doctor", None),
+            ("appealing", None),
+            ("scaling", None),
+            ("travelling", None),
+            ("fence", None),
+            ("sense", None),
+            ("dialed", Some("dialled")),
+            ("refueling", Some("refuelling")),
+            ("paralleled", None),
+            ("valor", Some("valour")),
+            ("squalor", None),
+            ("amoral", None),
+            ("amorally", None),
+            ("humoral", None),
+        ];
+
+        for (word, expected) in cases {
+            assert_eq!(rules.convert(word).as_deref(), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_uk_to_us_rules() {
+        let rules = uk_to_us();
+        let cases = [
+            ("decentralisation", Some("decentralization")),
+            ("analysed", Some("analyzed")),
+            ("honours", Some("honors")),
+            ("cancelled", Some("canceled")),
+            ("defence", Some("defense")),
+            ("hour", None),
+            ("falling", None),
+            ("theorised", Some("theorized")),
+            ("minimise", Some("minimize")),
+            ("economising", Some("economizing")),
+            ("criticise", Some("criticize")),
+            ("summarise", Some("summarize")),
+            ("fuelled", Some("fueled")),
+        ];
+
+        for (word, expected) in cases {
+            assert_eq!(rules.convert(word).as_deref(), expected, "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let rules = uk_to_us();
+        // Default exceptions, matched on the base form
+        assert_eq!(rules.convert("advertising"), None);
+        assert_eq!(rules.convert("surprised"), None);
+        assert_eq!(rules.convert("otherwise"), None);
+        assert_eq!(rules.convert("rebelling"), None);
+
+        // Words the rules used to corrupt
+        for word in [
+            "sunrise",
+            "uprising",
+            "appraise",
+            "tortoise",
+            "porpoise",
+            "chemise",
+            "crises",
+            "analyses",
+            "practise",
+            "liaising",
+            "enterprises",
+            "revised",
+            "anise",
+            "cerise",
+            "catalyses",
+            "hydrolyses",
+        ] {
+            assert_eq!(rules.convert(word), None, "{}", word);
+        }
+
+        let rules = SpellingRules::new(
+            SpellingDirection::UsToUk,
+            vec!["Organize".to_string()],
+            None,
+        );
+        assert_eq!(rules.convert("organized"), None);
+        assert_eq!(rules.convert("exhaled"), None);
+    }
+
+    #[test]
+    fn test_no_duplicate_exceptions() {
+        let mut seen = HashSet::new();
+        for word in DEFAULT_EXCEPTIONS {
+            assert!(seen.insert(word), "{} is listed twice", word);
+        }
+    }
+
+    #[test]
+    fn test_vocabulary() {
+        let vocabulary = HashSet::from(["realise".to_string()]);
+        let rules = SpellingRules::new(SpellingDirection::UsToUk, Vec::new(), Some(vocabulary));
+
+        assert_eq!(rules.convert("realize").as_deref(), Some("realise"));
+        assert_eq!(rules.convert("organize"), None);
+    }
+}
@@ -0,0 +1,243 @@
+use std::{borrow::Cow, str::FromStr};
+
+#[cfg(feature = "python")]
+use pyo3::pyclass;
+use rust_stemmers::Algorithm;
+use serde_json::Value;
+
+use crate::{
+    error::LibError,
+    model::{Data, DataType, Token},
+    pipeline_builder::Processor,
+    registry::{ParamKind, ParamSpec, ProcessorSpec},
+};
+
+use super::lancaster;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StemmingAlgorithm {
+    /// Original Porter algorithm (English only)
+    Porter,
+    /// Snowball stemmers, Porter2 for English
+    Porter2,
+    /// Paice/Husk stemmer (English only), more aggressive
+    Lancaster,
+}
+
+impl FromStr for StemmingAlgorithm {
+    type Err = LibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "porter" => Ok(StemmingAlgorithm::Porter),
+            "porter2" | "snowball" => Ok(StemmingAlgorithm::Porter2),
+            "lancaster" => Ok(StemmingAlgorithm::Lancaster),
+            _ => Err(LibError::InvalidInput(format!(
+                "Unknown stemming algorithm: {} (expected \"porter\", \"porter2\" or \"lancaster\")",
+                s
+            ))),
+        }
+    }
+}
+
+/// Parses a language name or ISO 639-1 code
+/// into one of the supported Snowball languages
+fn parse_language(language: &str) -> Result<Algorithm, LibError> {
+    let algorithm = match language.to_lowercase().as_str() {
+        "ar" | "arabic" => Algorithm::Arabic,
+        "da" | "danish" => Algorithm::Danish,
+        "nl" | "dutch" => Algorithm::Dutch,
+        "en" | "english" => Algorithm::English,
+        "fi" | "finnish" => Algorithm::Finnish,
+        "fr" | "french" => Algorithm::French,
+        "de" | "german" => Algorithm::German,
+        "el" | "greek" => Algorithm::Greek,
+        "hu" | "hungarian" => Algorithm::Hungarian,
+        "it" | "italian" => Algorithm::Italian,
+        "no" | "nb" | "norwegian" => Algorithm::Norwegian,
+        "pt" | "portuguese" => Algorithm::Portuguese,
+        "ro" | "romanian" => Algorithm::Romanian,
+        "ru" | "russian" => Algorithm::Russian,
+        "es" | "spanish" => Algorithm::Spanish,
+        "sv" | "swedish" => Algorithm::Swedish,
+        "ta" | "tamil" => Algorithm::Tamil,
+        "tr" | "turkish" => Algorithm::Turkish,
+        _ => {
+            return Err(LibError::InvalidInput(format!(
+                "Unsupported stemming language: {}",
+                language
+            )))
+        }
+    };
+
+    Ok(algorithm)
+}
+
+/// Stemmer supporting multiple algorithms and languages
+///
+/// - `porter`: Porter, M.F. "An Algorithm for Suffix Stripping" (1980),
+///   via the `porter_stemmer` crate, English only
+/// - `porter2`: Snowball stemmers via the `rust-stemmers` crate,
+///   English (Porter2) and most major European languages
+/// - `lancaster`: Paice/Husk "Another Stemmer" (1990), English only
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone)]
+pub struct Stemmer {
+    algorithm: StemmingAlgorithm,
+    language: Algorithm,
+}
+
+impl Stemmer {
+    pub fn new(algorithm: &str, language: &str) -> Result<Self, LibError> {
+        let algorithm = StemmingAlgorithm::from_str(algorithm)?;
+        let language = parse_language(language)?;
+
+        if algorithm != StemmingAlgorithm::Porter2 && language != Algorithm::English {
+            return Err(LibError::InvalidInput(format!(
+                "{:?} stemming only supports English, use \"porter2\" for {:?}",
+                algorithm, language
+            )));
+        }
+
+        Ok(Self {
+            algorithm,
+            language,
+        })
+    }
+
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("Stemmer", |params| {
+            Self::new(
+                &params.get::<String>("algorithm")?,
+                &params.get::<String>("language")?,
+            )
+        })
+        .with_description(
+            "Stems every token with the Porter, Porter2 (Snowball) or Lancaster algorithm",
+        )
+        .with_param(ParamSpec::optional(
+            "algorithm",
+            ParamKind::String,
+            "porter2",
+            "\"porter\", \"porter2\" or \"lancaster\"",
+        ))
+        .with_param(ParamSpec::optional(
+            "language",
+            ParamKind::String,
+            "english",
+            "Snowball language, only English for the other algorithms",
+        ))
+        .with_inputs(&[DataType::ListStr, DataType::ListDict])
+        .with_python::<Self>()
+    }
+
+    fn stem_word<'a>(&self, snowball: &rust_stemmers::Stemmer, word: Cow<'a, str>) -> Cow<'a, str> {
+        match self.algorithm {
+            StemmingAlgorithm::Porter => Cow::Owned(porter_stemmer::stem(&word)),
+            StemmingAlgorithm::Lancaster => Cow::Owned(lancaster::stem(&word)),
+            // Snowball only allocates if the word changes
+            StemmingAlgorithm::Porter2 => match word {
+                Cow::Borrowed(s) => snowball.stem(s),
+                Cow::Owned(s) => match snowball.stem(&s) {
+                    Cow::Borrowed(_) => Cow::Owned(s),
+                    Cow::Owned(stemmed) => Cow::Owned(stemmed),
+                },
+            },
+        }
+    }
+}
+
+impl Processor for Stemmer {
+    fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+        let snowball = rust_stemmers::Stemmer::create(self.language);
+
+        match input {
+            Data::VecCowStr(v) => Ok(Data::VecCowStr(
+                v.into_iter()
+                    .map(|word| self.stem_word(&snowball, word))
+                    .collect(),
+            )),
+            Data::VecToken(v) => Ok(Data::VecToken(
+                v.into_iter()
+                    .map(|token| Token {
+                        text: self.stem_word(&snowball, token.text),
+                        tags: token.tags,
+                    })
+                    .collect(),
+            )),
+            _ => Err(LibError::InvalidInput(
+                "Stemmer only accepts Data::VecCowStr or Data::VecToken as input".to_string(),
+            )),
+        }
+    }
+
+    fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+        match data {
+            Data::VecCowStr(v) => {
+                serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string()))
+            }
+            Data::VecToken(v) => serde_json::to_value(v).map_err(|e| LibError::Json(e.to_string())),
+            _ => Err(LibError::InvalidInput(
+                "Stemmer will never output this type".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stem_all(stemmer: &Stemmer, words: &[&'static str]) -> Vec<Cow<'static, str>> {
+        let input = words.iter().map(|w| Cow::Borrowed(*w)).collect();
+        match stemmer.process(Data::VecCowStr(input)) {
+            Ok(Data::VecCowStr(output_vec)) => output_vec,
+            _ => panic!("Expected Data::VecCowStr"),
+        }
+    }
+
+    #[test]
+    fn test_algorithms_differ() {
+        let words = ["generously", "connections"];
+
+        let porter = Stemmer::new("porter", "english").unwrap();
+        let porter2 = Stemmer::new("porter2", "english").unwrap();
+        let lancaster = Stemmer::new("lancaster", "en").unwrap();
+
+        assert_eq!(stem_all(&porter, &words), vec!["gener", "connect"]);
+        assert_eq!(stem_all(&porter2, &words), vec!["generous", "connect"]);
+        assert_eq!(stem_all(&lancaster, &words), vec!["gen", "connect"]);
+    }
+
+    #[test]
+    fn test_languages() {
+        let french = Stemmer::new("porter2", "fr").unwrap();
+        assert_eq!(
+            stem_all(&french, &["travailleurs", "enfants"]),
+            vec!["travailleur", "enfant"]
+        );
+
+        let spanish = Stemmer::new("snowball", "spanish").unwrap();
+        assert_eq!(stem_all(&spanish, &["trabajadores"]), vec!["trabaj"]);
+
+        let german = Stemmer::new("porter2", "german").unwrap();
+        assert_eq!(stem_all(&german, &["kinder"]), vec!["kind"]);
+    }
+
+    #[test]
+    fn test_invalid_configuration() {
+        assert!(Stemmer::new("porter", "french").is_err());
+        assert!(Stemmer::new("lancaster", "de").is_err());
+        assert!(Stemmer::new("porter2", "klingon").is_err());
+        assert!(Stemmer::new("lovins", "english").is_err());
+    }
+
+    #[test]
+    fn test_unchanged_words_stay_borrowed() {
+        let stemmer = Stemmer::new("porter2", "english").unwrap();
+        let output_vec = stem_all(&stemmer, &["run", "running"]);
+
+        assert!(matches!(&output_vec[0], Cow::Borrowed(s) if *s == "run"));
+        assert!(matches!(&output_vec[1], Cow::Owned(s) if s == "run"));
+    }
+}
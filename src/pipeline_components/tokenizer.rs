@@ -1,17 +1,21 @@
 use std::borrow::Cow;
 
-use pyo3::{pyclass, pymethods};
+#[cfg(feature = "python")]
+use pyo3::pyclass;
 use serde_json::Value;
 
-use crate::{error::LibError, model::Data, pipeline_builder::Processor};
+use crate::{
+    error::LibError,
+    model::{Data, DataType},
+    pipeline_builder::Processor,
+    registry::ProcessorSpec,
+};
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone)]
 pub struct Tokenizer;
 
-#[pymethods]
 impl Tokenizer {
-    #[new]
     pub fn new() -> Self {
         Self
     }
@@ -26,6 +30,16 @@ impl Default for Tokenizer {
 /// Tokenizer is special in regards to lifetimes
 /// as it creates new owned strings
 /// therefore we're returning with 'static
+impl Tokenizer {
+    pub(crate) fn spec() -> ProcessorSpec {
+        ProcessorSpec::new("Tokenizer", |_| Ok(Self::new()))
+            .with_description("Splits the input on whitespace, trimming punctuation")
+            .with_inputs(&[DataType::Str])
+            .with_output(DataType::ListStr)
+            .with_python::<Self>()
+    }
+}
+
 impl Processor for Tokenizer {
     fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
         let discard_char_map: &[_] = &[' ', '\t', '\n', '\r', '\0', '.', ',', '!', '?', ';', ':'];
@@ -0,0 +1,362 @@
+//! Python bindings, built with the `python` feature
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use crossbeam::select;
+use pyo3::{
+    exceptions::{PyRuntimeError, PyStopIteration, PyTypeError, PyValueError},
+    pyclass, pyfunction, pymethods, pymodule,
+    types::{PyAnyMethods, PyModule, PyModuleMethods},
+    wrap_pyfunction, Bound, IntoPyObject, PyAny, PyErr, PyObject, PyRef, PyResult, Python,
+};
+use pythonize::{depythonize, pythonize};
+use rayon::ThreadPoolBuilder;
+use serde_json::{Map, Value};
+
+use crate::{
+    error::LibError,
+    logging,
+    model::{DataType, ProcessingRequest, ProcessingResult},
+    pipeline_builder::Pipeline,
+    process_batch_with,
+    registry::{ConfiguredProcessor, Registry},
+    scheduling::{BatchOptions, CancelToken},
+};
+use crossbeam::channel::Receiver;
+
+#[pyclass]
+pub struct ProcPipeline {
+    pipeline: Arc<Pipeline>,
+}
+
+#[pymethods]
+impl ProcPipeline {
+    #[new]
+    pub fn new() -> Self {
+        // Only the first pipeline sets up the global pool,
+        // nested pipelines are constructed the same way
+        let _ = ThreadPoolBuilder::new()
+            .num_threads(num_cpus::get())
+            .build_global();
+        Self {
+            pipeline: Arc::new(Pipeline::new()),
+        }
+    }
+
+    pub fn build_pipeline(&mut self, py: Python, processors: Vec<PyObject>) -> PyResult<()> {
+        self.pipeline = Arc::new(build_pipeline(py, processors)?);
+        Ok(())
+    }
+
+    /// Builds the pipeline from a JSON config file, or the config
+    /// itself as a list of stages, see `Registry::build_pipeline`
+    #[staticmethod]
+    pub fn from_config(config: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let registry = Registry::global();
+        let pipeline = if let Ok(path) = config.extract::<PathBuf>() {
+            registry.load_pipeline(&path)
+        } else {
+            let config: Value = depythonize(config)
+                .map_err(|e| PyErr::new::<PyValueError, _>(format!("Invalid config: {}", e)))?;
+            registry.build_pipeline(&config)
+        }
+        .map_err(|e| PyErr::new::<PyValueError, _>(format!("{}", e)))?;
+
+        let mut proc_pipeline = Self::new();
+        proc_pipeline.pipeline = Arc::new(pipeline);
+        Ok(proc_pipeline)
+    }
+
+    /// Per-stage and batch metrics, see `Pipeline::stats`
+    pub fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        pythonize(py, &self.pipeline.stats())
+            .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+    }
+
+    pub fn reset_stats(&self) {
+        self.pipeline.reset_stats();
+    }
+
+    #[pyo3(signature = (requests, chunk_size = 1, split_size = None, timeout = None))]
+    pub fn process(
+        &self,
+        _py: Python,
+        requests: Vec<(String, String, Option<Bound<'_, PyAny>>)>,
+        chunk_size: usize,
+        split_size: Option<usize>,
+        timeout: Option<f64>,
+    ) -> PyResult<ResultIterator> {
+        let timeout = timeout
+            .map(|seconds| {
+                Duration::try_from_secs_f64(seconds)
+                    .ok()
+                    .filter(|timeout| !timeout.is_zero())
+                    .ok_or_else(|| {
+                        PyErr::new::<PyValueError, _>(format!("Invalid timeout: {}", seconds))
+                    })
+            })
+            .transpose()?;
+
+        let requests = requests
+            .into_iter()
+            .map(|(id, input, metadata)| {
+                Ok(ProcessingRequest {
+                    id,
+                    input,
+                    metadata: metadata_from_py(metadata)?,
+                })
+            })
+            .collect::<PyResult<_>>()?;
+
+        let cancel_token = CancelToken::new();
+        let options = BatchOptions::new()
+            .with_chunk_size(chunk_size)
+            .with_split_size(split_size)
+            .with_timeout(timeout)
+            .with_cancel_token(cancel_token.clone());
+        let result_rx = process_batch_with(self.pipeline.clone(), requests, options);
+
+        Ok(ResultIterator {
+            receiver: result_rx,
+            cancel_token,
+        })
+    }
+}
+
+/// Builds a pipeline from a list of python processors
+/// `ProcPipeline`s in the list are nested as a single stage
+///
+/// Every stage is checked to accept the output of the previous
+/// one, the input of the first stage isn't known as the
+/// pipeline may end up nested in another one
+pub(crate) fn build_pipeline(py: Python, processors: Vec<PyObject>) -> PyResult<Pipeline> {
+    let registry = Registry::global();
+    let mut pipeline = Pipeline::new();
+    let mut data_type = DataType::Any;
+
+    for (index, processor_obj) in processors.into_iter().enumerate() {
+        let stage_error = |e: LibError| {
+            PyErr::new::<PyTypeError, _>(format!("Stage {}: {}", index + 1, e.message()))
+        };
+
+        if let Ok(nested) = processor_obj.extract::<PyRef<ProcPipeline>>(py) {
+            pipeline.add_processor(nested.pipeline.clone());
+            data_type = DataType::Any;
+        } else if let Ok(configured) = processor_obj.extract::<PyRef<ConfiguredProcessor>>(py) {
+            data_type = configured.check(registry, data_type).map_err(stage_error)?;
+            pipeline.add_processor(configured.processor(py));
+        } else if let Some((processor, spec)) = registry.extract_py(py, &processor_obj) {
+            data_type = registry
+                .check_py(&spec, processor.as_ref(), data_type)
+                .map_err(stage_error)?;
+            pipeline.add_boxed(processor);
+        } else {
+            let type_name = processor_obj
+                .getattr(py, "__class__")?
+                .getattr(py, "__name__")?
+                .extract::<String>(py)
+                .unwrap_or_else(|_| "Unknown".to_string());
+
+            return Err(PyErr::new::<PyTypeError, _>(format!(
+                "Invalid processor type: {}",
+                type_name
+            )));
+        }
+    }
+
+    Ok(pipeline)
+}
+
+impl Default for ProcPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a sub-pipeline (e.g. a `FanOut` branch) from
+/// either a `ProcPipeline` or a list of python processors
+pub(crate) fn pipeline_from_py(py: Python, obj: PyObject) -> PyResult<Arc<Pipeline>> {
+    if let Ok(nested) = obj.extract::<PyRef<ProcPipeline>>(py) {
+        return Ok(nested.pipeline.clone());
+    }
+
+    let pipeline = build_pipeline(py, obj.extract::<Vec<PyObject>>(py)?)?;
+    if pipeline.is_empty() {
+        return Err(PyErr::new::<PyValueError, _>(
+            "No processors provided for sub-pipeline",
+        ));
+    }
+
+    Ok(Arc::new(pipeline))
+}
+
+#[derive(Debug, Clone)]
+pub struct PyJsonValue(Value);
+
+impl<'py> IntoPyObject<'py> for &PyJsonValue {
+    type Target = PyAny;
+    type Output = Bound<'py, PyAny>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(pythonize(py, &self.0)?)
+    }
+}
+
+impl<'py> IntoPyObject<'py> for PyJsonValue {
+    type Target = PyAny;
+    type Output = Bound<'py, PyAny>;
+    type Error = PyErr;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        (&self).into_pyobject(py)
+    }
+}
+
+#[pymethods]
+impl ProcessingRequest {
+    #[new]
+    #[pyo3(signature = (id, input, metadata = None))]
+    pub fn new(id: String, input: String, metadata: Option<Bound<'_, PyAny>>) -> PyResult<Self> {
+        Ok(Self {
+            id,
+            input,
+            metadata: metadata_from_py(metadata)?,
+        })
+    }
+
+    #[getter]
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[setter]
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    #[getter]
+    fn input(&self) -> &str {
+        &self.input
+    }
+
+    #[setter]
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+    }
+
+    #[getter]
+    fn metadata(&self) -> PyJsonValue {
+        PyJsonValue(Value::Object(self.metadata.clone()))
+    }
+}
+
+/// Converts an optional python dict into a metadata map
+pub fn metadata_from_py(metadata: Option<Bound<'_, PyAny>>) -> PyResult<Map<String, Value>> {
+    match metadata {
+        Some(metadata) => Ok(depythonize(&metadata)?),
+        None => Ok(Map::new()),
+    }
+}
+
+#[pyclass]
+pub struct ResultItem {
+    #[pyo3(get)]
+    id: String,
+    #[pyo3(get)]
+    content: Option<PyJsonValue>,
+    /// Why processing failed, if it did
+    #[pyo3(get)]
+    error: Option<String>,
+    #[pyo3(get)]
+    attributes: PyJsonValue,
+    #[pyo3(get)]
+    metadata: PyJsonValue,
+}
+
+#[pyclass]
+pub struct ResultIterator {
+    pub receiver: Receiver<ProcessingResult>,
+    /// Cancels the batch, also done once the iterator is
+    /// dropped so it doesn't carry on with nobody listening
+    pub cancel_token: CancelToken,
+}
+
+impl Drop for ResultIterator {
+    fn drop(&mut self) {
+        self.cancel_token.cancel();
+    }
+}
+
+#[pymethods]
+impl ResultIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    /// Stops processing the batch, the iterator ends
+    /// right away, even if `__next__` is waiting in
+    /// another thread. Documents being processed
+    /// run to completion, their results are dropped
+    fn cancel(&self) {
+        self.cancel_token.cancel();
+    }
+
+    fn __next__(slf: PyRef<Self>) -> PyResult<Option<ResultItem>> {
+        let receiver = &slf.receiver;
+        let cancel_token = &slf.cancel_token;
+        if cancel_token.is_cancelled() {
+            return Err(PyStopIteration::new_err("Iterator cancelled"));
+        }
+
+        // Released while waiting, as stages running python
+        // code need the GIL to produce the next result
+        let result = slf.py().allow_threads(|| {
+            select! {
+                recv(receiver) -> result => result.ok(),
+                recv(cancel_token.receiver()) -> _ => None,
+            }
+        });
+        match result {
+            Some(result) => Ok(Some(ResultItem {
+                id: result.id,
+                content: result.content.map(PyJsonValue),
+                error: result.error,
+                attributes: PyJsonValue(Value::Object(result.attributes)),
+                metadata: PyJsonValue(Value::Object(result.metadata)),
+            })),
+            None => Err(PyStopIteration::new_err("Iterator exhausted")),
+        }
+    }
+}
+
+/// Descriptions of every processor that can be
+/// constructed by name, with their parameters
+#[pyfunction]
+fn describe_processors(py: Python) -> PyResult<Bound<PyAny>> {
+    pythonize(py, &Registry::global().describe())
+        .map_err(|e| PyErr::new::<PyRuntimeError, _>(format!("{}", e)))
+}
+
+/// Convert a serde_json::Value to a Python object
+/// This is needed for correct python interop
+pub fn serde_to_py<'a>(py: Python<'a>, value: &'a Value) -> PyResult<Bound<'a, PyAny>> {
+    pythonize(py, value).map_err(|e| {
+        PyErr::new::<PyRuntimeError, _>(format!(
+            "Failed to convert serde_json::Value to Python object: {}",
+            e
+        ))
+    })
+}
+
+#[pymodule]
+fn algoforge(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<ResultIterator>()?;
+    m.add_class::<ProcPipeline>()?;
+    m.add_class::<ConfiguredProcessor>()?;
+    Registry::global().add_classes(m)?;
+    m.add_class::<ProcessingRequest>()?;
+    m.add_function(wrap_pyfunction!(describe_processors, m)?)?;
+    m.add_function(wrap_pyfunction!(logging::setup_logging, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,989 @@
+#[cfg(feature = "python")]
+use std::any::Any;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::Path,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+#[cfg(feature = "python")]
+use pyo3::{
+    exceptions::{PyAttributeError, PyRuntimeError, PyValueError},
+    pyclass,
+    pyclass_init::PyClassInitializer,
+    pymethods,
+    types::{PyDict, PyModule, PyModuleMethods},
+    Bound, Py, PyClass, PyErr, PyObject, PyRef, PyResult, Python,
+};
+#[cfg(feature = "python")]
+use pythonize::depythonize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    error::LibError,
+    model::DataType,
+    pipeline_builder::{Pipeline, Processor},
+    pipeline_components::{
+        FanOut, JsonFields, LanguageDetector, Lemmatizer, PorterStemmer, PosTagger, PostProcessor,
+        PreProcessor, Router, SpellingCorrector, SpellingMapper, Stemmer, ToLowerCase, Tokenizer,
+    },
+};
+
+#[cfg(feature = "python")]
+use crate::pipeline_components::PyFunctionProcessor;
+
+/// Key naming the processor of a stage in a pipeline config,
+/// the other keys of the stage being its parameters
+const PROCESSOR_KEY: &str = "processor";
+
+/// Type of a processor parameter, checked before the factory is called
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Bool,
+    Int,
+    Float,
+    String,
+    StringList,
+    /// A pipeline config (a list of stages)
+    Pipeline,
+    /// Pipeline configs keyed by name
+    PipelineMap,
+    Any,
+}
+
+impl ParamKind {
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            ParamKind::Bool => value.is_boolean(),
+            ParamKind::Int => value.is_u64() || value.is_i64(),
+            ParamKind::Float => value.is_number(),
+            ParamKind::String => value.is_string(),
+            ParamKind::StringList => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+            ParamKind::Pipeline => value.is_array() || value.is_object(),
+            ParamKind::PipelineMap => value
+                .as_object()
+                .is_some_and(|map| map.values().all(|v| v.is_array() || v.is_object())),
+            ParamKind::Any => true,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ParamKind::Bool => "bool",
+            ParamKind::Int => "int",
+            ParamKind::Float => "float",
+            ParamKind::String => "str",
+            ParamKind::StringList => "list[str]",
+            ParamKind::Pipeline => "pipeline",
+            ParamKind::PipelineMap => "dict[str, pipeline]",
+            ParamKind::Any => "any",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParamSpec {
+    pub name: String,
+    pub kind: ParamKind,
+    /// Used when the parameter is missing, `None` if it's required
+    pub default: Option<Value>,
+    pub description: String,
+}
+
+impl ParamSpec {
+    pub fn required(name: &str, kind: ParamKind, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            default: None,
+            description: description.to_string(),
+        }
+    }
+
+    /// A `Value::Null` default makes the parameter nullable
+    pub fn optional(
+        name: &str,
+        kind: ParamKind,
+        default: impl Into<Value>,
+        description: &str,
+    ) -> Self {
+        Self {
+            default: Some(default.into()),
+            ..Self::required(name, kind, description)
+        }
+    }
+}
+
+/// Validated parameters of a processor, with defaults filled in
+pub struct Params<'r> {
+    processor: String,
+    values: Map<String, Value>,
+    registry: &'r Registry,
+}
+
+impl Params<'_> {
+    /// e.g. `params.get::<Option<String>>("vocabulary_path")`
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T, LibError> {
+        let value = self.values.get(name).cloned().unwrap_or(Value::Null);
+        serde_json::from_value(value).map_err(|e| self.invalid(name, &e.to_string()))
+    }
+
+    pub fn pipeline(&self, name: &str) -> Result<Arc<Pipeline>, LibError> {
+        self.opt_pipeline(name)?
+            .ok_or_else(|| self.invalid(name, "missing pipeline"))
+    }
+
+    pub fn opt_pipeline(&self, name: &str) -> Result<Option<Arc<Pipeline>>, LibError> {
+        match self.values.get(name) {
+            None | Some(Value::Null) => Ok(None),
+            Some(config) => self
+                .registry
+                .sub_pipeline(config)
+                .map(Some)
+                .map_err(|e| self.invalid(name, &e.message())),
+        }
+    }
+
+    pub fn pipelines(&self, name: &str) -> Result<BTreeMap<String, Arc<Pipeline>>, LibError> {
+        let Some(Value::Object(configs)) = self.values.get(name) else {
+            return Err(self.invalid(name, "expected a mapping of name to pipeline"));
+        };
+
+        configs
+            .iter()
+            .map(|(key, config)| {
+                let pipeline = self
+                    .registry
+                    .sub_pipeline(config)
+                    .map_err(|e| self.invalid(&format!("{}.{}", name, key), &e.message()))?;
+                Ok((key.clone(), pipeline))
+            })
+            .collect()
+    }
+
+    fn invalid(&self, name: &str, reason: &str) -> LibError {
+        LibError::Config(format!("{} parameter {}: {}", self.processor, name, reason))
+    }
+}
+
+type Factory = dyn Fn(&Params) -> Result<Box<dyn Processor>, LibError> + Send + Sync;
+type OutputFn = dyn Fn(&Params, DataType) -> DataType + Send + Sync;
+#[cfg(feature = "python")]
+type TypedFactory<P> = Arc<dyn Fn(&Params) -> Result<P, LibError> + Send + Sync>;
+#[cfg(feature = "python")]
+type PyExtractor = fn(Python, &PyObject) -> Option<Box<dyn Processor>>;
+#[cfg(feature = "python")]
+type PyRegistration = fn(&Bound<'_, PyModule>) -> PyResult<()>;
+#[cfg(feature = "python")]
+type PyConstructor = fn(&ProcessorSpec, Python, &Params) -> PyResult<PyObject>;
+
+/// Type of the output of a processor
+enum Output {
+    Fixed(DataType),
+    SameAsInput,
+    /// Depends on the parameters, e.g. `annotate`
+    Params(Box<OutputFn>),
+}
+
+/// Everything needed to construct, document and
+/// type check a processor, registered once
+/// ```ignore
+/// ProcessorSpec::new("Stemmer", |params| {
+///     Stemmer::new(&params.get::<String>("algorithm")?, &params.get::<String>("language")?)
+/// })
+/// .with_param(ParamSpec::optional("algorithm", ParamKind::String, "porter2", "..."))
+/// .with_inputs(&[DataType::ListStr, DataType::ListDict])
+/// ```
+pub struct ProcessorSpec {
+    name: String,
+    description: String,
+    params: Vec<ParamSpec>,
+    /// Accepts anything if empty
+    inputs: Vec<DataType>,
+    output: Output,
+    factory: Box<Factory>,
+    /// `factory` returning the concrete processor (a `TypedFactory`),
+    /// to construct it as its python class
+    #[cfg(feature = "python")]
+    typed_factory: Box<dyn Any + Send + Sync>,
+    /// Converts and constructs the python class
+    /// of the processor, if it has one
+    #[cfg(feature = "python")]
+    python: Option<(PyExtractor, PyRegistration, PyConstructor)>,
+}
+
+impl fmt::Debug for ProcessorSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProcessorSpec")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .field("inputs", &self.inputs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProcessorSpec {
+    pub fn new<P, F>(name: &str, factory: F) -> Self
+    where
+        P: Processor + 'static,
+        F: Fn(&Params) -> Result<P, LibError> + Send + Sync + 'static,
+    {
+        let factory = Arc::new(factory);
+        #[cfg(feature = "python")]
+        let typed_factory: TypedFactory<P> = factory.clone();
+
+        Self {
+            name: name.to_string(),
+            description: String::new(),
+            params: Vec::new(),
+            inputs: Vec::new(),
+            output: Output::SameAsInput,
+            factory: Box::new(move |params| Ok(Box::new(factory(params)?))),
+            #[cfg(feature = "python")]
+            typed_factory: Box::new(typed_factory),
+            #[cfg(feature = "python")]
+            python: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_param(mut self, param: ParamSpec) -> Self {
+        self.params.push(param);
+        self
+    }
+
+    pub fn with_inputs(mut self, inputs: &[DataType]) -> Self {
+        self.inputs = inputs.to_vec();
+        self
+    }
+
+    /// The output is the same type as the input unless set
+    pub fn with_output(mut self, output: DataType) -> Self {
+        self.output = Output::Fixed(output);
+        self
+    }
+
+    pub fn with_output_fn(
+        mut self,
+        output: impl Fn(&Params, DataType) -> DataType + Send + Sync + 'static,
+    ) -> Self {
+        self.output = Output::Params(Box::new(output));
+        self
+    }
+
+    /// Lets `ProcPipeline` accept the python class `T`, adds it
+    /// to the python module, and makes `Processor(name, **params)`
+    /// construct it, so its methods can be called from python.
+    /// `T` has to be the type the factory returns
+    #[cfg(feature = "python")]
+    pub fn with_python<T>(mut self) -> Self
+    where
+        T: PyClass + Processor + Clone + 'static,
+        PyClassInitializer<T>: From<T>,
+    {
+        fn extract<T: PyClass + Processor + Clone + 'static>(
+            py: Python,
+            obj: &PyObject,
+        ) -> Option<Box<dyn Processor>> {
+            let processor = obj.extract::<PyRef<T>>(py).ok()?;
+            Some(Box::new(processor.clone()))
+        }
+        fn add_class<T: PyClass>(m: &Bound<'_, PyModule>) -> PyResult<()> {
+            m.add_class::<T>()
+        }
+        fn construct<T>(spec: &ProcessorSpec, py: Python, params: &Params) -> PyResult<PyObject>
+        where
+            T: PyClass + 'static,
+            PyClassInitializer<T>: From<T>,
+        {
+            let factory = spec
+                .typed_factory
+                .downcast_ref::<TypedFactory<T>>()
+                .expect("Checked by with_python");
+            let processor = factory(params).map_err(to_py_err)?;
+            Ok(Py::new(py, processor)?.into_any())
+        }
+
+        assert!(
+            self.typed_factory.is::<TypedFactory<T>>(),
+            "The factory of {} doesn't return its python class",
+            self.name
+        );
+        self.python = Some((extract::<T>, add_class::<T>, construct::<T>));
+        self
+    }
+
+    /// Without the `python` feature there is no class to add
+    #[cfg(not(feature = "python"))]
+    pub fn with_python<T>(self) -> Self
+    where
+        T: Processor + Clone + 'static,
+    {
+        self
+    }
+
+    /// Type checks the stage, returning its output type
+    /// `params` are `None` for processors constructed from
+    /// their python class, in which case an output depending
+    /// on the parameters is unknown
+    fn check(&self, params: Option<&Params>, input: DataType) -> Result<DataType, LibError> {
+        if !self.inputs.is_empty() && !self.inputs.iter().any(|t| t.accepts(input)) {
+            let expected: Vec<String> = self.inputs.iter().map(|t| t.to_string()).collect();
+            return Err(LibError::Config(format!(
+                "{} does not accept {} (expected {})",
+                self.name,
+                input,
+                expected.join(" or ")
+            )));
+        }
+
+        Ok(match (&self.output, params) {
+            (Output::Fixed(output), _) => *output,
+            (Output::SameAsInput, _) => input,
+            (Output::Params(output), Some(params)) => output(params, input),
+            (Output::Params(_), None) => DataType::Any,
+        })
+    }
+
+    fn params<'r>(
+        &self,
+        registry: &'r Registry,
+        mut values: Map<String, Value>,
+    ) -> Result<Params<'r>, LibError> {
+        let invalid =
+            |reason: String| LibError::Config(format!("{} parameter {}", self.name, reason));
+
+        if let Some(unknown) = values
+            .keys()
+            .find(|key| !self.params.iter().any(|p| &p.name == *key))
+        {
+            let known: Vec<&str> = self.params.iter().map(|p| p.name.as_str()).collect();
+            return Err(invalid(format!(
+                "{} is unknown (expected one of: {})",
+                unknown,
+                known.join(", ")
+            )));
+        }
+
+        for param in &self.params {
+            match (values.get(&param.name), &param.default) {
+                (None, Some(default)) => {
+                    values.insert(param.name.clone(), default.clone());
+                }
+                (None, None) => return Err(invalid(format!("{} is required", param.name))),
+                (Some(Value::Null), Some(Value::Null)) => {}
+                (Some(value), _) if !param.kind.matches(value) => {
+                    return Err(invalid(format!(
+                        "{} should be {}, got {}",
+                        param.name,
+                        param.kind.as_str(),
+                        value
+                    )))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Params {
+            processor: self.name.clone(),
+            values,
+            registry,
+        })
+    }
+
+    /// JSON description of the processor and its parameters
+    pub fn describe(&self) -> Value {
+        let params: Vec<Value> = self
+            .params
+            .iter()
+            .map(|param| {
+                json!({
+                    "name": param.name,
+                    "type": param.kind.as_str(),
+                    "required": param.default.is_none(),
+                    "default": param.default,
+                    "description": param.description,
+                })
+            })
+            .collect();
+        let inputs: Vec<String> = self.inputs.iter().map(|t| t.to_string()).collect();
+
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "params": params,
+            "inputs": inputs,
+        })
+    }
+}
+
+/// Processors that can be constructed by name, from pipeline
+/// configs or python. The built-in processors are registered
+/// in `Registry::global`, which other crates can add theirs to
+#[derive(Debug, Default)]
+pub struct Registry {
+    specs: RwLock<BTreeMap<String, Arc<ProcessorSpec>>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry holding every built-in processor
+    pub fn with_builtins() -> Self {
+        let registry = Self::new();
+        for spec in [
+            PreProcessor::spec(),
+            PostProcessor::spec(),
+            LanguageDetector::spec(),
+            ToLowerCase::spec(),
+            Tokenizer::spec(),
+            SpellingMapper::spec(),
+            SpellingCorrector::spec(),
+            PosTagger::spec(),
+            Lemmatizer::spec(),
+            PorterStemmer::spec(),
+            Stemmer::spec(),
+            FanOut::spec(),
+            Router::spec(),
+            JsonFields::spec(),
+            #[cfg(feature = "python")]
+            PyFunctionProcessor::spec(),
+        ] {
+            registry
+                .register(spec)
+                .expect("Built-in processor names are unique");
+        }
+        registry
+    }
+
+    /// Registry used by the python bindings
+    pub fn global() -> &'static Registry {
+        static GLOBAL: OnceLock<Registry> = OnceLock::new();
+        GLOBAL.get_or_init(Self::with_builtins)
+    }
+
+    pub fn register(&self, spec: ProcessorSpec) -> Result<(), LibError> {
+        let mut specs = self.specs.write().unwrap_or_else(|e| e.into_inner());
+        if specs.contains_key(&spec.name) {
+            return Err(LibError::Config(format!(
+                "A processor named {} is already registered",
+                spec.name
+            )));
+        }
+        specs.insert(spec.name.clone(), Arc::new(spec));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<ProcessorSpec>> {
+        self.specs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+    }
+
+    fn specs(&self) -> Vec<Arc<ProcessorSpec>> {
+        self.specs
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    fn spec(&self, name: &str) -> Result<Arc<ProcessorSpec>, LibError> {
+        self.get(name)
+            .ok_or_else(|| LibError::Config(format!("Unknown processor: {}", name)))
+    }
+
+    /// Constructs a processor from its parameters
+    pub fn build(
+        &self,
+        name: &str,
+        params: Map<String, Value>,
+    ) -> Result<Box<dyn Processor>, LibError> {
+        let spec = self.spec(name)?;
+        let params = spec.params(self, params)?;
+        (spec.factory)(&params)
+    }
+
+    /// Output type of a processor given its parameters,
+    /// or an error if it doesn't accept `input`
+    pub fn check(
+        &self,
+        name: &str,
+        params: Map<String, Value>,
+        input: DataType,
+    ) -> Result<DataType, LibError> {
+        let spec = self.spec(name)?;
+        let params = spec.params(self, params)?;
+        spec.check(Some(&params), input)
+    }
+
+    /// Builds a pipeline from a config, either a list of stages or
+    /// an object with a `"processors"` list of stages, where every
+    /// stage names its processor along with its parameters:
+    /// `[{"processor": "Tokenizer"}, {"processor": "Stemmer", "language": "french"}]`
+    ///
+    /// Every stage is checked to accept the output of the previous one
+    pub fn build_pipeline(&self, config: &Value) -> Result<Pipeline, LibError> {
+        self.build_stages(config, DataType::Str)
+            .map(|(pipeline, _)| pipeline)
+    }
+
+    /// Reads a JSON pipeline config, see `build_pipeline`
+    pub fn load_pipeline(&self, path: &Path) -> Result<Pipeline, LibError> {
+        let config = std::fs::read_to_string(path)
+            .map_err(|e| LibError::IO(format!("Failed to read {}: {}", path.display(), e)))?;
+        let config: Value = serde_json::from_str(&config)
+            .map_err(|e| LibError::Json(format!("Invalid config {}: {}", path.display(), e)))?;
+        let pipeline = self.build_pipeline(&config)?;
+        tracing::info!(path = %path.display(), "Loaded pipeline");
+        Ok(pipeline)
+    }
+
+    /// Sub-pipelines (e.g. `FanOut` branches) may get any input
+    fn sub_pipeline(&self, config: &Value) -> Result<Arc<Pipeline>, LibError> {
+        let (pipeline, _) = self.build_stages(config, DataType::Any)?;
+        Ok(Arc::new(pipeline))
+    }
+
+    fn build_stages(
+        &self,
+        config: &Value,
+        input: DataType,
+    ) -> Result<(Pipeline, DataType), LibError> {
+        let stages = match config {
+            Value::Array(stages) => stages,
+            Value::Object(config) => match config.get("processors") {
+                Some(Value::Array(stages)) => stages,
+                _ => {
+                    return Err(LibError::Config(
+                        "Pipeline config needs a \"processors\" list".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(LibError::Config(
+                    "Pipeline config should be a list of processors".to_string(),
+                ))
+            }
+        };
+        if stages.is_empty() {
+            return Err(LibError::Config(
+                "No processors provided for pipeline".to_string(),
+            ));
+        }
+
+        let mut pipeline = Pipeline::new();
+        let mut data_type = input;
+        for (index, stage) in stages.iter().enumerate() {
+            let stage_error =
+                |e: LibError| LibError::Config(format!("Stage {}: {}", index + 1, e.message()));
+
+            let Value::Object(stage) = stage else {
+                return Err(stage_error(LibError::Config(format!(
+                    "expected an object with a \"{}\" key",
+                    PROCESSOR_KEY
+                ))));
+            };
+            let mut params = stage.clone();
+            let name = match params.remove(PROCESSOR_KEY) {
+                Some(Value::String(name)) => name,
+                _ => {
+                    return Err(stage_error(LibError::Config(format!(
+                        "missing \"{}\"",
+                        PROCESSOR_KEY
+                    ))))
+                }
+            };
+
+            let spec = self.spec(&name).map_err(stage_error)?;
+            let params = spec.params(self, params).map_err(stage_error)?;
+            data_type = spec.check(Some(&params), data_type).map_err(stage_error)?;
+            pipeline.add_boxed((spec.factory)(&params).map_err(stage_error)?);
+        }
+
+        Ok((pipeline, data_type))
+    }
+
+    /// Converts an instance of a processor's python class,
+    /// returning it with its spec for type checking
+    #[cfg(feature = "python")]
+    pub(crate) fn extract_py(
+        &self,
+        py: Python,
+        obj: &PyObject,
+    ) -> Option<(Box<dyn Processor>, Arc<ProcessorSpec>)> {
+        self.specs().into_iter().find_map(|spec| {
+            let (extract, _, _) = spec.python?;
+            extract(py, obj).map(|processor| (processor, spec.clone()))
+        })
+    }
+
+    /// Checks a processor constructed from its python class,
+    /// using the types it declares if it has any
+    #[cfg(feature = "python")]
+    pub(crate) fn check_py(
+        &self,
+        spec: &ProcessorSpec,
+        processor: &dyn Processor,
+        input: DataType,
+    ) -> Result<DataType, LibError> {
+        match processor.declared_types() {
+            Some((accepted, output)) if accepted.accepts(input) => Ok(output),
+            Some((accepted, _)) => Err(LibError::Config(format!(
+                "{} does not accept {} (expected {})",
+                spec.name, input, accepted
+            ))),
+            None => spec.check(None, input),
+        }
+    }
+
+    /// Adds the python class of every registered processor
+    #[cfg(feature = "python")]
+    pub(crate) fn add_classes(&self, m: &Bound<'_, PyModule>) -> PyResult<()> {
+        for spec in self.specs() {
+            if let Some((_, add_class, _)) = spec.python {
+                add_class(m)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Constructs a processor as its python class if it has one
+    #[cfg(feature = "python")]
+    fn build_py(&self, py: Python, name: &str, params: Map<String, Value>) -> PyResult<Instance> {
+        let spec = self.spec(name).map_err(to_py_err)?;
+        let params = spec.params(self, params).map_err(to_py_err)?;
+        match spec.python {
+            Some((extract, _, construct)) => {
+                Ok(Instance::Python(construct(&spec, py, &params)?, extract))
+            }
+            None => Ok(Instance::Rust(Arc::from(
+                (spec.factory)(&params).map_err(to_py_err)?,
+            ))),
+        }
+    }
+
+    /// JSON description of every registered processor
+    pub fn describe(&self) -> Value {
+        Value::Array(self.specs().iter().map(|spec| spec.describe()).collect())
+    }
+}
+
+/// Invalid parameters raise a `ValueError`,
+/// failing to load files a `RuntimeError`
+#[cfg(feature = "python")]
+fn to_py_err(e: LibError) -> PyErr {
+    match e {
+        LibError::Config(_) | LibError::InvalidInput(_) => {
+            PyErr::new::<PyValueError, _>(format!("{}", e))
+        }
+        _ => PyErr::new::<PyRuntimeError, _>(format!("{}", e)),
+    }
+}
+
+#[cfg(feature = "python")]
+#[derive(Debug)]
+enum Instance {
+    /// Instance of the processor's python class,
+    /// converted whenever it's added to a pipeline
+    Python(PyObject, PyExtractor),
+    Rust(Arc<dyn Processor>),
+}
+
+/// Any registered processor, constructed by name
+/// from python: `Processor("Stemmer", language="french")`
+///
+/// Processors with a python class are constructed as that class,
+/// whose methods (e.g. `reload`) can be called on the `Processor`
+#[cfg(feature = "python")]
+#[pyclass(name = "Processor")]
+#[derive(Debug)]
+pub struct ConfiguredProcessor {
+    name: String,
+    params: Map<String, Value>,
+    instance: Instance,
+}
+
+#[cfg(feature = "python")]
+#[pymethods]
+impl ConfiguredProcessor {
+    #[new]
+    #[pyo3(signature = (name, **params))]
+    pub fn py_new(py: Python, name: &str, params: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let params: Map<String, Value> = match params {
+            Some(params) => {
+                depythonize(params).map_err(|e| PyErr::new::<PyValueError, _>(format!("{}", e)))?
+            }
+            None => Map::new(),
+        };
+        let instance = Registry::global().build_py(py, name, params.clone())?;
+
+        Ok(Self {
+            name: name.to_string(),
+            params,
+            instance,
+        })
+    }
+
+    #[getter]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Only called for attributes `Processor` doesn't have
+    fn __getattr__(&self, py: Python, attribute: &str) -> PyResult<PyObject> {
+        match &self.instance {
+            Instance::Python(obj, _) => obj.getattr(py, attribute),
+            Instance::Rust(_) => Err(PyErr::new::<PyAttributeError, _>(format!(
+                "{} has no attribute {}",
+                self.name, attribute
+            ))),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Processor({:?}, {})",
+            self.name,
+            Value::Object(self.params.clone())
+        )
+    }
+}
+
+#[cfg(feature = "python")]
+impl ConfiguredProcessor {
+    pub(crate) fn processor(&self, py: Python) -> Arc<dyn Processor> {
+        match &self.instance {
+            Instance::Python(obj, extract) => {
+                Arc::from(extract(py, obj).expect("Constructed as the class extract converts"))
+            }
+            Instance::Rust(processor) => processor.clone(),
+        }
+    }
+
+    pub(crate) fn check(&self, registry: &Registry, input: DataType) -> Result<DataType, LibError> {
+        registry.check(&self.name, self.params.clone(), input)
+    }
+
+    /// Instance of the python class `T`, given either
+    /// directly or as a `Processor` constructing it
+    pub(crate) fn extract_class<T: PyClass + Clone>(py: Python, obj: &PyObject) -> Option<T> {
+        if let Ok(processor) = obj.extract::<PyRef<T>>(py) {
+            return Some(processor.clone());
+        }
+        match &obj.extract::<PyRef<Self>>(py).ok()?.instance {
+            Instance::Python(instance, _) => Some(instance.extract::<PyRef<T>>(py).ok()?.clone()),
+            Instance::Rust(_) => None,
+        }
+    }
+}
+
+/// Output of processors with an `annotate` parameter,
+/// which switches them to outputting annotated tokens
+pub(crate) fn annotated_output(params: &Params, input: DataType) -> DataType {
+    match params.get::<bool>("annotate") {
+        Ok(true) => DataType::ListDict,
+        _ => input,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Context, Data};
+
+    #[test]
+    fn test_build_pipeline() {
+        let registry = Registry::with_builtins();
+        let pipeline = registry
+            .build_pipeline(&json!({
+                "name": "stems",
+                "processors": [
+                    {"processor": "Tokenizer"},
+                    {"processor": "ToLowerCase"},
+                    {"processor": "Stemmer", "language": "english"}
+                ]
+            }))
+            .unwrap();
+
+        let result = pipeline
+            .process_to_json(
+                Data::OwnedStr("Connected Connections".to_string()),
+                &mut Context::default(),
+            )
+            .unwrap();
+        assert_eq!(result, json!(["connect", "connect"]));
+    }
+
+    #[test]
+    fn test_nested_pipelines() {
+        let registry = Registry::with_builtins();
+        let pipeline = registry
+            .build_pipeline(&json!([
+                {"processor": "FanOut", "branches": {
+                    "tokens": [{"processor": "Tokenizer"}],
+                    "stems": [{"processor": "Tokenizer"}, {"processor": "PorterStemmer"}]
+                }}
+            ]))
+            .unwrap();
+
+        let result = pipeline
+            .process_to_json(
+                Data::OwnedStr("connections".to_string()),
+                &mut Context::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            result,
+            json!({"tokens": ["connections"], "stems": ["connect"]})
+        );
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let registry = Registry::with_builtins();
+        let error = |config: Value| registry.build_pipeline(&config).unwrap_err().to_string();
+
+        assert!(error(json!([{"processor": "Unknown"}])).contains("Unknown processor: Unknown"));
+        assert!(
+            error(json!([{"processor": "Stemmer", "lang": "french"}])).contains("lang is unknown")
+        );
+        assert!(error(json!([{"processor": "Stemmer", "language": 1}]))
+            .contains("language should be str"));
+        assert!(error(json!([{"processor": "PosTagger"}])).contains("model_path is required"));
+        assert!(
+            error(json!([{"processor": "Tokenizer"}, {"processor": "Tokenizer"}]))
+                .contains("Stage 2: Tokenizer does not accept list[str] (expected str)")
+        );
+        assert!(error(json!({"stages": []})).contains("\"processors\" list"));
+        assert!(error(json!([{"processor": "Tokenizer"}, {
+            "processor": "SpellingMapper",
+            "spelling_map_path": "data/spelling_map.csv",
+            "exceptions": ["organize"]
+        }]))
+        .contains("only apply with rules=true"));
+    }
+
+    #[test]
+    fn test_output_types() {
+        let registry = Registry::with_builtins();
+        let check = |name: &str, params: Value, input: DataType| {
+            let Value::Object(params) = params else {
+                unreachable!()
+            };
+            registry.check(name, params, input)
+        };
+
+        assert_eq!(
+            check("Tokenizer", json!({}), DataType::Str).unwrap(),
+            DataType::ListStr
+        );
+        assert_eq!(
+            check("Stemmer", json!({}), DataType::ListDict).unwrap(),
+            DataType::ListDict
+        );
+        assert_eq!(
+            check("Lemmatizer", json!({"annotate": true}), DataType::ListStr).unwrap(),
+            DataType::ListDict
+        );
+        assert!(check("PorterStemmer", json!({}), DataType::Str).is_err());
+        // Unknown types (e.g. the output of a `Router`) aren't rejected
+        assert_eq!(
+            check("PorterStemmer", json!({}), DataType::Any).unwrap(),
+            DataType::ListStr
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        #[derive(Debug, Clone)]
+        struct Reverse;
+
+        impl Processor for Reverse {
+            fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+                match input {
+                    Data::OwnedStr(s) => Ok(Data::OwnedStr(s.chars().rev().collect())),
+                    _ => Err(LibError::InvalidInput(
+                        "Reverse only accepts str".to_string(),
+                    )),
+                }
+            }
+
+            fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+                data.to_value()
+            }
+        }
+
+        let registry = Registry::new();
+        let spec = || ProcessorSpec::new("Reverse", |_| Ok(Reverse)).with_inputs(&[DataType::Str]);
+        registry.register(spec()).unwrap();
+        assert!(registry.register(spec()).is_err());
+
+        let pipeline = registry
+            .build_pipeline(&json!([{"processor": "Reverse"}]))
+            .unwrap();
+        let result = pipeline
+            .process_to_json(Data::OwnedStr("abc".to_string()), &mut Context::default())
+            .unwrap();
+        assert_eq!(result, json!("cba"));
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_python_instance() {
+        use pyo3::types::PyDictMethods;
+        use std::borrow::Cow;
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let params = PyDict::new(py);
+            params.set_item("language", "french").unwrap();
+            let configured = ConfiguredProcessor::py_new(py, "Stemmer", Some(&params)).unwrap();
+
+            let processor = configured.processor(py);
+            let output = processor
+                .process(Data::VecCowStr(vec![Cow::Borrowed("enfants")]))
+                .unwrap();
+            assert_eq!(processor.to_json(&output).unwrap(), json!(["enfant"]));
+            assert!(configured
+                .__getattr__(py, "reload")
+                .unwrap_err()
+                .is_instance_of::<PyAttributeError>(py));
+
+            // Constructed as its python class
+            let obj = Py::new(py, configured).unwrap().into_any();
+            assert!(ConfiguredProcessor::extract_class::<Stemmer>(py, &obj).is_some());
+            assert!(ConfiguredProcessor::extract_class::<Lemmatizer>(py, &obj).is_none());
+
+            // Parameters are checked against the spec
+            params.set_item("algorithm", 2).unwrap();
+            let error = ConfiguredProcessor::py_new(py, "Stemmer", Some(&params)).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+        });
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    #[should_panic(expected = "doesn't return its python class")]
+    fn test_python_class_mismatch() {
+        let _ =
+            ProcessorSpec::new("Stemmer", |_| Ok(PorterStemmer::new())).with_python::<Stemmer>();
+    }
+}
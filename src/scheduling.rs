@@ -0,0 +1,820 @@
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossbeam::channel::{Receiver, Sender, TryRecvError};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::de::IgnoredAny;
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    error::LibError,
+    model::{Context, Data, ProcessingRequest, ProcessingResult},
+    pipeline_builder::Pipeline,
+};
+
+/// Stops a batch before all its requests are processed, e.g.
+/// when its results are no longer wanted. Clones share the state
+#[derive(Debug, Clone)]
+pub struct CancelToken {
+    /// Never sent on, dropped by `cancel` so
+    /// that waiting on `receiver` wakes up
+    sender: Arc<Mutex<Option<Sender<()>>>>,
+    receiver: Receiver<()>,
+}
+
+impl Default for CancelToken {
+    fn default() -> Self {
+        let (sender, receiver) = crossbeam::channel::bounded(0);
+        Self {
+            sender: Arc::new(Mutex::new(Some(sender))),
+            receiver,
+        }
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests not started yet are skipped, and the
+    /// results of those being processed are dropped
+    pub fn cancel(&self) {
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.receiver.try_recv(), Err(TryRecvError::Disconnected))
+    }
+
+    /// Becomes ready once cancelled, to `select!` on
+    pub(crate) fn receiver(&self) -> &Receiver<()> {
+        &self.receiver
+    }
+}
+
+/// How `process_batch` spreads requests over the rayon pool
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    chunk_size: usize,
+    split_size: Option<usize>,
+    timeout: Option<Duration>,
+    cancel_token: CancelToken,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: 1,
+            split_size: None,
+            timeout: None,
+            cancel_token: CancelToken::new(),
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimum number of requests processed by a single task,
+    /// so short documents (e.g. tweets) aren't dominated by
+    /// the overhead of scheduling them
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Documents longer than `split_size` bytes are split at
+    /// paragraph boundaries (blank lines) into parts of about
+    /// that size, processed in parallel and reassembled in order,
+    /// see `merge_outputs` and `merge_attributes`
+    ///
+    /// Only meant for plain text pipelines, JSON documents
+    /// (for `JsonFields`) are never split
+    pub fn with_split_size(mut self, split_size: Option<usize>) -> Self {
+        self.split_size = split_size.filter(|size| *size > 0);
+        self
+    }
+
+    /// Documents taking longer than `timeout` are reported as
+    /// failed instead of holding up the batch. Stages can't be
+    /// interrupted, so a document is given up on once the stage
+    /// running at the deadline returns, see `Pipeline::run`
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Token to cancel the batch with, dropping
+    /// its receiver cancels it as well
+    pub fn with_cancel_token(mut self, cancel_token: CancelToken) -> Self {
+        self.cancel_token = cancel_token;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn cancel_token(&self) -> &CancelToken {
+        &self.cancel_token
+    }
+}
+
+/// `process_request`, reporting a panicking stage on the
+/// result of the request rather than unwinding into the batch
+pub(crate) fn process_request_catching_panics(
+    pipeline: &Pipeline,
+    req: ProcessingRequest,
+    options: &BatchOptions,
+) -> ProcessingResult {
+    let id = req.id.clone();
+    let metadata = req.metadata.clone();
+    panic::catch_unwind(AssertUnwindSafe(|| process_request(pipeline, req, options)))
+        .unwrap_or_else(|panic| failed_result(id, metadata, panic_error(panic)))
+}
+
+/// Result of a request that couldn't be processed at all
+pub(crate) fn failed_result(
+    id: String,
+    metadata: Map<String, Value>,
+    error: LibError,
+) -> ProcessingResult {
+    tracing::warn!(id = %id, error = %error, "Request failed");
+
+    ProcessingResult {
+        id,
+        content: None,
+        error: Some(error.to_string()),
+        attributes: Map::new(),
+        metadata,
+    }
+}
+
+/// Error for a panic caught while processing a request
+pub(crate) fn panic_error(panic: Box<dyn Any + Send>) -> LibError {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    LibError::Unknown(format!("Processing panicked: {}", message))
+}
+
+/// Runs a single request through the pipeline, splitting it first
+/// if it's too long, within the timeout and cancel token of `options`
+pub(crate) fn process_request(
+    pipeline: &Pipeline,
+    req: ProcessingRequest,
+    options: &BatchOptions,
+) -> ProcessingResult {
+    let span = tracing::info_span!("request", id = %req.id);
+    let _entered = span.enter();
+
+    let parts = match options.split_size {
+        Some(split_size) if req.input.len() > split_size && !is_json(&req.input) => {
+            split_paragraphs(&req.input, split_size)
+        }
+        _ => Vec::new(),
+    };
+
+    // A failing document (e.g. a python stage raising)
+    // is reported on its result, the others carry on
+    let mut context = Context::new(req.metadata.clone())
+        .with_limits(options.timeout, Some(options.cancel_token.clone()));
+    let (content, attributes) = if parts.len() > 1 {
+        tracing::debug!(
+            parts = parts.len(),
+            bytes = req.input.len(),
+            "Splitting document"
+        );
+        process_parts(pipeline, &req.input, &parts, &context, &span)
+    } else {
+        let content = pipeline.process_to_json(Data::OwnedStr(req.input), &mut context);
+        (content, context.into_parts().0)
+    };
+    let (content, error) = match content {
+        Ok(content) => (Some(content), None),
+        Err(e) => {
+            tracing::warn!(error = %e, "Request failed");
+            (None, Some(e.to_string()))
+        }
+    };
+
+    ProcessingResult {
+        id: req.id,
+        content,
+        error,
+        attributes,
+        metadata: req.metadata,
+    }
+}
+
+/// Processes the parts of a document in parallel, each with
+/// a copy of `context`, returning the merged output and attributes
+fn process_parts(
+    pipeline: &Pipeline,
+    text: &str,
+    parts: &[&str],
+    context: &Context,
+    span: &tracing::Span,
+) -> (Result<Value, LibError>, Map<String, Value>) {
+    let outputs: Vec<(Result<Value, LibError>, Map<String, Value>)> = parts
+        .into_par_iter()
+        .map(|part| {
+            // Parts may run on other threads
+            let _entered = span.enter();
+            let mut context = context.clone();
+            let content = pipeline.process_to_json(Data::OwnedStr(part.to_string()), &mut context);
+            (content, context.into_parts().0)
+        })
+        .collect();
+
+    let mut part_attributes = Vec::with_capacity(outputs.len());
+    let mut contents = Vec::with_capacity(outputs.len());
+    for ((content, attributes), part) in outputs.into_iter().zip(parts) {
+        part_attributes.push((attributes, part.len()));
+        contents.push(content);
+    }
+
+    let gaps = gaps(text, parts);
+    let content = contents
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|outputs| merge_outputs(outputs, &gaps));
+    (content, merge_attributes(part_attributes))
+}
+
+/// Only objects are processed as JSON (by `JsonFields`),
+/// so only those are kept whole
+fn is_json(input: &str) -> bool {
+    input.trim_start().starts_with('{') && serde_json::from_str::<IgnoredAny>(input).is_ok()
+}
+
+/// Byte ranges of the paragraphs of `text`: runs of lines
+/// that aren't blank, without their last line break
+fn paragraphs(text: &str) -> Vec<(usize, usize)> {
+    let mut paragraphs = Vec::new();
+    let mut paragraph: Option<(usize, usize)> = None;
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let start = line_start;
+        line_start += line.len();
+        if line.trim().is_empty() {
+            paragraphs.extend(paragraph.take());
+            continue;
+        }
+        let end = start + line.trim_end_matches(['\r', '\n']).len();
+        paragraph = Some((paragraph.map_or(start, |(start, _)| start), end));
+    }
+    paragraphs.extend(paragraph);
+
+    paragraphs
+}
+
+/// Groups consecutive paragraphs into parts of at most `max_size`
+/// bytes, a single paragraph longer than that being a part of its own
+fn split_paragraphs(text: &str, max_size: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    // Byte range of the part being grown
+    let mut part: Option<(usize, usize)> = None;
+
+    for (start, end) in paragraphs(text) {
+        part = match part {
+            Some((part_start, part_end)) if end - part_start > max_size => {
+                parts.push(&text[part_start..part_end]);
+                Some((start, end))
+            }
+            Some((part_start, _)) => Some((part_start, end)),
+            None => Some((start, end)),
+        };
+    }
+    if let Some((start, end)) = part {
+        parts.push(&text[start..end]);
+    }
+
+    parts
+}
+
+/// Text around the parts of `text`: before the first
+/// one, between each of them and after the last one
+fn gaps<'a>(text: &'a str, parts: &[&str]) -> Vec<&'a str> {
+    let mut gaps = Vec::with_capacity(parts.len() + 1);
+    let mut gap_start = 0;
+    for part in parts {
+        let start = part.as_ptr() as usize - text.as_ptr() as usize;
+        gaps.push(&text[gap_start..start]);
+        gap_start = start + part.len();
+    }
+    gaps.push(&text[gap_start..]);
+
+    gaps
+}
+
+/// Reassembles the outputs of the parts of a document: lists are
+/// concatenated, strings joined with the text around the parts
+/// (`gaps`, the blank lines they were split at) and objects
+/// (e.g. the output of `FanOut`) merged key by key
+fn merge_outputs(outputs: Vec<Value>, gaps: &[&str]) -> Result<Value, LibError> {
+    match outputs.first() {
+        Some(Value::Array(_)) => {
+            let mut merged = Vec::new();
+            for output in outputs {
+                match output {
+                    Value::Array(items) => merged.extend(items),
+                    _ => return Err(merge_error()),
+                }
+            }
+            Ok(Value::Array(merged))
+        }
+        Some(Value::String(_)) => {
+            let mut merged = gaps.first().copied().unwrap_or_default().to_string();
+            for (index, output) in outputs.iter().enumerate() {
+                merged.push_str(output.as_str().ok_or_else(merge_error)?);
+                merged.push_str(gaps.get(index + 1).copied().unwrap_or_default());
+            }
+            Ok(Value::String(merged))
+        }
+        Some(Value::Object(_)) => {
+            let mut fields: Map<String, Value> = Map::new();
+            let mut values: Vec<(String, Vec<Value>)> = Vec::new();
+            for output in outputs {
+                let Value::Object(output) = output else {
+                    return Err(merge_error());
+                };
+                for (key, value) in output {
+                    match values.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, key_values)) => key_values.push(value),
+                        None => values.push((key, vec![value])),
+                    }
+                }
+            }
+            for (key, key_values) in values {
+                fields.insert(key, merge_outputs(key_values, gaps)?);
+            }
+            Ok(Value::Object(fields))
+        }
+        Some(_) if outputs.len() == 1 => Ok(outputs.into_iter().next().unwrap_or(Value::Null)),
+        _ => Err(merge_error()),
+    }
+}
+
+/// Merges the attributes recorded for the parts of a document,
+/// given with the length of their part, as if recorded for the
+/// whole document: a value recorded for every part is kept, numbers
+/// (e.g. the language confidence) are averaged weighted by the length
+/// of the parts and other values (e.g. the language) take the value
+/// recorded for most of the document
+fn merge_attributes(parts: Vec<(Map<String, Value>, usize)>) -> Map<String, Value> {
+    let mut values: Vec<(String, Vec<(Value, usize)>)> = Vec::new();
+    for (attributes, length) in parts {
+        for (key, value) in attributes {
+            match values.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, key_values)) => key_values.push((value, length)),
+                None => values.push((key, vec![(value, length)])),
+            }
+        }
+    }
+
+    values
+        .into_iter()
+        .map(|(key, key_values)| (key, merge_attribute(key_values)))
+        .collect()
+}
+
+fn merge_attribute(mut values: Vec<(Value, usize)>) -> Value {
+    if values.iter().all(|(value, _)| *value == values[0].0) {
+        return values.swap_remove(0).0;
+    }
+
+    if values.iter().all(|(value, _)| value.is_number()) {
+        let total: usize = values.iter().map(|(_, length)| length).sum();
+        let sum: f64 = values
+            .iter()
+            .map(|(value, length)| value.as_f64().unwrap_or(0.0) * *length as f64)
+            .sum();
+        if let Some(mean) = Number::from_f64(sum / total.max(1) as f64) {
+            return Value::Number(mean);
+        }
+    }
+
+    // Ties go to the value recorded first
+    let mut lengths: Vec<(Value, usize)> = Vec::new();
+    for (value, length) in values {
+        match lengths.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, total)) => *total += length,
+            None => lengths.push((value, length)),
+        }
+    }
+    let longest = lengths.iter().map(|(_, length)| *length).max();
+    lengths
+        .into_iter()
+        .find(|(_, length)| Some(*length) == longest)
+        .map_or(Value::Null, |(value, _)| value)
+}
+
+fn merge_error() -> LibError {
+    LibError::InvalidInput(
+        "Outputs of the parts of a split document can't be reassembled".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Instant,
+    };
+
+    use crossbeam::channel::RecvTimeoutError;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        pipeline_builder::Processor,
+        pipeline_components::{LanguageDetector, ToLowerCase, Tokenizer},
+        process_batch_with,
+    };
+
+    /// Sleeps for as many milliseconds as its input says,
+    /// counting the documents it processed
+    #[derive(Debug, Default)]
+    struct Sleep {
+        calls: Arc<AtomicUsize>,
+        /// Signalled when the pipeline holding it is dropped
+        dropped: Option<Sender<()>>,
+    }
+
+    impl Drop for Sleep {
+        fn drop(&mut self) {
+            if let Some(dropped) = &self.dropped {
+                let _ = dropped.send(());
+            }
+        }
+    }
+
+    impl Processor for Sleep {
+        fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+            if let Data::OwnedStr(millis) = &input {
+                let millis = millis.parse().unwrap_or(0);
+                std::thread::sleep(Duration::from_millis(millis));
+            }
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(input)
+        }
+
+        fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+            data.to_value()
+        }
+    }
+
+    fn sleep_pipeline() -> (Arc<Pipeline>, Arc<AtomicUsize>) {
+        sleep_stages(1)
+    }
+
+    /// `stages` sleeping stages sharing their call count
+    fn sleep_stages(stages: usize) -> (Arc<Pipeline>, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut pipeline = Pipeline::new();
+        for _ in 0..stages {
+            pipeline.add_processor(Sleep {
+                calls: calls.clone(),
+                dropped: None,
+            });
+        }
+        (Arc::new(pipeline), calls)
+    }
+
+    #[derive(Debug)]
+    struct Panicking;
+
+    impl Processor for Panicking {
+        fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+            match &input {
+                Data::OwnedStr(text) if text == "panic" => panic!("Unexpected input"),
+                _ => Ok(input),
+            }
+        }
+
+        fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+            data.to_value()
+        }
+    }
+
+    /// Every result of a batch, failing instead of hanging if it deadlocks
+    fn collect_results(results: Receiver<ProcessingResult>) -> Vec<ProcessingResult> {
+        let mut collected = Vec::new();
+        loop {
+            match results.recv_timeout(Duration::from_secs(10)) {
+                Ok(result) => collected.push(result),
+                Err(RecvTimeoutError::Disconnected) => return collected,
+                Err(RecvTimeoutError::Timeout) => panic!("Batch didn't complete"),
+            }
+        }
+    }
+
+    fn sleep_requests(count: usize, millis: u64) -> Vec<ProcessingRequest> {
+        (0..count)
+            .map(|i| ProcessingRequest {
+                id: i.to_string(),
+                input: millis.to_string(),
+                metadata: Map::new(),
+            })
+            .collect()
+    }
+
+    fn request(input: &str) -> ProcessingRequest {
+        ProcessingRequest {
+            id: "1".to_string(),
+            input: input.to_string(),
+            metadata: Map::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_paragraphs() {
+        let text = "one two\n\nthree\n\n\n\nfour five six\n\nseven";
+
+        assert_eq!(
+            split_paragraphs(text, 14),
+            vec!["one two\n\nthree", "four five six", "seven"]
+        );
+        // Paragraphs longer than the size aren't split further
+        assert_eq!(
+            split_paragraphs(text, 1),
+            vec!["one two", "three", "four five six", "seven"]
+        );
+        assert_eq!(split_paragraphs(text, 100), vec![text]);
+        assert!(split_paragraphs("\n\n", 1).is_empty());
+
+        // Lines of whitespace are blank too
+        let text = "one\r\ntwo\r\n\r\nthree\n  \t\nfour\r\n";
+        assert_eq!(
+            split_paragraphs(text, 1),
+            vec!["one\r\ntwo", "three", "four"]
+        );
+        assert_eq!(
+            gaps(text, &split_paragraphs(text, 1)),
+            vec!["", "\r\n\r\n", "\n  \t\n", "\r\n"]
+        );
+    }
+
+    #[test]
+    fn test_merge_outputs() {
+        let gaps = ["\n", "\r\n\r\n", ""];
+        assert_eq!(
+            merge_outputs(vec![json!(["a"]), json!(["b", "c"])], &gaps).unwrap(),
+            json!(["a", "b", "c"])
+        );
+        assert_eq!(
+            merge_outputs(vec![json!("a"), json!("b")], &gaps).unwrap(),
+            json!("\na\r\n\r\nb")
+        );
+        assert_eq!(
+            merge_outputs(
+                vec![
+                    json!({"tokens": ["a"], "text": "a"}),
+                    json!({"tokens": ["b"], "text": "b"})
+                ],
+                &gaps
+            )
+            .unwrap(),
+            json!({"tokens": ["a", "b"], "text": "\na\r\n\r\nb"})
+        );
+        assert!(merge_outputs(vec![json!(["a"]), json!("b")], &gaps).is_err());
+        assert!(merge_outputs(vec![json!(1), json!(2)], &gaps).is_err());
+    }
+
+    #[test]
+    fn test_merge_attributes() {
+        let part = |attributes: Value, length| match attributes {
+            Value::Object(attributes) => (attributes, length),
+            _ => unreachable!(),
+        };
+
+        let merged = merge_attributes(vec![
+            part(
+                json!({"language": "en", "confidence": 0.5, "source": "ocr"}),
+                10,
+            ),
+            part(
+                json!({"language": "fr", "confidence": 1.0, "source": "ocr"}),
+                20,
+            ),
+            part(json!({"language": "en", "confidence": 1.0, "pages": 3}), 5),
+            part(json!({"language": "de", "confidence": 0.0}), 5),
+        ]);
+        assert_eq!(
+            Value::Object(merged),
+            json!({"language": "fr", "confidence": 0.75, "source": "ocr", "pages": 3})
+        );
+
+        // Ties go to the first value
+        let merged = merge_attributes(vec![
+            part(json!({"language": "en"}), 10),
+            part(json!({"language": "fr"}), 10),
+        ]);
+        assert_eq!(merged["language"], "en");
+    }
+
+    #[test]
+    fn test_split_document() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(LanguageDetector::embedded());
+        pipeline.add_processor(Tokenizer::new());
+
+        let paragraph = "The quick brown fox jumps over the lazy dog.";
+        let text = [paragraph; 20].join("\n\n");
+        let options = BatchOptions::new().with_split_size(Some(100));
+
+        let split = process_request(&pipeline, request(&text), &options);
+        let whole = process_request(&pipeline, request(&text), &BatchOptions::new());
+
+        assert!(split.error.is_none());
+        assert_eq!(split.content, whole.content);
+        assert_eq!(split.attributes["language"], whole.attributes["language"]);
+    }
+
+    #[test]
+    fn test_split_document_attributes() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(LanguageDetector::embedded());
+        pipeline.add_processor(ToLowerCase::new());
+
+        // A short English cover page before a French report, with CRLF line breaks
+        let paragraph = "Le renard brun rapide saute par-dessus le chien paresseux.";
+        let text = format!(
+            "Annual Report\r\n\r\n{}\r\n",
+            [paragraph; 20].join("\r\n \r\n")
+        );
+        let options = BatchOptions::new().with_split_size(Some(100));
+
+        let split = process_request(&pipeline, request(&text), &options);
+        let whole = process_request(&pipeline, request(&text), &BatchOptions::new());
+
+        assert!(split_paragraphs(&text, 100).len() > 10);
+        assert_eq!(split.content, whole.content);
+        assert_eq!(split.attributes["language"], "fr");
+        assert_eq!(whole.attributes["language"], "fr");
+        let confidence = split.attributes["language_confidence"].as_f64().unwrap();
+        assert!(confidence > 0.5 && confidence < 1.0);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (pipeline, calls) = sleep_stages(100);
+        let options = BatchOptions::new().with_timeout(Some(Duration::from_millis(50)));
+
+        // Given up on after the stage running at the deadline
+        let start = Instant::now();
+        let slow = process_request(&pipeline, request("20"), &options);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(slow.error.as_deref(), Some("Timed out after 50ms"));
+        assert!(slow.content.is_none());
+        assert!(calls.load(Ordering::Relaxed) < 10);
+
+        let fast = process_request(&pipeline, request("0"), &options);
+        assert_eq!(fast.content, Some(json!("0")));
+    }
+
+    #[test]
+    fn test_split_timeout() {
+        let (pipeline, _) = sleep_stages(3);
+        let document = |millis: u64| ProcessingRequest {
+            input: vec![millis.to_string(); 8].join("\n\n"),
+            ..request("")
+        };
+        let options = |timeout| {
+            BatchOptions::new()
+                .with_split_size(Some(1))
+                .with_timeout(Some(Duration::from_millis(timeout)))
+        };
+
+        // More parts than threads, every one waiting on the others
+        let requests = vec![document(10); 2 * rayon::current_num_threads()];
+        let results = collect_results(process_batch_with(
+            pipeline.clone(),
+            requests.clone(),
+            options(10_000),
+        ));
+        assert_eq!(results.len(), requests.len());
+        assert!(results.iter().all(|result| result.error.is_none()));
+
+        let results = collect_results(process_batch_with(pipeline, requests, options(5)));
+        assert!(results
+            .iter()
+            .all(|result| result.error.as_deref() == Some("Timed out after 5ms")));
+    }
+
+    #[test]
+    fn test_batch_panic() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Panicking);
+        let requests = ["ok", "panic", "ok"]
+            .iter()
+            .enumerate()
+            .map(|(id, input)| ProcessingRequest {
+                id: id.to_string(),
+                ..request(input)
+            })
+            .collect();
+
+        let mut results = collect_results(process_batch_with(
+            Arc::new(pipeline),
+            requests,
+            BatchOptions::new(),
+        ));
+        results.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[1].error.as_deref(),
+            Some("Unknown error: Processing panicked: Unexpected input")
+        );
+        assert_eq!(results[2].content, Some(json!("ok")));
+    }
+
+    #[test]
+    fn test_cancel_between_stages() {
+        let (pipeline, calls) = sleep_stages(100);
+        let cancel_token = CancelToken::new();
+        let options = BatchOptions::new().with_cancel_token(cancel_token.clone());
+
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel_token.cancel();
+        });
+        let result = process_request(&pipeline, request("10"), &options);
+        canceller.join().unwrap();
+
+        assert_eq!(result.error.as_deref(), Some("Cancelled"));
+        assert!(calls.load(Ordering::Relaxed) < 50);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (pipeline, calls) = sleep_pipeline();
+        let cancel_token = CancelToken::new();
+        let options = BatchOptions::new().with_cancel_token(cancel_token.clone());
+
+        let results = process_batch_with(pipeline, sleep_requests(1000, 5), options);
+        results.recv().unwrap();
+        cancel_token.cancel();
+        assert!(cancel_token.is_cancelled());
+
+        // Ends once the requests being processed are done
+        assert!(results.iter().count() < 900);
+        assert!(calls.load(Ordering::Relaxed) < 900);
+    }
+
+    #[test]
+    fn test_cancel_on_drop() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (dropped_tx, dropped_rx) = crossbeam::channel::unbounded();
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Sleep {
+            calls: calls.clone(),
+            dropped: Some(dropped_tx),
+        });
+
+        let results = process_batch_with(
+            Arc::new(pipeline),
+            sleep_requests(1000, 5),
+            BatchOptions::new(),
+        );
+        results.recv().unwrap();
+        drop(results);
+
+        // The batch drops the pipeline once no document is being
+        // processed, after which the count can't change
+        dropped_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(calls.load(Ordering::Relaxed) < 900);
+    }
+
+    #[test]
+    fn test_json_not_split() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(ToLowerCase::new());
+
+        // Splitting would drop the empty paragraph
+        let input = "{\"title\": \"a\",\n\n\n\n\"body\": \"b\"}";
+        let options = BatchOptions::new().with_split_size(Some(5));
+        let result = process_request(&pipeline, request(input), &options);
+        assert_eq!(result.content, Some(json!(input)));
+
+        // Text only starting with a brace isn't JSON
+        let input = "{Draft}\n\nFirst PARAGRAPH\n\nSecond PARAGRAPH";
+        assert!(!is_json(input));
+        let result = process_request(&pipeline, request(input), &options);
+        assert_eq!(
+            result.content,
+            Some(json!("{draft}\n\nfirst paragraph\n\nsecond paragraph"))
+        );
+    }
+}
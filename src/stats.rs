@@ -0,0 +1,284 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::model::Data;
+
+/// Counters of a pipeline stage, updated concurrently
+/// by every thread processing documents
+#[derive(Debug)]
+pub(crate) struct StageCounters {
+    name: &'static str,
+    calls: AtomicU64,
+    errors: AtomicU64,
+    nanos: AtomicU64,
+    tokens_in: AtomicU64,
+    tokens_out: AtomicU64,
+}
+
+impl StageCounters {
+    pub(crate) fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            calls: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            nanos: AtomicU64::new(0),
+            tokens_in: AtomicU64::new(0),
+            tokens_out: AtomicU64::new(0),
+        }
+    }
+
+    /// `tokens_out` is `None` if the stage failed
+    pub(crate) fn record(&self, elapsed: Duration, tokens_in: u64, tokens_out: Option<u64>) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.nanos
+            .fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+        self.tokens_in.fetch_add(tokens_in, Ordering::Relaxed);
+        match tokens_out {
+            Some(tokens_out) => self.tokens_out.fetch_add(tokens_out, Ordering::Relaxed),
+            None => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn snapshot(&self) -> StageStats {
+        StageStats {
+            name: self.name.to_string(),
+            calls: self.calls.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            seconds: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)).as_secs_f64(),
+            tokens_in: self.tokens_in.load(Ordering::Relaxed),
+            tokens_out: self.tokens_out.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        for counter in [
+            &self.calls,
+            &self.errors,
+            &self.nanos,
+            &self.tokens_in,
+            &self.tokens_out,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct BatchCounters {
+    batches: u64,
+    documents: u64,
+    errors: u64,
+    bytes: u64,
+    elapsed: Duration,
+}
+
+/// Statistics collected by a pipeline, snapshotted by `Pipeline::stats`
+#[derive(Debug, Default)]
+pub(crate) struct PipelineCounters {
+    stages: Vec<StageCounters>,
+    /// Only updated once per batch, so a lock is cheap enough
+    batches: Mutex<BatchCounters>,
+}
+
+impl PipelineCounters {
+    pub(crate) fn add_stage(&mut self, name: &'static str) {
+        self.stages.push(StageCounters::new(name));
+    }
+
+    pub(crate) fn stage(&self, index: usize) -> &StageCounters {
+        &self.stages[index]
+    }
+
+    pub(crate) fn record_batch(&self, documents: u64, errors: u64, bytes: u64, elapsed: Duration) {
+        let mut batches = self.batches.lock().unwrap_or_else(|e| e.into_inner());
+        batches.batches += 1;
+        batches.documents += documents;
+        batches.errors += errors;
+        batches.bytes += bytes;
+        batches.elapsed += elapsed;
+    }
+
+    pub(crate) fn snapshot(&self) -> PipelineStats {
+        let batches = *self.batches.lock().unwrap_or_else(|e| e.into_inner());
+        let seconds = batches.elapsed.as_secs_f64();
+        let per_second = |count: u64| {
+            if seconds > 0.0 {
+                count as f64 / seconds
+            } else {
+                0.0
+            }
+        };
+
+        PipelineStats {
+            stages: self.stages.iter().map(StageCounters::snapshot).collect(),
+            batches: batches.batches,
+            documents: batches.documents,
+            errors: batches.errors,
+            bytes: batches.bytes,
+            seconds,
+            documents_per_second: per_second(batches.documents),
+            bytes_per_second: per_second(batches.bytes),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        for stage in &self.stages {
+            stage.reset();
+        }
+        *self.batches.lock().unwrap_or_else(|e| e.into_inner()) = BatchCounters::default();
+    }
+}
+
+/// Cumulative metrics of a single stage
+#[derive(Debug, Clone, Serialize)]
+pub struct StageStats {
+    pub name: String,
+    pub calls: u64,
+    pub errors: u64,
+    /// Summed over every thread, so it can exceed the wall time
+    pub seconds: f64,
+    pub tokens_in: u64,
+    pub tokens_out: u64,
+}
+
+/// Cumulative metrics of a pipeline since it was built or reset
+/// The batch metrics are only collected by `process_batch`
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineStats {
+    pub stages: Vec<StageStats>,
+    pub batches: u64,
+    pub documents: u64,
+    /// Documents whose result has `error` set
+    pub errors: u64,
+    /// Size of the inputs
+    pub bytes: u64,
+    /// Wall time spent processing batches
+    pub seconds: f64,
+    pub documents_per_second: f64,
+    pub bytes_per_second: f64,
+}
+
+/// Tokens for lists, words for strings, and
+/// nothing for JSON (e.g. the output of `FanOut`)
+pub(crate) fn token_count(data: &Data<'_>) -> u64 {
+    let count = match data {
+        Data::OwnedStr(s) => s.split_whitespace().count(),
+        Data::CowStr(s) => s.split_whitespace().count(),
+        Data::VecCowStr(v) => v.len(),
+        Data::VecToken(v) => v.len(),
+        Data::Json(_) => 0,
+    };
+    count as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use serde_json::{Map, Value};
+
+    use super::*;
+    use crate::{
+        error::LibError,
+        model::{Context, ProcessingRequest},
+        pipeline_builder::{Pipeline, Processor},
+        pipeline_components::{ToLowerCase, Tokenizer},
+        process_batch,
+    };
+
+    /// Fails on documents containing "fail"
+    #[derive(Debug)]
+    struct Failing;
+
+    impl Processor for Failing {
+        fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+            match &input {
+                Data::VecCowStr(tokens) if tokens.iter().any(|t| t == "fail") => {
+                    Err(LibError::InvalidInput("failed".to_string()))
+                }
+                _ => Ok(input),
+            }
+        }
+
+        fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+            data.to_value()
+        }
+    }
+
+    fn pipeline() -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Tokenizer::new());
+        pipeline.add_processor(ToLowerCase::new());
+        pipeline.add_processor(Failing);
+        pipeline
+    }
+
+    #[test]
+    fn test_stage_stats() {
+        let pipeline = pipeline();
+        for input in ["One Two Three", "fail now"] {
+            let _ = pipeline.run(Data::OwnedStr(input.to_string()), &mut Context::default());
+        }
+
+        let stats = pipeline.stats();
+        let names: Vec<&str> = stats.stages.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Tokenizer", "ToLowerCase", "Failing"]);
+
+        let tokenizer = &stats.stages[0];
+        assert_eq!((tokenizer.calls, tokenizer.errors), (2, 0));
+        assert_eq!((tokenizer.tokens_in, tokenizer.tokens_out), (5, 5));
+
+        let failing = &stats.stages[2];
+        assert_eq!((failing.calls, failing.errors), (2, 1));
+        assert_eq!((failing.tokens_in, failing.tokens_out), (5, 3));
+
+        pipeline.reset_stats();
+        let stats = pipeline.stats();
+        assert!(stats
+            .stages
+            .iter()
+            .all(|s| s.calls == 0 && s.seconds == 0.0));
+    }
+
+    #[test]
+    fn test_batch_stats() {
+        let pipeline = Arc::new(pipeline());
+        let requests: Vec<ProcessingRequest> = ["a b", "fail", "c"]
+            .iter()
+            .enumerate()
+            .map(|(i, input)| ProcessingRequest {
+                id: i.to_string(),
+                input: input.to_string(),
+                metadata: Map::new(),
+            })
+            .collect();
+
+        assert_eq!(process_batch(pipeline.clone(), requests).iter().count(), 3);
+        // Recorded once the last result is sent
+        let stats = (0..100)
+            .map(|_| {
+                std::thread::sleep(Duration::from_millis(5));
+                pipeline.stats()
+            })
+            .find(|stats| stats.batches == 1)
+            .unwrap();
+
+        assert_eq!((stats.documents, stats.errors, stats.bytes), (3, 1, 8));
+        assert!(stats.documents_per_second > 0.0);
+        assert_eq!(stats.stages[0].calls, 3);
+    }
+
+    #[test]
+    fn test_token_count() {
+        assert_eq!(token_count(&Data::OwnedStr(" a  b c ".to_string())), 3);
+        assert_eq!(token_count(&Data::VecCowStr(vec![Cow::Borrowed("a b")])), 1);
+        assert_eq!(token_count(&Data::Json(Value::Null)), 0);
+    }
+}
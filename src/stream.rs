@@ -0,0 +1,219 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    Stream, StreamExt,
+};
+
+use crate::{
+    model::{ProcessingRequest, ProcessingResult},
+    pipeline_builder::Pipeline,
+    scheduling::{process_request_catching_panics, BatchOptions},
+};
+
+/// Same buffering as the channel of `process_batch`
+const DEFAULT_MAX_IN_FLIGHT: usize = 100;
+
+/// Async counterpart of `process_batch`, for services running
+/// on an async runtime: requests are pulled from `requests` as
+/// results are consumed, and processed on the rayon pool
+/// ```ignore
+/// let results = process_stream(pipeline, requests.boxed())
+///     .with_max_in_flight(64)
+///     .collect::<Vec<_>>()
+///     .await;
+/// ```
+pub fn process_stream<S>(pipeline: Arc<Pipeline>, requests: S) -> ResultStream<S>
+where
+    S: Stream<Item = ProcessingRequest> + Unpin,
+{
+    let (result_tx, result_rx) = mpsc::unbounded();
+    ResultStream {
+        pipeline,
+        requests: Some(requests),
+        in_flight: 0,
+        max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+        result_tx,
+        result_rx,
+    }
+}
+
+/// Results in the order they finish processing, which
+/// may differ from the order of the requests
+///
+/// Doesn't need to be polled from any particular runtime,
+/// the processing itself never blocks the polling task
+pub struct ResultStream<S> {
+    pipeline: Arc<Pipeline>,
+    /// `None` once every request has been pulled
+    requests: Option<S>,
+    /// Requests pulled whose result hasn't been consumed yet
+    in_flight: usize,
+    max_in_flight: usize,
+    result_tx: UnboundedSender<ProcessingResult>,
+    result_rx: UnboundedReceiver<ProcessingResult>,
+}
+
+impl<S> ResultStream<S> {
+    /// Bounds the number of requests being processed or
+    /// waiting to be consumed, so a slow consumer
+    /// doesn't let results pile up in memory
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+}
+
+impl<S> Stream for ResultStream<S>
+where
+    S: Stream<Item = ProcessingRequest> + Unpin,
+{
+    type Item = ProcessingResult;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        while this.in_flight < this.max_in_flight {
+            let Some(requests) = this.requests.as_mut() else {
+                break;
+            };
+            match requests.poll_next_unpin(cx) {
+                Poll::Ready(Some(req)) => {
+                    let pipeline = this.pipeline.clone();
+                    let result_tx = this.result_tx.clone();
+                    rayon::spawn(move || {
+                        // A panic would abort the process, and the
+                        // request would be in flight forever otherwise
+                        let result = process_request_catching_panics(
+                            &pipeline,
+                            req,
+                            &BatchOptions::default(),
+                        );
+                        // Fails if the stream was dropped, the result isn't needed then
+                        let _ = result_tx.unbounded_send(result);
+                    });
+                    this.in_flight += 1;
+                }
+                Poll::Ready(None) => this.requests = None,
+                Poll::Pending => break,
+            }
+        }
+
+        // The stream holds a sender, so the receiver never ends on its own
+        match this.result_rx.poll_next_unpin(cx) {
+            Poll::Ready(Some(result)) => {
+                this.in_flight -= 1;
+                Poll::Ready(Some(result))
+            }
+            _ if this.in_flight == 0 && this.requests.is_none() => Poll::Ready(None),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::{executor::block_on, stream};
+    use serde_json::{json, Map, Value};
+
+    use super::*;
+    use crate::{
+        error::LibError, model::Data, pipeline_builder::Processor, pipeline_components::Tokenizer,
+    };
+
+    fn requests(count: usize) -> Vec<ProcessingRequest> {
+        (0..count)
+            .map(|i| ProcessingRequest {
+                id: i.to_string(),
+                input: format!("document {}", i),
+                metadata: Map::new(),
+            })
+            .collect()
+    }
+
+    fn pipeline() -> Arc<Pipeline> {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Tokenizer::new());
+        Arc::new(pipeline)
+    }
+
+    #[test]
+    fn test_process_stream() {
+        let mut results: Vec<ProcessingResult> = block_on(
+            process_stream(pipeline(), stream::iter(requests(50)))
+                .with_max_in_flight(8)
+                .collect(),
+        );
+        results.sort_by_key(|result| result.id.parse::<usize>().unwrap());
+
+        assert_eq!(results.len(), 50);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(result.content, Some(json!(["document", i.to_string()])));
+            assert!(result.error.is_none());
+        }
+    }
+
+    #[test]
+    fn test_bounded_buffering() {
+        let pulled = AtomicUsize::new(0);
+        let requests = stream::iter(requests(1000)).inspect(|_| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut results = process_stream(pipeline(), requests).with_max_in_flight(4);
+
+        assert!(block_on(results.next()).is_some());
+        assert!(pulled.load(Ordering::SeqCst) <= 4);
+
+        assert_eq!(block_on(results.count()), 999);
+        assert_eq!(pulled.load(Ordering::SeqCst), 1000);
+    }
+
+    #[derive(Debug)]
+    struct Panicking;
+
+    impl Processor for Panicking {
+        fn process<'a>(&self, input: Data<'a>) -> Result<Data<'a>, LibError> {
+            match input {
+                Data::OwnedStr(s) if s == "document 3" => panic!("unexpected input"),
+                input => Ok(input),
+            }
+        }
+
+        fn to_json(&self, data: &Data<'_>) -> Result<Value, LibError> {
+            data.to_value()
+        }
+    }
+
+    #[test]
+    fn test_panicking_stage() {
+        let mut pipeline = Pipeline::new();
+        pipeline.add_processor(Panicking);
+
+        let results: Vec<ProcessingResult> = block_on(
+            process_stream(Arc::new(pipeline), stream::iter(requests(5)))
+                .with_max_in_flight(2)
+                .collect(),
+        );
+
+        assert_eq!(results.len(), 5);
+        let failed: Vec<&ProcessingResult> = results.iter().filter(|r| r.error.is_some()).collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, "3");
+        assert_eq!(
+            failed[0].error.as_deref(),
+            Some("Unknown error: Processing panicked: unexpected input")
+        );
+    }
+
+    #[test]
+    fn test_empty_stream() {
+        let results = process_stream(pipeline(), stream::iter(Vec::new()));
+        assert_eq!(block_on(results.count()), 0);
+    }
+}